@@ -0,0 +1,490 @@
+//! Parse many files in parallel.
+//!
+//! [`parse_batch`] hands files to a pool of worker threads and passes each
+//! result back to the caller as soon as it is ready, so output can be streamed
+//! while the run continues. A file that fails to read or parse, or whose parser
+//! panics, becomes an error entry; it never stops the run.
+
+use crate::document_parsing::{
+    DocumentFormat, DocumentView, ParseError, ParseOptions, ParsedDocument, ParserRegistry,
+};
+use crate::tokenizer::Tokenizer;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{self, Read};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Default [`BatchConfig::max_bytes`]: 256 MiB.
+pub const DEFAULT_MAX_BYTES: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct BatchConfig {
+    /// Number of worker threads. Defaults to the available parallelism.
+    pub workers: usize,
+    /// Counts the tokens of each document into `metadata.token_count`.
+    pub tokenizer: Option<Arc<dyn Tokenizer>>,
+    /// Picks the parser of each file, as [`ParserRegistry::resolve`] does.
+    /// `None` uses [`ParserRegistry::global`].
+    pub registry: Option<Arc<ParserRegistry>>,
+    /// Parse every file as this format instead of detecting it.
+    pub format: Option<DocumentFormat>,
+    /// Files larger than this many bytes are not read and become `TooLarge`
+    /// errors. `None` reads files of any size.
+    pub max_bytes: Option<u64>,
+    /// Options passed to the parser of every file.
+    pub options: ParseOptions,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            workers: thread::available_parallelism().map_or(4, |n| n.get()),
+            tokenizer: None,
+            registry: None,
+            format: None,
+            max_bytes: Some(DEFAULT_MAX_BYTES),
+            options: ParseOptions::default(),
+        }
+    }
+}
+
+/// The outcome of parsing one file.
+#[derive(Debug)]
+pub struct BatchItem {
+    pub path: PathBuf,
+    /// Size of the file in bytes, or 0 if it could not be read.
+    pub bytes: usize,
+    pub result: Result<ParsedDocument, ParseError>,
+}
+
+impl BatchItem {
+    /// The item as one JSONL record.
+    ///
+    /// Successes carry `"status": "ok"` and the document as
+    /// [`ParsedDocument::view`] writes it. Failures carry `"status": "error"`
+    /// and the error kind and message.
+    pub fn to_record(&self, include_content: bool) -> Value {
+        let source = self.path.display().to_string();
+        let record = match &self.result {
+            Ok(doc) => Record {
+                source,
+                status: "ok",
+                document: Some(doc.view(include_content)),
+                error: None,
+            },
+            Err(err) => Record {
+                source,
+                status: "error",
+                document: None,
+                error: Some(ErrorRecord {
+                    kind: err.kind(),
+                    message: err.to_string(),
+                }),
+            },
+        };
+        serde_json::to_value(record).expect("batch records serialize to JSON")
+    }
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    source: String,
+    status: &'static str,
+    #[serde(flatten)]
+    document: Option<DocumentView<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ErrorRecord>,
+}
+
+#[derive(Serialize)]
+struct ErrorRecord {
+    kind: &'static str,
+    message: String,
+}
+
+/// Totals for a finished batch.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct BatchSummary {
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    /// Failure counts keyed by [`ParseError::kind`].
+    pub failures_by_kind: BTreeMap<String, usize>,
+    /// Bytes read across all files.
+    pub bytes: u64,
+    pub elapsed: Duration,
+}
+
+impl BatchSummary {
+    fn record(&mut self, item: &BatchItem) {
+        self.total += 1;
+        self.bytes += item.bytes as u64;
+        match &item.result {
+            Ok(_) => self.succeeded += 1,
+            Err(err) => {
+                self.failed += 1;
+                *self
+                    .failures_by_kind
+                    .entry(err.kind().to_string())
+                    .or_default() += 1;
+            }
+        }
+    }
+
+    pub fn files_per_second(&self) -> f64 {
+        self.total as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    pub fn bytes_per_second(&self) -> f64 {
+        self.bytes as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// Every regular file under `dir`, in path order, whatever its extension:
+/// formats are detected from content when the files are parsed, and files
+/// of no known format become error entries. Hidden files and directories
+/// are skipped, and a directory reached again through a symbolic link is
+/// walked only once.
+///
+/// A directory that cannot be read is returned with its error next to the
+/// files, and the walk goes on with the rest of the tree.
+pub fn collect_files(dir: impl AsRef<Path>) -> (Vec<PathBuf>, Vec<(PathBuf, io::Error)>) {
+    let mut files = Vec::new();
+    let mut errors = Vec::new();
+    walk(dir.as_ref(), &mut HashSet::new(), &mut files, &mut errors);
+    (files, errors)
+}
+
+fn walk(
+    dir: &Path,
+    visited: &mut HashSet<PathBuf>,
+    files: &mut Vec<PathBuf>,
+    errors: &mut Vec<(PathBuf, io::Error)>,
+) {
+    let entries = fs::canonicalize(dir).and_then(|canonical| {
+        if !visited.insert(canonical) {
+            return Ok(Vec::new());
+        }
+        fs::read_dir(dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<io::Result<Vec<_>>>()
+    });
+    let mut entries = match entries {
+        Ok(entries) => entries,
+        Err(err) => {
+            errors.push((dir.to_path_buf(), err));
+            return;
+        }
+    };
+    entries.sort();
+
+    for path in entries {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if name.starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            walk(&path, visited, files, errors);
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+}
+
+/// Parse `paths` on `config.workers` threads, calling `on_item` on the calling
+/// thread for each file as it finishes. Items arrive in completion order, not
+/// input order.
+pub fn parse_batch(
+    paths: &[PathBuf],
+    config: &BatchConfig,
+    mut on_item: impl FnMut(BatchItem),
+) -> BatchSummary {
+    let started = Instant::now();
+    let mut summary = BatchSummary::default();
+    let workers = config.workers.clamp(1, paths.len().max(1));
+    let next = AtomicUsize::new(0);
+
+    thread::scope(|scope| {
+        let (tx, rx) = mpsc::sync_channel(workers * 2);
+        for _ in 0..workers {
+            let tx = tx.clone();
+            let next = &next;
+            scope.spawn(move || {
+                while let Some(path) = paths.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    if tx.send(parse_file(path, config)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        // Only the workers hold senders now, so the loop ends when they do.
+        drop(tx);
+
+        for item in rx {
+            summary.record(&item);
+            on_item(item);
+        }
+    });
+
+    summary.elapsed = started.elapsed();
+    summary
+}
+
+fn parse_file(path: &Path, config: &BatchConfig) -> BatchItem {
+    let bytes = match read_file(path, config.max_bytes) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            return BatchItem {
+                path: path.to_path_buf(),
+                bytes: 0,
+                result: Err(err),
+            }
+        }
+    };
+
+    let name = path.file_name().and_then(|n| n.to_str());
+    let global;
+    let registry = match &config.registry {
+        Some(registry) => registry.as_ref(),
+        None => {
+            global = ParserRegistry::read_global();
+            &*global
+        }
+    };
+    let parser = match config.format {
+        Some(format) => registry.get(format.as_str()),
+        None => registry.resolve(&bytes, name),
+    };
+    let mut result = match parser {
+        Some(parser) => panic::catch_unwind(AssertUnwindSafe(|| {
+            parser.parse_with_options(&bytes, &config.options)
+        }))
+        .unwrap_or_else(|payload| Err(ParseError::Format(panic_message(&payload)))),
+        None => Err(ParseError::UnsupportedFormat(path.display().to_string())),
+    };
+    if let (Ok(doc), Some(tokenizer)) = (&mut result, &config.tokenizer) {
+        doc.count_tokens(tokenizer.as_ref());
+    }
+
+    BatchItem {
+        path: path.to_path_buf(),
+        bytes: bytes.len(),
+        result,
+    }
+}
+
+/// The content of `path`, refusing files over `max_bytes` before reading
+/// them, and files that grow past it while being read.
+fn read_file(path: &Path, max_bytes: Option<u64>) -> Result<Vec<u8>, ParseError> {
+    let file = fs::File::open(path)?;
+    let size = file.metadata()?.len();
+    let limit = max_bytes.unwrap_or(u64::MAX);
+    if size > limit {
+        return Err(ParseError::TooLarge { size, limit });
+    }
+
+    let mut bytes = Vec::with_capacity(size as usize);
+    file.take(limit.saturating_add(1)).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > limit {
+        return Err(ParseError::TooLarge {
+            size: bytes.len() as u64,
+            limit,
+        });
+    }
+    Ok(bytes)
+}
+
+fn panic_message(payload: &Box<dyn std::any::Any + Send>) -> String {
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown error");
+    format!("parser panicked: {}", message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document_parsing::DocumentFormat;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_collect_files() {
+        let (files, errors) = collect_files("test_files");
+        assert!(errors.is_empty());
+        assert!(files.contains(&PathBuf::from("test_files/sample.csv")));
+        assert!(files.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_collect_files_without_extensions_or_cycles() {
+        let dir = TempDir::new("batch-walk");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("notes"), "Plain text without an extension.").unwrap();
+        fs::write(dir.join("sub/blob.dat"), [0u8, 159, 146, 150, 0, 1]).unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("..", dir.join("sub/up")).unwrap();
+
+        let (files, errors) = collect_files(&*dir);
+        assert!(errors.is_empty());
+        assert_eq!(files, [dir.join("notes"), dir.join("sub/blob.dat")]);
+
+        let mut items = Vec::new();
+        let summary = parse_batch(&files, &BatchConfig::default(), |item| items.push(item));
+        assert_eq!(summary.succeeded, 1);
+        assert_eq!(summary.failures_by_kind.get("UnsupportedFormat"), Some(&1));
+        let unsupported = items.iter().find(|i| i.path.ends_with("blob.dat")).unwrap();
+        assert_eq!(
+            unsupported.to_record(true)["error"]["kind"],
+            "UnsupportedFormat"
+        );
+    }
+
+    #[test]
+    fn test_collect_files_reports_unreadable_directories() {
+        let dir = TempDir::new("batch-walk");
+        fs::write(dir.join("notes.txt"), "Readable.").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("missing", dir.join("dangling")).unwrap();
+
+        let (files, errors) = collect_files(&*dir);
+        assert_eq!(files, [dir.join("notes.txt")]);
+        assert!(errors.is_empty());
+
+        let (files, errors) = collect_files(dir.join("gone"));
+        assert!(files.is_empty());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, dir.join("gone"));
+    }
+
+    #[test]
+    fn test_max_bytes() {
+        let config = BatchConfig {
+            max_bytes: Some(4),
+            ..BatchConfig::default()
+        };
+        let item = parse_file(Path::new("test_files/sample.txt"), &config);
+        assert!(matches!(
+            item.result,
+            Err(ParseError::TooLarge { limit: 4, .. })
+        ));
+        assert_eq!(item.bytes, 0);
+    }
+
+    #[test]
+    fn test_parse_batch_records_failures() {
+        let (mut paths, _) = collect_files("test_files");
+        let found = paths.len();
+        paths.push(PathBuf::from("test_files/missing.txt"));
+
+        let mut items = Vec::new();
+        let config = BatchConfig {
+            workers: 3,
+            ..BatchConfig::default()
+        };
+        let summary = parse_batch(&paths, &config, |item| items.push(item));
+
+        assert_eq!(items.len(), found + 1);
+        assert_eq!(summary.total, found + 1);
+        assert_eq!(summary.succeeded, found);
+        assert_eq!(summary.failures_by_kind.get("Io"), Some(&1));
+        assert!(summary.bytes > 0);
+    }
+
+    #[test]
+    fn test_batch_item_records() {
+        let config = BatchConfig::default();
+        let ok = parse_file(Path::new("test_files/sample.json"), &config);
+        let record = ok.to_record(false);
+        assert_eq!(record["status"], "ok");
+        assert_eq!(record["metadata"]["format"], "json");
+        assert!(record["metadata"]["token_count"].is_null());
+        assert!(record.get("text").is_none());
+        assert!(ok.to_record(true)["text"].is_string());
+
+        let mut outlined = parse_file(Path::new("test_files/sample.txt"), &config);
+        if let Ok(doc) = &mut outlined.result {
+            doc.outline = vec![crate::document_parsing::OutlineItem {
+                title: "Introduction".into(),
+                page: Some(1),
+                children: Vec::new(),
+            }];
+        }
+        assert!(outlined.to_record(false).get("outline").is_none());
+        assert_eq!(
+            outlined.to_record(true)["outline"][0]["title"],
+            "Introduction"
+        );
+
+        let err = parse_file(Path::new("test_files/missing.txt"), &config);
+        let record = err.to_record(true);
+        assert_eq!(record["status"], "error");
+        assert_eq!(record["error"]["kind"], "Io");
+    }
+
+    #[test]
+    fn test_forced_format() {
+        let config = BatchConfig {
+            format: Some(DocumentFormat::Txt),
+            ..BatchConfig::default()
+        };
+        let item = parse_file(Path::new("test_files/sample.json"), &config);
+        assert_eq!(item.result.unwrap().metadata.format, "txt");
+    }
+
+    #[test]
+    fn test_token_count() {
+        let config = BatchConfig {
+            tokenizer: Some(Arc::new(crate::tokenizer::WhitespaceTokenizer)),
+            ..BatchConfig::default()
+        };
+        let item = parse_file(Path::new("test_files/sample.txt"), &config);
+        let doc = item.result.as_ref().unwrap();
+        let words = doc.text.split_whitespace().count();
+        assert_eq!(item.to_record(false)["metadata"]["token_count"], words);
+    }
+
+    #[test]
+    fn test_custom_parser_from_registry() {
+        struct Shouting;
+
+        impl crate::document_parsing::DocumentParser for Shouting {
+            fn id(&self) -> &str {
+                "shouting"
+            }
+
+            fn extensions(&self) -> &[&str] {
+                &["txt"]
+            }
+
+            fn parse(&self, bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
+                let mut doc = crate::document_parsing::parse(bytes, DocumentFormat::Txt)?;
+                doc.metadata.format = "shouting".into();
+                Ok(doc)
+            }
+        }
+
+        let mut registry = ParserRegistry::default();
+        registry.register(Box::new(Shouting));
+        let config = BatchConfig {
+            registry: Some(Arc::new(registry)),
+            ..BatchConfig::default()
+        };
+        let item = parse_file(Path::new("test_files/sample.txt"), &config);
+        assert_eq!(item.result.unwrap().metadata.format, "shouting");
+    }
+
+    #[test]
+    fn test_panic_message() {
+        let payload = panic::catch_unwind(|| panic!("bad xref")).unwrap_err();
+        assert_eq!(panic_message(&payload), "parser panicked: bad xref");
+    }
+}
@@ -0,0 +1,416 @@
+//! Split parsed documents into chunks sized for LLM context windows.
+//!
+//! Chunks follow the document structure: a chunk never spans two pages, slides
+//! or sheets, and by default a heading always starts a new chunk. Blocks that
+//! are too large on their own are split at whitespace. Every chunk is a slice of
+//! [`ParsedDocument::text`] and records where it came from, so retrieval
+//! results can be cited back to the source.
+
+use crate::document_parsing::{render_segments, ParsedDocument, Segment, SegmentKind};
+use crate::tokenizer::Tokenizer;
+use serde::Serialize;
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// What chunk budgets are measured in.
+#[derive(Clone)]
+pub enum SizeUnit {
+    Characters,
+    /// Tokens, as counted by the given tokenizer.
+    Tokens(Arc<dyn Tokenizer>),
+}
+
+impl SizeUnit {
+    fn measure(&self, text: &str) -> usize {
+        match self {
+            Self::Characters => text.chars().count(),
+            Self::Tokens(tokenizer) => tokenizer.count_tokens(text),
+        }
+    }
+}
+
+impl fmt::Debug for SizeUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Characters => f.write_str("Characters"),
+            Self::Tokens(_) => f.write_str("Tokens"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChunkConfig {
+    /// Largest chunk size, in `unit`s.
+    pub max_size: usize,
+    /// How much of the previous chunk to repeat at the start of the next one,
+    /// in `unit`s. Capped at half of `max_size`. Overlap never crosses a page,
+    /// slide, sheet or heading boundary.
+    pub overlap: usize,
+    pub unit: SizeUnit,
+    /// Start a new chunk at every heading.
+    pub split_on_headings: bool,
+}
+
+impl ChunkConfig {
+    pub fn characters(max_size: usize, overlap: usize) -> Self {
+        Self {
+            max_size,
+            overlap,
+            unit: SizeUnit::Characters,
+            split_on_headings: true,
+        }
+    }
+
+    pub fn tokens(max_size: usize, overlap: usize, tokenizer: Arc<dyn Tokenizer>) -> Self {
+        Self {
+            unit: SizeUnit::Tokens(tokenizer),
+            ..Self::characters(max_size, overlap)
+        }
+    }
+}
+
+impl Default for ChunkConfig {
+    fn default() -> Self {
+        Self::characters(2000, 200)
+    }
+}
+
+/// A piece of a document, with enough context to cite it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Chunk {
+    /// Position of the chunk within the document, from 0.
+    pub index: usize,
+    pub text: String,
+    /// Byte offset of the chunk within [`ParsedDocument::text`].
+    pub start: usize,
+    /// Byte offset one past the end of the chunk.
+    pub end: usize,
+    /// Size of the chunk, in the configured unit.
+    pub size: usize,
+    pub page: Option<usize>,
+    pub slide: Option<usize>,
+    pub sheet: Option<String>,
+    /// Titles of the headings the chunk sits under, outermost first.
+    pub heading_path: Vec<String>,
+}
+
+/// Split `doc` into chunks according to `config`.
+pub fn chunk_document(doc: &ParsedDocument, config: &ChunkConfig) -> Vec<Chunk> {
+    let (text, segments) = render_segments(&doc.blocks);
+    Chunker::new(&text, config).run(&segments)
+}
+
+struct Chunker<'a> {
+    text: &'a str,
+    config: &'a ChunkConfig,
+    max_size: usize,
+    overlap: usize,
+    chunks: Vec<Chunk>,
+}
+
+/// A run of segments being collected into one chunk.
+struct Pending<'s> {
+    /// Segments repeated from the previous chunk.
+    overlap: Vec<Piece<'s>>,
+    pieces: Vec<Piece<'s>>,
+}
+
+/// A segment, or part of one when the segment alone is over budget.
+#[derive(Clone)]
+struct Piece<'s> {
+    range: Range<usize>,
+    segment: &'s Segment,
+}
+
+impl<'a> Chunker<'a> {
+    fn new(text: &'a str, config: &'a ChunkConfig) -> Self {
+        let max_size = config.max_size.max(1);
+        Self {
+            text,
+            config,
+            max_size,
+            overlap: config.overlap.min(max_size / 2),
+            chunks: Vec::new(),
+        }
+    }
+
+    fn run(mut self, segments: &[Segment]) -> Vec<Chunk> {
+        let mut pending = Pending {
+            overlap: Vec::new(),
+            pieces: Vec::new(),
+        };
+
+        for segment in segments {
+            let starts_fresh = match pending.pieces.last() {
+                None => false,
+                Some(last) => {
+                    last.segment.section != segment.section
+                        || (self.config.split_on_headings && segment.kind == SegmentKind::Heading)
+                }
+            };
+            if starts_fresh {
+                self.emit(&pending);
+                pending.overlap.clear();
+                pending.pieces.clear();
+            }
+
+            for piece in self.split_oversized(segment) {
+                if !pending.pieces.is_empty() && !self.fits(&pending, &piece) {
+                    self.emit(&pending);
+                    pending.overlap = self.overlap_tail(&pending);
+                    pending.pieces.clear();
+                }
+                pending.pieces.push(piece);
+                while !pending.overlap.is_empty() && !self.fits_all(&pending) {
+                    pending.overlap.remove(0);
+                }
+            }
+        }
+        self.emit(&pending);
+
+        self.chunks
+    }
+
+    fn measure(&self, range: Range<usize>) -> usize {
+        self.config.unit.measure(&self.text[range])
+    }
+
+    fn span(pending: &Pending) -> Option<Range<usize>> {
+        let first = pending.overlap.first().or(pending.pieces.first())?;
+        let last = pending.pieces.last()?;
+        Some(first.range.start..last.range.end)
+    }
+
+    fn fits(&self, pending: &Pending, next: &Piece) -> bool {
+        let start = pending
+            .overlap
+            .first()
+            .or(pending.pieces.first())
+            .map_or(next.range.start, |p| p.range.start);
+        self.measure(start..next.range.end) <= self.max_size
+    }
+
+    fn fits_all(&self, pending: &Pending) -> bool {
+        Self::span(pending).is_none_or(|span| self.measure(span) <= self.max_size)
+    }
+
+    /// The trailing pieces of the emitted chunk that fit in the overlap budget.
+    /// Banners and headings are not repeated on their own.
+    fn overlap_tail<'s>(&self, pending: &Pending<'s>) -> Vec<Piece<'s>> {
+        if self.overlap == 0 {
+            return Vec::new();
+        }
+        let mut tail = Vec::new();
+        let end = match pending.pieces.last() {
+            Some(last) => last.range.end,
+            None => return tail,
+        };
+        for piece in pending.overlap.iter().chain(&pending.pieces).rev() {
+            if piece.segment.kind != SegmentKind::Body
+                || self.measure(piece.range.start..end) > self.overlap
+            {
+                break;
+            }
+            tail.insert(0, piece.clone());
+        }
+        tail
+    }
+
+    /// Split a segment that is over budget on its own at whitespace, falling back
+    /// to character boundaries for unbroken runs.
+    fn split_oversized<'s>(&self, segment: &'s Segment) -> Vec<Piece<'s>> {
+        let range = segment.range.clone();
+        if self.measure(range.clone()) <= self.max_size {
+            return vec![Piece { range, segment }];
+        }
+
+        let mut pieces = Vec::new();
+        let mut start = range.start;
+        while start < range.end {
+            let end = self.longest_fitting_end(start, range.end);
+            pieces.push(Piece {
+                range: start..end,
+                segment,
+            });
+            start = end;
+        }
+        pieces
+    }
+
+    /// The furthest end (after `start`, at most `limit`) such that the piece fits
+    /// the budget, preferring to end after whitespace.
+    fn longest_fitting_end(&self, start: usize, limit: usize) -> usize {
+        let slice = &self.text[start..limit];
+        let ends: Vec<usize> = slice
+            .char_indices()
+            .map(|(offset, ch)| start + offset + ch.len_utf8())
+            .collect();
+        // Sizes grow with the piece, so binary search over the character
+        // boundaries for the furthest end that fits.
+        let fitting = ends.partition_point(|&end| self.measure(start..end) <= self.max_size);
+        let Some(&last_fit) = fitting.checked_sub(1).and_then(|i| ends.get(i)) else {
+            // Even one character is over budget; take it anyway to make progress.
+            return ends.first().copied().unwrap_or(start);
+        };
+        if last_fit == limit {
+            return last_fit;
+        }
+
+        let mut last_word_fit = None;
+        let mut prev_ws = false;
+        for (offset, ch) in self.text[start..last_fit].char_indices() {
+            let is_ws = ch.is_whitespace();
+            if is_ws && !prev_ws {
+                last_word_fit = Some(start + offset + ch.len_utf8());
+            }
+            prev_ws = is_ws;
+        }
+        last_word_fit.unwrap_or(last_fit)
+    }
+
+    fn emit(&mut self, pending: &Pending) {
+        let Some(span) = Self::span(pending) else {
+            return;
+        };
+        let text = &self.text[span.clone()];
+        if text.trim().is_empty() {
+            return;
+        }
+
+        // Cite the first new piece rather than the repeated overlap.
+        let origin = pending.pieces[0].segment;
+        self.chunks.push(Chunk {
+            index: self.chunks.len(),
+            text: text.to_string(),
+            start: span.start,
+            end: span.end,
+            size: self.config.unit.measure(text),
+            page: origin.location.page,
+            slide: origin.location.slide,
+            sheet: origin.location.sheet.clone(),
+            heading_path: origin.heading_path.clone(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document_parsing::{Block, DocumentMetadata, SectionKind};
+    use crate::tokenizer::{CharHeuristicTokenizer, WhitespaceTokenizer};
+
+    fn doc(blocks: Vec<Block>) -> ParsedDocument {
+        ParsedDocument::new(b"", blocks, DocumentMetadata::new("test"))
+    }
+
+    #[test]
+    fn test_chunks_are_slices_of_text() {
+        let document = doc(vec![
+            Block::paragraph("alpha beta gamma"),
+            Block::paragraph("delta epsilon"),
+            Block::paragraph("zeta eta theta"),
+        ]);
+        let chunks = chunk_document(&document, &ChunkConfig::characters(32, 0));
+        assert_eq!(chunks.len(), 2);
+        for chunk in &chunks {
+            assert_eq!(&document.text[chunk.start..chunk.end], chunk.text);
+            assert!(chunk.size <= 32);
+        }
+        assert_eq!(chunks[0].text, "alpha beta gamma\n\ndelta epsilon");
+    }
+
+    #[test]
+    fn test_chunks_split_at_headings_and_record_path() {
+        let document = doc(vec![
+            Block::heading(1, "Manual"),
+            Block::paragraph("Intro text."),
+            Block::heading(2, "Install"),
+            Block::paragraph("Run the installer."),
+        ]);
+        let chunks = chunk_document(&document, &ChunkConfig::default());
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1].text, "## Install\n\nRun the installer.");
+        assert_eq!(chunks[1].heading_path, ["Manual", "Install"]);
+    }
+
+    #[test]
+    fn test_chunks_follow_sections() {
+        let slide = |number: usize, text: &str| Block::Section {
+            kind: SectionKind::Slide,
+            number,
+            title: None,
+            blocks: vec![Block::paragraph(text)],
+        };
+        let document = doc(vec![slide(1, "first"), slide(2, "second")]);
+        let chunks = chunk_document(&document, &ChunkConfig::default());
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1].slide, Some(2));
+        assert!(chunks[1].text.ends_with("second"));
+    }
+
+    #[test]
+    fn test_chunks_overlap() {
+        let document = doc(vec![
+            Block::paragraph("one one"),
+            Block::paragraph("two two"),
+            Block::paragraph("three three"),
+        ]);
+        let chunks = chunk_document(&document, &ChunkConfig::characters(20, 8));
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].text, "one one\n\ntwo two");
+        assert_eq!(chunks[1].text, "two two\n\nthree three");
+    }
+
+    #[test]
+    fn test_oversized_paragraph_is_split_at_whitespace() {
+        let document = doc(vec![Block::paragraph("aaaa bbbb cccc dddd")]);
+        let chunks = chunk_document(&document, &ChunkConfig::characters(10, 0));
+        let texts: Vec<&str> = chunks.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, ["aaaa bbbb ", "cccc dddd"]);
+    }
+
+    #[test]
+    fn test_token_budget() {
+        let document = doc(vec![Block::paragraph("x".repeat(100))]);
+        let tokenizer = Arc::new(CharHeuristicTokenizer::default());
+        let chunks = chunk_document(&document, &ChunkConfig::tokens(10, 0, tokenizer));
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.iter().all(|c| c.size <= 10));
+    }
+
+    #[test]
+    fn test_token_budget_uses_tokenizer() {
+        let document = doc(vec![Block::paragraph("a b c d e f g")]);
+        let config = ChunkConfig::tokens(3, 0, Arc::new(WhitespaceTokenizer));
+        let texts: Vec<String> = chunk_document(&document, &config)
+            .into_iter()
+            .map(|c| c.text)
+            .collect();
+        assert_eq!(texts, ["a b c ", "d e f ", "g"]);
+    }
+
+    /// Counts whitespace-separated words and how often it was asked to.
+    #[derive(Default)]
+    struct CountingTokenizer(std::sync::atomic::AtomicUsize);
+
+    impl Tokenizer for CountingTokenizer {
+        fn count_tokens(&self, text: &str) -> usize {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            text.split_whitespace().count()
+        }
+    }
+
+    #[test]
+    fn test_oversized_paragraph_is_measured_few_times() {
+        let words = vec!["word"; 2000].join(" ");
+        let document = doc(vec![Block::paragraph(words)]);
+        let tokenizer = Arc::new(CountingTokenizer::default());
+        let config = ChunkConfig::tokens(500, 0, tokenizer.clone());
+        let chunks = chunk_document(&document, &config);
+        assert_eq!(chunks.len(), 4);
+        // A binary search per cut, not one measurement per character.
+        let calls = tokenizer.0.load(std::sync::atomic::Ordering::Relaxed);
+        assert!(calls < 200, "{} measurements", calls);
+    }
+}
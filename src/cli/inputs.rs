@@ -0,0 +1,114 @@
+use rust_test::batch::collect_files;
+use std::path::{Path, PathBuf};
+
+/// One document to parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Input {
+    Stdin,
+    File(PathBuf),
+}
+
+impl Input {
+    /// How the input is named in output and error messages.
+    pub fn name(&self) -> String {
+        match self {
+            Self::Stdin => "-".to_string(),
+            Self::File(path) => path.display().to_string(),
+        }
+    }
+}
+
+/// Expand command-line arguments into inputs.
+///
+/// `-` is standard input, directories are walked recursively for every file
+/// (see [`collect_files`]), and arguments containing glob metacharacters are
+/// matched against the filesystem. Anything else is taken as a file path as-is.
+/// Arguments that match nothing, and directories that cannot be read, are
+/// returned as errors alongside the inputs that were found.
+pub fn expand(args: &[String]) -> (Vec<Input>, Vec<String>) {
+    let mut inputs = Vec::new();
+    let mut errors = Vec::new();
+
+    for arg in args {
+        if arg == "-" {
+            inputs.push(Input::Stdin);
+        } else if Path::new(arg).is_dir() {
+            let files = walk_dir(Path::new(arg), &mut errors);
+            inputs.extend(files.into_iter().map(Input::File));
+        } else if is_glob(arg) {
+            match expand_glob(arg, &mut errors) {
+                Ok(files) if files.is_empty() => errors.push(format!("{}: no matching files", arg)),
+                Ok(files) => inputs.extend(files.into_iter().map(Input::File)),
+                Err(e) => errors.push(format!("{}: {}", arg, e)),
+            }
+        } else {
+            inputs.push(Input::File(PathBuf::from(arg)));
+        }
+    }
+
+    (inputs, errors)
+}
+
+fn is_glob(arg: &str) -> bool {
+    arg.contains(['*', '?', '['])
+}
+
+/// The files matching `pattern`. Matches that cannot be read go to `errors`.
+fn expand_glob(pattern: &str, errors: &mut Vec<String>) -> Result<Vec<PathBuf>, String> {
+    let paths = glob::glob(pattern).map_err(|e| e.to_string())?;
+    let mut files = Vec::new();
+    for path in paths {
+        match path {
+            Ok(path) if path.is_dir() => files.extend(walk_dir(&path, errors)),
+            Ok(path) => files.push(path),
+            Err(e) => errors.push(e.to_string()),
+        }
+    }
+    Ok(files)
+}
+
+/// [`collect_files`], with the directories it could not read added to
+/// `errors`.
+fn walk_dir(dir: &Path, errors: &mut Vec<String>) -> Vec<PathBuf> {
+    let (files, failed) = collect_files(dir);
+    errors.extend(
+        failed
+            .into_iter()
+            .map(|(path, e)| format!("{}: {}", path.display(), e)),
+    );
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_directory() {
+        let (inputs, errors) = expand(&["test_files".to_string()]);
+        assert!(errors.is_empty());
+        assert!(inputs.contains(&Input::File(PathBuf::from("test_files/sample.csv"))));
+        assert!(inputs.windows(2).all(|w| w[0].name() <= w[1].name()));
+    }
+
+    #[test]
+    fn test_expand_glob_and_stdin() {
+        let args = ["test_files/*.json".to_string(), "-".to_string()];
+        let (inputs, errors) = expand(&args);
+        assert!(errors.is_empty());
+        assert_eq!(
+            inputs,
+            [
+                Input::File(PathBuf::from("test_files/sample.json")),
+                Input::Stdin
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expand_unmatched_glob() {
+        let (inputs, errors) = expand(&["test_files/*.nothing".to_string()]);
+        assert!(inputs.is_empty());
+        assert_eq!(errors.len(), 1);
+    }
+}
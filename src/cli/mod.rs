@@ -0,0 +1,528 @@
+//! The `agent-toolkit` command line.
+
+mod inputs;
+mod output;
+
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, Subcommand};
+use inputs::Input;
+use output::{OutputFormat, Printer};
+use rust_test::batch::{self, BatchConfig};
+use rust_test::document_parsing::{
+    DocumentFormat, MediaMode, OcrEngine, PageRange, ParseError, ParseOptions, ParsedDocument,
+    ParserRegistry, ReadingOrder, TesseractEngine, TrackedChanges,
+};
+use rust_test::tokenizer::{
+    BpeTokenizer, CharHeuristicTokenizer, Tokenizer, WhitespaceTokenizer, CL100K_PATTERN,
+    GPT2_PATTERN, O200K_PATTERN,
+};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::Arc;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "agent-toolkit",
+    version,
+    about = "Document tools for LLM agents"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Parse documents and print their text, structure or metadata.
+    ///
+    /// Exits with status 1 if any input could not be read or parsed, and 2 on
+    /// invalid arguments.
+    Parse(ParseArgs),
+    /// Parse many files in parallel, streaming one JSON line per file.
+    ///
+    /// Files that fail are written as error records and the run continues. A
+    /// summary with failures by kind and throughput goes to standard error.
+    /// Exits with status 1 if any file failed.
+    Batch(BatchArgs),
+}
+
+#[derive(Debug, clap::Args)]
+struct ParseArgs {
+    /// Files, directories (walked recursively), glob patterns, or `-` for
+    /// standard input.
+    #[arg(required = true)]
+    paths: Vec<String>,
+
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+
+    /// Print only the metadata of each document.
+    #[arg(long)]
+    metadata_only: bool,
+
+    #[command(flatten)]
+    document: DocumentArgs,
+
+    #[command(flatten)]
+    tokenizer: TokenizerArgs,
+}
+
+#[derive(Debug, clap::Args)]
+struct BatchArgs {
+    /// Directories (walked recursively), files or glob patterns.
+    #[arg(required = true)]
+    paths: Vec<String>,
+
+    /// Number of files parsed at once. Defaults to the number of CPUs.
+    #[arg(long, short = 'j')]
+    workers: Option<usize>,
+
+    /// Write only the metadata of each document, not its text and blocks.
+    #[arg(long)]
+    metadata_only: bool,
+
+    /// Skip files larger than this many bytes, writing an error record for
+    /// each. `0` removes the limit.
+    #[arg(long, default_value_t = batch::DEFAULT_MAX_BYTES)]
+    max_bytes: u64,
+
+    #[command(flatten)]
+    document: DocumentArgs,
+
+    #[command(flatten)]
+    tokenizer: TokenizerArgs,
+}
+
+/// How to read documents, shared by `parse` and `batch`.
+#[derive(Debug, clap::Args)]
+struct DocumentArgs {
+    /// Parse every input as this format instead of detecting it, e.g. `pdf`
+    /// or `md`.
+    #[arg(long, value_parser = parse_format_name)]
+    format: Option<DocumentFormat>,
+
+    /// Only extract these pages of paged formats, e.g. `3`, `10-20`, `-5`
+    /// (the first five) or `10-` (page 10 onwards).
+    #[arg(long)]
+    pages: Option<PageRange>,
+
+    /// How to order the text of PDF pages: `content` (as drawn) or `layout`
+    /// (by position, reading columns in turn and dropping running headers).
+    #[arg(long, default_value_t = ReadingOrder::Content)]
+    reading_order: ReadingOrder,
+
+    /// Password for encrypted PDFs (the user or the owner password).
+    #[arg(long)]
+    password: Option<String>,
+
+    /// Read scanned PDF pages and images with a locally installed
+    /// `tesseract`.
+    #[arg(long)]
+    ocr: bool,
+
+    /// Tesseract languages for `--ocr`, e.g. `eng+deu`.
+    #[arg(long, requires = "ocr")]
+    ocr_lang: Option<String>,
+
+    /// What to do with tracked changes in DOCX files: `accept` them, `reject`
+    /// them, or `show` insertions and deletions with their author and date.
+    #[arg(long, default_value_t = TrackedChanges::Accept)]
+    tracked_changes: TrackedChanges,
+
+    /// Return the pictures and other media embedded in DOCX, PPTX and XLSX
+    /// files as attachments: `hash` lists them, `bytes` also includes their
+    /// content (base64 in JSON).
+    #[arg(long, default_value_t = MediaMode::Off)]
+    media: MediaMode,
+}
+
+impl DocumentArgs {
+    fn options(&self) -> ParseOptions {
+        ParseOptions {
+            pages: self.pages,
+            reading_order: self.reading_order,
+            password: self.password.clone(),
+            ocr: self.ocr.then(|| {
+                let mut engine = TesseractEngine::new();
+                engine.languages = self.ocr_lang.clone();
+                Arc::new(engine) as Arc<dyn OcrEngine>
+            }),
+            tracked_changes: self.tracked_changes,
+            media: self.media,
+        }
+    }
+}
+
+fn parse_format_name(name: &str) -> Result<DocumentFormat, String> {
+    DocumentFormat::from_str_name(name)
+        .or_else(|| DocumentFormat::ALL.into_iter().find(|f| f.as_str() == name))
+        .ok_or_else(|| {
+            let names: Vec<&str> = DocumentFormat::ALL.iter().map(|f| f.as_str()).collect();
+            format!("unknown format (expected one of: {})", names.join(", "))
+        })
+}
+
+#[derive(Debug, clap::Args)]
+struct TokenizerArgs {
+    /// Count the tokens of each document into its metadata: `whitespace`,
+    /// `chars` (about four characters per token), or the path of a BPE
+    /// vocabulary, either a Hugging Face `tokenizer.json` or a tiktoken file.
+    #[arg(long)]
+    tokenizer: Option<String>,
+
+    /// Pre-tokenizer pattern of a tiktoken `--tokenizer` file, which does not
+    /// record it. Required for tiktoken files.
+    #[arg(long, value_enum, requires = "tokenizer")]
+    tokenizer_pattern: Option<TokenizerPattern>,
+}
+
+/// Pre-tokenizer pattern of a tiktoken encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum TokenizerPattern {
+    /// GPT-2, `r50k_base` and `p50k_base`.
+    #[value(name = "gpt2")]
+    Gpt2,
+    /// `cl100k_base` (GPT-4, GPT-3.5).
+    #[value(name = "cl100k")]
+    Cl100k,
+    /// `o200k_base` (GPT-4o).
+    #[value(name = "o200k")]
+    O200k,
+}
+
+impl TokenizerPattern {
+    fn regex(self) -> &'static str {
+        match self {
+            Self::Gpt2 => GPT2_PATTERN,
+            Self::Cl100k => CL100K_PATTERN,
+            Self::O200k => O200K_PATTERN,
+        }
+    }
+}
+
+impl TokenizerArgs {
+    /// The tokenizer named by `--tokenizer`, or loaded from its vocabulary
+    /// file.
+    fn load(&self) -> Result<Option<Arc<dyn Tokenizer>>, String> {
+        let Some(spec) = self.tokenizer.as_deref() else {
+            return Ok(None);
+        };
+        let tokenizer: Arc<dyn Tokenizer> = match (spec, self.tokenizer_pattern) {
+            ("whitespace" | "chars", Some(_)) => {
+                return Err(format!("--tokenizer-pattern does not apply to {}", spec));
+            }
+            ("whitespace", None) => Arc::new(WhitespaceTokenizer),
+            ("chars", None) => Arc::new(CharHeuristicTokenizer::default()),
+            (path, None) if path.ends_with(".json") => {
+                Arc::new(BpeTokenizer::from_hf_json_file(path).map_err(|e| e.to_string())?)
+            }
+            (path, Some(_)) if path.ends_with(".json") => {
+                return Err(format!(
+                    "--tokenizer-pattern does not apply to {}, which has its own",
+                    path
+                ));
+            }
+            (path, None) => {
+                return Err(format!(
+                    "{}: a tiktoken vocabulary needs --tokenizer-pattern",
+                    path
+                ));
+            }
+            (path, Some(pattern)) => Arc::new(
+                BpeTokenizer::from_tiktoken_file(path, pattern.regex())
+                    .map_err(|e| format!("{}: {}", path, e))?,
+            ),
+        };
+        Ok(Some(tokenizer))
+    }
+}
+
+/// The `--tokenizer` of a command, exiting as clap does on invalid arguments
+/// if it cannot be loaded.
+fn load_tokenizer(args: &TokenizerArgs) -> Option<Arc<dyn Tokenizer>> {
+    args.load()
+        .unwrap_or_else(|e| Cli::command().error(ErrorKind::ValueValidation, e).exit())
+}
+
+pub fn run() -> ExitCode {
+    match Cli::parse().command {
+        Command::Parse(args) => parse_command(args),
+        Command::Batch(args) => batch_command(args),
+    }
+}
+
+fn parse_command(args: ParseArgs) -> ExitCode {
+    let tokenizer = load_tokenizer(&args.tokenizer);
+    let (inputs, errors) = inputs::expand(&args.paths);
+    let mut failed = !errors.is_empty();
+    for error in errors {
+        eprintln!("agent-toolkit: {}", error);
+    }
+
+    // A single file argument prints bare; directories and globs always print
+    // one entry per document, however many they matched.
+    let single = match args.paths.as_slice() {
+        [path] => path == "-" || Path::new(path).is_file(),
+        _ => false,
+    };
+    let multiple = !single;
+    let stdout = io::stdout().lock();
+    let mut printer = Printer::new(stdout, args.output, args.metadata_only, multiple);
+    let options = args.document.options();
+
+    for input in &inputs {
+        let name = input.name();
+        let result = parse_input(input, args.document.format, &options).and_then(|mut doc| {
+            if let Some(tokenizer) = &tokenizer {
+                doc.count_tokens(tokenizer.as_ref());
+            }
+            printer.print(&name, &doc)?;
+            Ok(())
+        });
+        if let Err(e) = result {
+            // A closed pipe (e.g. `| head`) is not worth reporting.
+            if matches!(&e, ParseError::Io(io) if io.kind() == io::ErrorKind::BrokenPipe) {
+                return ExitCode::SUCCESS;
+            }
+            eprintln!("agent-toolkit: {}: {}", name, e);
+            failed = true;
+        }
+    }
+
+    if let Err(e) = printer.finish() {
+        if e.kind() != io::ErrorKind::BrokenPipe {
+            eprintln!("agent-toolkit: {}", e);
+            failed = true;
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn batch_command(args: BatchArgs) -> ExitCode {
+    let tokenizer = load_tokenizer(&args.tokenizer);
+    let (inputs, errors) = inputs::expand(&args.paths);
+    let mut failed = !errors.is_empty();
+    for error in errors {
+        eprintln!("agent-toolkit: {}", error);
+    }
+
+    let mut paths = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        match input {
+            Input::File(path) => paths.push(path),
+            Input::Stdin => {
+                eprintln!("agent-toolkit: standard input is not supported by batch");
+                failed = true;
+            }
+        }
+    }
+
+    let mut config = BatchConfig {
+        tokenizer,
+        format: args.document.format,
+        max_bytes: (args.max_bytes > 0).then_some(args.max_bytes),
+        options: args.document.options(),
+        ..BatchConfig::default()
+    };
+    if let Some(workers) = args.workers {
+        config.workers = workers;
+    }
+
+    let mut stdout = io::stdout().lock();
+    let mut closed = false;
+    let summary = batch::parse_batch(&paths, &config, |item| {
+        if closed {
+            return;
+        }
+        let record = item.to_record(!args.metadata_only);
+        if let Err(e) = writeln!(stdout, "{}", record).and_then(|_| stdout.flush()) {
+            // Keep going after a closed pipe so the summary still reports
+            // the whole run.
+            if e.kind() != io::ErrorKind::BrokenPipe {
+                eprintln!("agent-toolkit: {}", e);
+            }
+            closed = true;
+        }
+    });
+
+    eprintln!(
+        "Parsed {} files in {:.2}s ({:.1} files/s, {:.2} MiB/s): {} succeeded, {} failed",
+        summary.total,
+        summary.elapsed.as_secs_f64(),
+        summary.files_per_second(),
+        summary.bytes_per_second() / (1024.0 * 1024.0),
+        summary.succeeded,
+        summary.failed,
+    );
+    for (kind, count) in &summary.failures_by_kind {
+        eprintln!("  {}: {}", kind, count);
+    }
+
+    if failed || summary.failed > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn parse_input(
+    input: &Input,
+    format: Option<DocumentFormat>,
+    options: &ParseOptions,
+) -> Result<ParsedDocument, ParseError> {
+    let (bytes, hint) = match input {
+        Input::Stdin => {
+            let mut bytes = Vec::new();
+            io::stdin().lock().read_to_end(&mut bytes)?;
+            (bytes, None)
+        }
+        Input::File(path) => (
+            fs::read(path)?,
+            path.file_name().and_then(|n| n.to_str()).map(String::from),
+        ),
+    };
+
+    let registry = ParserRegistry::read_global();
+    match format {
+        Some(format) => registry.parse_with_options(format.as_str(), &bytes, options),
+        None => registry
+            .resolve(&bytes, hint.as_deref())
+            .ok_or_else(|| ParseError::UnsupportedFormat(input.name()))?
+            .parse_with_options(&bytes, options),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let cli = Cli::try_parse_from([
+            "agent-toolkit",
+            "parse",
+            "--format",
+            "md",
+            "--output",
+            "jsonl",
+            "--metadata-only",
+            "--pages",
+            "2-4",
+            "--reading-order",
+            "layout",
+            "--ocr",
+            "--ocr-lang",
+            "deu",
+            "--tracked-changes",
+            "show",
+            "--media",
+            "hash",
+            "--tokenizer",
+            "whitespace",
+            "a.txt",
+            "-",
+        ])
+        .unwrap();
+        let Command::Parse(args) = cli.command else {
+            panic!("expected parse");
+        };
+        assert_eq!(args.document.format, Some(DocumentFormat::Markdown));
+        assert_eq!(args.output, OutputFormat::Jsonl);
+        assert!(args.metadata_only);
+        assert_eq!(args.document.pages, Some(PageRange::new(2, 4)));
+        assert_eq!(args.document.reading_order, ReadingOrder::Layout);
+        assert!(args.document.ocr);
+        assert_eq!(args.document.ocr_lang.as_deref(), Some("deu"));
+        assert_eq!(args.document.tracked_changes, TrackedChanges::Show);
+        assert_eq!(args.document.media, MediaMode::Hash);
+        let tokenizer = args.tokenizer.load().unwrap().unwrap();
+        assert_eq!(tokenizer.count_tokens("two words"), 2);
+        assert_eq!(args.paths, ["a.txt", "-"]);
+    }
+
+    #[test]
+    fn test_batch_args() {
+        let cli = Cli::try_parse_from([
+            "agent-toolkit",
+            "batch",
+            "-j",
+            "8",
+            "--format",
+            "pdf",
+            "--pages",
+            "1-2",
+            "--password",
+            "secret",
+            "docs",
+        ])
+        .unwrap();
+        let Command::Batch(args) = cli.command else {
+            panic!("expected batch");
+        };
+        assert_eq!(args.workers, Some(8));
+        assert_eq!(args.max_bytes, batch::DEFAULT_MAX_BYTES);
+        assert_eq!(args.document.format, Some(DocumentFormat::Pdf));
+        let options = args.document.options();
+        assert_eq!(options.pages, Some(PageRange::new(1, 2)));
+        assert_eq!(options.password.as_deref(), Some("secret"));
+        assert_eq!(args.paths, ["docs"]);
+    }
+
+    fn batch_tokenizer(flags: &[&str]) -> Result<Option<Arc<dyn Tokenizer>>, String> {
+        let args = ["agent-toolkit", "batch"]
+            .iter()
+            .chain(flags)
+            .chain(&["docs"]);
+        let Command::Batch(args) = Cli::try_parse_from(args).unwrap().command else {
+            panic!("expected batch");
+        };
+        args.tokenizer.load()
+    }
+
+    #[test]
+    fn test_missing_vocabulary_is_rejected() {
+        let result = batch_tokenizer(&[
+            "--tokenizer",
+            "missing/cl100k_base.tiktoken",
+            "--tokenizer-pattern",
+            "cl100k",
+        ]);
+        assert!(result.unwrap_err().contains("missing/cl100k_base.tiktoken"));
+    }
+
+    #[test]
+    fn test_tiktoken_needs_explicit_pattern() {
+        let result = batch_tokenizer(&["--tokenizer", "o200k_base.tiktoken"]);
+        assert!(result.unwrap_err().contains("--tokenizer-pattern"));
+        assert!(
+            batch_tokenizer(&["--tokenizer", "chars", "--tokenizer-pattern", "o200k"]).is_err()
+        );
+        let result = Cli::try_parse_from([
+            "agent-toolkit",
+            "batch",
+            "--tokenizer-pattern",
+            "gpt2",
+            "docs",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_unknown_format_is_rejected() {
+        let result = Cli::try_parse_from(["agent-toolkit", "parse", "--format", "doc", "a"]);
+        assert!(result.is_err());
+    }
+}
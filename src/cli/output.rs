@@ -0,0 +1,152 @@
+use clap::ValueEnum;
+use rust_test::document_parsing::{render_markdown, DocumentView, ParsedDocument};
+use serde::Serialize;
+use std::io::{self, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Plain text; a `==> name <==` header separates multiple documents.
+    Text,
+    /// One JSON object, or an array when there are several documents.
+    Json,
+    /// GitHub-flavoured Markdown rendered from the block tree.
+    Markdown,
+    /// One compact JSON object per line.
+    Jsonl,
+}
+
+/// A parsed document as written by the JSON outputs.
+#[derive(Serialize)]
+struct Record<'a> {
+    source: &'a str,
+    #[serde(flatten)]
+    document: DocumentView<'a>,
+}
+
+/// Writes parsed documents to `out` in the chosen format.
+pub struct Printer<W: Write> {
+    out: W,
+    format: OutputFormat,
+    metadata_only: bool,
+    /// Whether more than one document may be printed, so each needs a header.
+    multiple: bool,
+    printed: usize,
+    /// `--output json` prints once everything is parsed, so it can decide
+    /// between an object and an array.
+    json_records: Vec<serde_json::Value>,
+}
+
+impl<W: Write> Printer<W> {
+    pub fn new(out: W, format: OutputFormat, metadata_only: bool, multiple: bool) -> Self {
+        Self {
+            out,
+            format,
+            metadata_only,
+            multiple,
+            printed: 0,
+            json_records: Vec::new(),
+        }
+    }
+
+    pub fn print(&mut self, source: &str, doc: &ParsedDocument) -> io::Result<()> {
+        let record = Record {
+            source,
+            document: doc.view(!self.metadata_only),
+        };
+
+        match self.format {
+            OutputFormat::Json => {
+                self.json_records.push(serde_json::to_value(&record)?);
+            }
+            OutputFormat::Jsonl => {
+                serde_json::to_writer(&mut self.out, &record)?;
+                writeln!(self.out)?;
+            }
+            OutputFormat::Text | OutputFormat::Markdown => {
+                if self.printed > 0 {
+                    writeln!(self.out)?;
+                }
+                if self.multiple {
+                    writeln!(self.out, "==> {} <==", source)?;
+                }
+                let body = if self.metadata_only {
+                    serde_json::to_string_pretty(&doc.metadata)?
+                } else if self.format == OutputFormat::Markdown {
+                    render_markdown(&doc.blocks)
+                } else {
+                    doc.text.clone()
+                };
+                writeln!(self.out, "{}", body)?;
+            }
+        }
+
+        self.printed += 1;
+        self.out.flush()
+    }
+
+    /// Write anything held back until the end and return the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.format == OutputFormat::Json {
+            let value = match self.json_records.len() {
+                1 if !self.multiple => self.json_records.remove(0),
+                _ => serde_json::Value::Array(self.json_records),
+            };
+            serde_json::to_writer_pretty(&mut self.out, &value)?;
+            writeln!(self.out)?;
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_test::document_parsing::{parse, DocumentFormat};
+
+    fn render(format: OutputFormat, metadata_only: bool, multiple: bool) -> String {
+        let doc = parse(b"# Title\n\nBody", DocumentFormat::Markdown).unwrap();
+        let mut printer = Printer::new(Vec::new(), format, metadata_only, multiple);
+        printer.print("a.md", &doc).unwrap();
+        if multiple {
+            printer.print("b.md", &doc).unwrap();
+        }
+        String::from_utf8(printer.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_text_output() {
+        assert_eq!(
+            render(OutputFormat::Text, false, false),
+            "# Title\n\nBody\n"
+        );
+        assert_eq!(
+            render(OutputFormat::Text, false, true),
+            "==> a.md <==\n# Title\n\nBody\n\n==> b.md <==\n# Title\n\nBody\n"
+        );
+    }
+
+    #[test]
+    fn test_json_output_shape() {
+        let single: serde_json::Value =
+            serde_json::from_str(&render(OutputFormat::Json, false, false)).unwrap();
+        assert_eq!(single["source"], "a.md");
+        assert_eq!(single["blocks"][0]["type"], "heading");
+
+        let many: serde_json::Value =
+            serde_json::from_str(&render(OutputFormat::Json, true, true)).unwrap();
+        assert_eq!(many.as_array().unwrap().len(), 2);
+        assert!(many[0].get("text").is_none());
+        assert_eq!(many[1]["metadata"]["format"], "markdown");
+    }
+
+    #[test]
+    fn test_jsonl_output() {
+        let out = render(OutputFormat::Jsonl, false, true);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let record: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(record["source"], "b.md");
+        assert_eq!(record["text"], "# Title\n\nBody");
+    }
+}
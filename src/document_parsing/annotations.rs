@@ -0,0 +1,106 @@
+use base64::Engine;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A hyperlink to a location outside the document.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Link {
+    pub uri: String,
+    /// The linked text, when it can be recovered.
+    pub text: Option<String>,
+    /// 1-based page the link is on, for paged formats.
+    pub page: Option<usize>,
+}
+
+/// A note attached to the document, such as a PDF sticky note or a review
+/// comment.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    /// Kind of annotation in snake case, e.g. `"text"`, `"free_text"` or
+    /// `"highlight"` for PDF, `"comment"` for DOCX.
+    pub kind: String,
+    pub text: String,
+    pub author: Option<String>,
+    /// Last modification timestamp, RFC 3339.
+    pub modified: Option<String>,
+    /// 1-based page the annotation is on, for paged formats.
+    pub page: Option<usize>,
+    /// The commented text, when the annotation is anchored to a range of it.
+    pub anchor: Option<String>,
+}
+
+/// A fillable form field and its current value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FormField {
+    /// Fully qualified name, with the names of parent fields joined by `.`.
+    pub name: String,
+    pub kind: FieldKind,
+    /// Current value: the text of a text field, the chosen option(s) of a
+    /// choice field, or the state of a check box or radio button (`"Off"`
+    /// when unset). `None` when the field is empty.
+    pub value: Option<String>,
+    /// 1-based page the field is shown on.
+    pub page: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldKind {
+    Text,
+    Checkbox,
+    Radio,
+    Button,
+    Choice,
+    Signature,
+}
+
+/// A file embedded in a document, such as a picture.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attachment {
+    /// Name of the file within the document's package, e.g.
+    /// `word/media/image1.png`. Image blocks showing it have it as their
+    /// `source`.
+    pub name: String,
+    pub mime_type: String,
+    /// Size in bytes.
+    pub size: usize,
+    /// SHA-256 of the content, lowercase hex.
+    pub sha256: String,
+    /// The content, when asked for; base64 in JSON.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_base64",
+        deserialize_with = "deserialize_base64"
+    )]
+    pub data: Option<Vec<u8>>,
+    /// Alternative text of the first image showing it.
+    pub alt: Option<String>,
+    /// Byte offset within [`ParsedDocument::text`] of the first image showing
+    /// it; `None` when the document does not show it.
+    ///
+    /// [`ParsedDocument::text`]: super::ParsedDocument::text
+    pub offset: Option<usize>,
+    /// 1-based page, 1-based slide or name of the sheet it is first shown on.
+    pub page: Option<usize>,
+    pub slide: Option<usize>,
+    pub sheet: Option<String>,
+}
+
+fn serialize_base64<S: Serializer>(
+    data: &Option<Vec<u8>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let engine = base64::engine::general_purpose::STANDARD;
+    data.as_ref()
+        .map(|d| engine.encode(d))
+        .serialize(serializer)
+}
+
+fn deserialize_base64<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Vec<u8>>, D::Error> {
+    let engine = base64::engine::general_purpose::STANDARD;
+    Option::<String>::deserialize(deserializer)?
+        .map(|text| engine.decode(text).map_err(serde::de::Error::custom))
+        .transpose()
+}
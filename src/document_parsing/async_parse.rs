@@ -0,0 +1,223 @@
+//! Async wrappers around the blocking parsers.
+//!
+//! PDF and Office parsing can take seconds, which would stall an async
+//! executor. These functions move that work onto tokio's blocking pool and
+//! let the caller bound it with a timeout or cancel it.
+
+use super::{parse_with_options, DocumentFormat, ParseError, ParseOptions, ParsedDocument};
+use std::future::Future;
+use std::path::Path;
+use std::time::Duration;
+
+pub use tokio_util::sync::CancellationToken;
+
+/// Inputs at least this large go to the blocking pool whatever their format.
+const BLOCKING_THRESHOLD: usize = 256 * 1024;
+
+/// Limits applied to one async parse.
+///
+/// A parser already running on the blocking pool cannot be interrupted: on
+/// timeout or cancellation the call returns at once and the parser's result is
+/// discarded when it finishes.
+#[derive(Debug, Clone, Default)]
+pub struct AsyncParseOptions {
+    /// Give up on the document after this long, including the time to read it.
+    pub timeout: Option<Duration>,
+    /// Give up on the document as soon as this token is cancelled.
+    pub cancellation: Option<CancellationToken>,
+}
+
+impl AsyncParseOptions {
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_cancellation(cancellation: CancellationToken) -> Self {
+        Self {
+            cancellation: Some(cancellation),
+            ..Self::default()
+        }
+    }
+
+    /// Drive `work` to completion unless the timeout expires or the token is
+    /// cancelled first.
+    async fn run<F>(&self, work: F) -> Result<ParsedDocument, ParseError>
+    where
+        F: Future<Output = Result<ParsedDocument, ParseError>>,
+    {
+        let timed = async {
+            match self.timeout {
+                Some(limit) => tokio::time::timeout(limit, work)
+                    .await
+                    .unwrap_or(Err(ParseError::Timeout(limit))),
+                None => work.await,
+            }
+        };
+
+        match &self.cancellation {
+            Some(token) => tokio::select! {
+                biased;
+                _ = token.cancelled() => Err(ParseError::Cancelled),
+                result = timed => result,
+            },
+            None => timed.await,
+        }
+    }
+}
+
+/// Parse `bytes` as `format` with `options` without blocking the executor,
+/// within `limits`.
+pub async fn parse_async(
+    bytes: Vec<u8>,
+    format: DocumentFormat,
+    options: &ParseOptions,
+    limits: &AsyncParseOptions,
+) -> Result<ParsedDocument, ParseError> {
+    limits.run(parse_off_executor(bytes, format, options)).await
+}
+
+/// Read and parse the file at `path` with `options` without blocking the
+/// executor, within `limits`, detecting its format as
+/// [`parse_path`](super::parse_path) does.
+pub async fn parse_path_async(
+    path: impl AsRef<Path>,
+    options: &ParseOptions,
+    limits: &AsyncParseOptions,
+) -> Result<ParsedDocument, ParseError> {
+    let path = path.as_ref();
+    limits
+        .run(async {
+            let bytes = tokio::fs::read(path).await?;
+            let name = path.file_name().and_then(|n| n.to_str());
+            let format = DocumentFormat::detect(&bytes, name)
+                .ok_or_else(|| ParseError::UnsupportedFormat(path.display().to_string()))?;
+            parse_off_executor(bytes, format, options).await
+        })
+        .await
+}
+
+async fn parse_off_executor(
+    bytes: Vec<u8>,
+    format: DocumentFormat,
+    options: &ParseOptions,
+) -> Result<ParsedDocument, ParseError> {
+    // Small text formats parse in microseconds; a thread hop would cost more.
+    if !is_heavy(format, options) && bytes.len() < BLOCKING_THRESHOLD {
+        return parse_with_options(&bytes, format, options);
+    }
+
+    let options = options.clone();
+    let parse = move || parse_with_options(&bytes, format, &options);
+    match tokio::task::spawn_blocking(parse).await {
+        Ok(result) => result,
+        // Surface parser panics the same way the blocking API would.
+        Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+        Err(_) => Err(ParseError::Cancelled),
+    }
+}
+
+/// Whether parsing may take long whatever the size of the input: binary
+/// formats, and anything read with OCR.
+fn is_heavy(format: DocumentFormat, options: &ParseOptions) -> bool {
+    options.ocr.is_some()
+        || matches!(
+            format,
+            DocumentFormat::Pdf
+                | DocumentFormat::Docx
+                | DocumentFormat::Xlsx
+                | DocumentFormat::Pptx
+                | DocumentFormat::Image
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document_parsing::{parse, StubOcrEngine};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_parse_async_matches_parse() {
+        let bytes = b"name,age\nAlice,30".to_vec();
+        let expected = parse(&bytes, DocumentFormat::Csv).unwrap();
+        let doc = parse_async(
+            bytes,
+            DocumentFormat::Csv,
+            &ParseOptions::default(),
+            &AsyncParseOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(doc.text, expected.text);
+    }
+
+    #[tokio::test]
+    async fn test_parse_async_blocking_pool_errors() {
+        let result = parse_async(
+            b"not a pdf".to_vec(),
+            DocumentFormat::Pdf,
+            &ParseOptions::default(),
+            &AsyncParseOptions::default(),
+        )
+        .await;
+        assert!(matches!(result, Err(ParseError::Format(_))));
+    }
+
+    #[tokio::test]
+    async fn test_parse_async_passes_options() {
+        let engine = Arc::new(StubOcrEngine::new("Scanned text"));
+        let options = ParseOptions {
+            ocr: Some(engine.clone()),
+            ..ParseOptions::default()
+        };
+        let png = b"\x89PNG\r\n\x1a\n".to_vec();
+        let doc = parse_async(png, DocumentFormat::Image, &options, &Default::default())
+            .await
+            .unwrap();
+        assert_eq!(doc.text, "Scanned text");
+        assert_eq!(engine.calls(), 1);
+    }
+
+    #[test]
+    fn test_is_heavy() {
+        let plain = ParseOptions::default();
+        assert!(is_heavy(DocumentFormat::Image, &plain));
+        assert!(!is_heavy(DocumentFormat::Txt, &plain));
+        let ocr = ParseOptions {
+            ocr: Some(Arc::new(StubOcrEngine::default())),
+            ..ParseOptions::default()
+        };
+        assert!(is_heavy(DocumentFormat::Txt, &ocr));
+    }
+
+    #[tokio::test]
+    async fn test_parse_path_async() {
+        let doc = parse_path_async(
+            "test_files/sample.json",
+            &ParseOptions::default(),
+            &AsyncParseOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(doc.metadata.format, "json");
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let options = AsyncParseOptions::with_timeout(Duration::from_millis(10));
+        let result = options.run(std::future::pending()).await;
+        assert!(matches!(result, Err(ParseError::Timeout(_))));
+    }
+
+    #[tokio::test]
+    async fn test_cancellation() {
+        let token = CancellationToken::new();
+        let options = AsyncParseOptions::with_cancellation(token.clone());
+        tokio::spawn(async move { token.cancel() });
+        let result = options.run(std::future::pending()).await;
+        assert!(matches!(result, Err(ParseError::Cancelled)));
+    }
+}
@@ -0,0 +1,532 @@
+use serde::{Deserialize, Serialize};
+
+/// A structural element of a parsed document.
+///
+/// Parsers produce a tree of blocks; [`ParsedDocument::text`] is rendered from
+/// it with [`render_text`].
+///
+/// [`ParsedDocument::text`]: super::ParsedDocument::text
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Block {
+    /// A section heading. `level` starts at 1 for the top level.
+    Heading {
+        level: u8,
+        text: String,
+    },
+    Paragraph {
+        text: String,
+    },
+    /// One item of a list. `level` starts at 0 for the outermost list.
+    ListItem {
+        ordered: bool,
+        level: u8,
+        text: String,
+    },
+    Table(Table),
+    CodeBlock {
+        language: Option<String>,
+        text: String,
+    },
+    Image {
+        alt: Option<String>,
+        /// Where the picture is: a URL, or the name of a file in the
+        /// document's package.
+        source: Option<String>,
+    },
+    PageBreak,
+    /// A page, slide or sheet and the blocks it contains, or a part of the
+    /// document kept apart from the main text, such as its headers or notes.
+    Section {
+        kind: SectionKind,
+        /// 1-based position of the section within the document.
+        number: usize,
+        title: Option<String>,
+        blocks: Vec<Block>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SectionKind {
+    Page,
+    Slide,
+    Sheet,
+    /// A page header; `title` says which pages it is shown on, e.g.
+    /// `"first page"`.
+    Header,
+    Footer,
+    /// All footnotes, each opening with the `[^id]: ` label its reference in
+    /// the text points at.
+    Footnotes,
+    Endnotes,
+    Comments,
+}
+
+impl SectionKind {
+    /// The label of a section, e.g. `Page 3`, `Sheet: Q1` or `Footnotes`.
+    fn label(self, number: usize, title: Option<&str>) -> String {
+        match (self, title) {
+            (SectionKind::Sheet, Some(title)) => format!("Sheet: {}", title),
+            (SectionKind::Sheet, None) => format!("Sheet {}", number),
+            (SectionKind::Slide, _) => format!("Slide {}", number),
+            (SectionKind::Page, _) => format!("Page {}", number),
+            (SectionKind::Header, Some(title)) => format!("Header {} ({})", number, title),
+            (SectionKind::Header, None) => format!("Header {}", number),
+            (SectionKind::Footer, Some(title)) => format!("Footer {} ({})", number, title),
+            (SectionKind::Footer, None) => format!("Footer {}", number),
+            (SectionKind::Footnotes, _) => "Footnotes".to_string(),
+            (SectionKind::Endnotes, _) => "Endnotes".to_string(),
+            (SectionKind::Comments, _) => "Comments".to_string(),
+        }
+    }
+}
+
+/// A table as rows of cell text.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Table {
+    pub rows: Vec<Vec<String>>,
+    /// Whether the first row holds column headers.
+    pub header: bool,
+}
+
+/// An entry of a document's table of contents, such as a PDF bookmark.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutlineItem {
+    pub title: String,
+    /// 1-based page the entry points at, when it can be resolved.
+    pub page: Option<usize>,
+    pub children: Vec<OutlineItem>,
+}
+
+impl Block {
+    pub fn paragraph(text: impl Into<String>) -> Self {
+        Self::Paragraph { text: text.into() }
+    }
+
+    pub fn heading(level: u8, text: impl Into<String>) -> Self {
+        Self::Heading {
+            level,
+            text: text.into(),
+        }
+    }
+}
+
+/// Render blocks as plain text.
+///
+/// Blocks are separated by a blank line, except consecutive list items. Headings
+/// and list items keep a light Markdown-style marker so the outline survives,
+/// tables are rendered one row per line with tab-separated cells, and sections
+/// get a `--- Slide N ---` style banner.
+pub fn render_text(blocks: &[Block]) -> String {
+    render_segments(blocks).0
+}
+
+/// Render blocks as GitHub-flavoured Markdown.
+///
+/// Unlike [`render_text`], tables become pipe tables, code blocks are fenced
+/// and images keep their source. Section boundaries are marked with HTML
+/// comments such as `<!-- Slide 3 -->` so they do not disturb the heading
+/// outline.
+pub fn render_markdown(blocks: &[Block]) -> String {
+    let mut out = String::new();
+    markdown_blocks(blocks, &mut out);
+    out
+}
+
+fn markdown_blocks(blocks: &[Block], out: &mut String) {
+    let mut prev: Option<&Block> = None;
+    for block in blocks {
+        if let Some(prev) = prev {
+            let is_list_run =
+                matches!(prev, Block::ListItem { .. }) && matches!(block, Block::ListItem { .. });
+            out.push_str(if is_list_run { "\n" } else { "\n\n" });
+        }
+        markdown_block(block, out);
+        prev = Some(block);
+    }
+}
+
+fn markdown_block(block: &Block, out: &mut String) {
+    match block {
+        Block::Heading { level, text } => {
+            let marker = "#".repeat(usize::from((*level).clamp(1, 6)));
+            out.push_str(&format!("{} {}", marker, text));
+        }
+        Block::Paragraph { text } => out.push_str(text),
+        Block::ListItem {
+            ordered,
+            level,
+            text,
+        } => {
+            let indent = if *ordered { "   " } else { "  " }.repeat(usize::from(*level));
+            let marker = if *ordered { "1. " } else { "- " };
+            out.push_str(&format!("{}{}{}", indent, marker, text));
+        }
+        Block::Table(table) => markdown_table(table, out),
+        Block::CodeBlock { language, text } => {
+            // Use a fence longer than any backtick run inside the code.
+            let longest_run = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+            let fence = "`".repeat(longest_run.max(2) + 1);
+            let language = language.as_deref().unwrap_or("");
+            out.push_str(&format!("{}{}\n{}\n{}", fence, language, text, fence));
+        }
+        Block::Image { alt, source } => {
+            let alt = alt.as_deref().unwrap_or("");
+            match source {
+                Some(source) => out.push_str(&format!("![{}]({})", alt, source)),
+                None => out.push_str(&format!("![{}]()", alt)),
+            }
+        }
+        Block::PageBreak => out.push_str("---"),
+        Block::Section {
+            kind,
+            number,
+            title,
+            blocks,
+        } => {
+            let marker = format!("<!-- {} -->", kind.label(*number, title.as_deref()));
+            out.push_str(&marker);
+            if !blocks.is_empty() {
+                out.push_str("\n\n");
+                markdown_blocks(blocks, out);
+            }
+        }
+    }
+}
+
+/// Pipe tables need a header row; tables without one get empty headers.
+fn markdown_table(table: &Table, out: &mut String) {
+    let columns = table.rows.iter().map(Vec::len).max().unwrap_or(0);
+    if columns == 0 {
+        return;
+    }
+
+    let row_line = |cells: &[String]| -> String {
+        let mut line = String::from("|");
+        for i in 0..columns {
+            let cell = cells.get(i).map_or("", String::as_str);
+            let cell = cell.replace('|', "\\|").replace('\n', "<br>");
+            line.push_str(&format!(" {} |", cell));
+        }
+        line
+    };
+
+    let (header, body) = match table.header {
+        true => (table.rows[0].clone(), &table.rows[1..]),
+        false => (Vec::new(), &table.rows[..]),
+    };
+    let mut lines = vec![row_line(&header), format!("|{}", " --- |".repeat(columns))];
+    lines.extend(body.iter().map(|row| row_line(row)));
+    out.push_str(&lines.join("\n"));
+}
+
+/// Where in the document a piece of rendered text comes from.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Location {
+    pub page: Option<usize>,
+    pub slide: Option<usize>,
+    pub sheet: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SegmentKind {
+    /// The `--- Slide N ---` line opening a section.
+    Banner,
+    Heading,
+    Body,
+}
+
+/// A span of the rendered text produced by one leaf block, or one table row.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Segment {
+    /// Byte range within the rendered text.
+    pub range: std::ops::Range<usize>,
+    pub kind: SegmentKind,
+    /// Increments with every section, so a change marks a section boundary.
+    pub section: usize,
+    pub location: Location,
+    /// Titles of the enclosing headings, outermost first.
+    pub heading_path: Vec<String>,
+    /// The source of the image the segment shows.
+    pub source: Option<String>,
+}
+
+/// Render blocks as [`render_text`] does, also returning the span of every
+/// leaf block in the output.
+pub(crate) fn render_segments(blocks: &[Block]) -> (String, Vec<Segment>) {
+    let mut renderer = Renderer::default();
+    renderer.render_blocks(blocks);
+    (renderer.out, renderer.segments)
+}
+
+#[derive(Default)]
+struct Renderer {
+    out: String,
+    segments: Vec<Segment>,
+    section: usize,
+    location: Location,
+    headings: Vec<(u8, String)>,
+}
+
+impl Renderer {
+    fn render_blocks(&mut self, blocks: &[Block]) {
+        let mut prev: Option<&Block> = None;
+        for block in blocks {
+            if let Some(prev) = prev {
+                let is_list_run = matches!(prev, Block::ListItem { .. })
+                    && matches!(block, Block::ListItem { .. });
+                self.out.push_str(if is_list_run { "\n" } else { "\n\n" });
+            }
+            self.render_block(block);
+            prev = Some(block);
+        }
+    }
+
+    /// Append `text` and record it as a segment.
+    fn push_segment(&mut self, text: &str, kind: SegmentKind) {
+        let start = self.out.len();
+        self.out.push_str(text);
+        self.segments.push(Segment {
+            range: start..self.out.len(),
+            kind,
+            section: self.section,
+            location: self.location.clone(),
+            heading_path: self.headings.iter().map(|(_, t)| t.clone()).collect(),
+            source: None,
+        });
+    }
+
+    fn render_block(&mut self, block: &Block) {
+        match block {
+            Block::Heading { level, text } => {
+                self.headings.retain(|(l, _)| l < level);
+                self.headings.push((*level, text.clone()));
+                let marker = "#".repeat(usize::from((*level).max(1)));
+                self.push_segment(&format!("{} {}", marker, text), SegmentKind::Heading);
+            }
+            Block::Paragraph { text } => self.push_segment(text, SegmentKind::Body),
+            Block::ListItem {
+                ordered,
+                level,
+                text,
+            } => {
+                let indent = "  ".repeat(usize::from(*level));
+                let marker = if *ordered { "1. " } else { "- " };
+                self.push_segment(&format!("{}{}{}", indent, marker, text), SegmentKind::Body);
+            }
+            Block::Table(table) => {
+                for (i, row) in table.rows.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push('\n');
+                    }
+                    // Keep one row per line and one cell per column.
+                    let cells: Vec<String> = row
+                        .iter()
+                        .map(|cell| cell.split_whitespace().collect::<Vec<_>>().join(" "))
+                        .collect();
+                    self.push_segment(&cells.join("\t"), SegmentKind::Body);
+                }
+            }
+            Block::CodeBlock { text, .. } => self.push_segment(text, SegmentKind::Body),
+            Block::Image { alt, source } => {
+                let text = match alt {
+                    Some(alt) => format!("[Image: {}]", alt),
+                    None => "[Image]".to_string(),
+                };
+                self.push_segment(&text, SegmentKind::Body);
+                if let Some(segment) = self.segments.last_mut() {
+                    segment.source = source.clone();
+                }
+            }
+            Block::PageBreak => self.push_segment("\u{000C}", SegmentKind::Body),
+            Block::Section {
+                kind,
+                number,
+                title,
+                blocks,
+            } => {
+                let outer = self.location.clone();
+                let outer_headings = self.headings.clone();
+                self.section += 1;
+
+                let banner = format!("--- {} ---", kind.label(*number, title.as_deref()));
+                match kind {
+                    SectionKind::Page => self.location.page = Some(*number),
+                    SectionKind::Slide => {
+                        self.location.slide = Some(*number);
+                        self.headings.clear();
+                    }
+                    SectionKind::Sheet => {
+                        self.location.sheet =
+                            Some(title.clone().unwrap_or_else(|| number.to_string()));
+                        self.headings.clear();
+                    }
+                    // Not under any heading of the main text.
+                    _ => self.headings.clear(),
+                }
+
+                self.push_segment(&banner, SegmentKind::Banner);
+                if !blocks.is_empty() {
+                    self.out.push('\n');
+                    self.render_blocks(blocks);
+                }
+
+                // Headings run across pages, but other sections start afresh.
+                self.location = outer;
+                if *kind != SectionKind::Page {
+                    self.headings = outer_headings;
+                }
+                self.section += 1;
+            }
+        }
+    }
+}
+
+/// Split text into paragraphs on blank lines. Used by parsers whose source has
+/// no richer structure.
+pub(crate) fn paragraphs_from_text(text: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            if !current.is_empty() {
+                blocks.push(Block::paragraph(current.join("\n")));
+                current.clear();
+            }
+        } else {
+            current.push(line);
+        }
+    }
+    if !current.is_empty() {
+        blocks.push(Block::paragraph(current.join("\n")));
+    }
+
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_text_separators() {
+        let blocks = vec![
+            Block::heading(2, "Intro"),
+            Block::paragraph("Hello"),
+            Block::ListItem {
+                ordered: false,
+                level: 0,
+                text: "one".into(),
+            },
+            Block::ListItem {
+                ordered: false,
+                level: 1,
+                text: "two".into(),
+            },
+        ];
+        assert_eq!(render_text(&blocks), "## Intro\n\nHello\n\n- one\n  - two");
+    }
+
+    #[test]
+    fn test_render_text_sections() {
+        let blocks = vec![Block::Section {
+            kind: SectionKind::Sheet,
+            number: 1,
+            title: Some("Q1".into()),
+            blocks: vec![Block::Table(Table {
+                rows: vec![vec!["a".into(), "b".into()], vec!["1".into(), "2".into()]],
+                header: true,
+            })],
+        }];
+        assert_eq!(render_text(&blocks), "--- Sheet: Q1 ---\na\tb\n1\t2");
+    }
+
+    #[test]
+    fn test_render_text_table_cells_stay_on_one_line() {
+        let blocks = vec![Block::Table(Table {
+            rows: vec![
+                vec!["a\nb".into(), "c\td".into()],
+                vec!["1".into(), "2".into()],
+            ],
+            header: false,
+        })];
+        assert_eq!(render_text(&blocks), "a b\tc d\n1\t2");
+    }
+
+    #[test]
+    fn test_render_segments() {
+        let blocks = vec![
+            Block::heading(1, "Guide"),
+            Block::Section {
+                kind: SectionKind::Page,
+                number: 3,
+                title: None,
+                blocks: vec![Block::heading(2, "Setup"), Block::paragraph("Run it.")],
+            },
+        ];
+        let (text, segments) = render_segments(&blocks);
+        assert_eq!(text, render_text(&blocks));
+
+        let last = segments.last().unwrap();
+        assert_eq!(&text[last.range.clone()], "Run it.");
+        assert_eq!(last.location.page, Some(3));
+        assert_eq!(last.heading_path, ["Guide", "Setup"]);
+        assert_eq!(segments[1].kind, SegmentKind::Banner);
+        assert_ne!(segments[0].section, last.section);
+    }
+
+    #[test]
+    fn test_render_markdown() {
+        let blocks = vec![
+            Block::heading(1, "Report"),
+            Block::Table(Table {
+                rows: vec![vec!["a".into(), "b|c".into()], vec!["1".into(), "2".into()]],
+                header: true,
+            }),
+            Block::CodeBlock {
+                language: Some("rust".into()),
+                text: "let x = 1;".into(),
+            },
+            Block::Section {
+                kind: SectionKind::Slide,
+                number: 2,
+                title: None,
+                blocks: vec![Block::Image {
+                    alt: Some("Chart".into()),
+                    source: Some("media/chart.png".into()),
+                }],
+            },
+        ];
+        assert_eq!(
+            render_markdown(&blocks),
+            "# Report\n\n| a | b\\|c |\n| --- | --- |\n| 1 | 2 |\n\n```rust\nlet x = 1;\n```\n\n<!-- Slide 2 -->\n\n![Chart](media/chart.png)"
+        );
+    }
+
+    #[test]
+    fn test_render_markdown_headerless_table() {
+        let table = Block::Table(Table {
+            rows: vec![vec!["x".into()]],
+            header: false,
+        });
+        assert_eq!(render_markdown(&[table]), "|  |\n| --- |\n| x |");
+    }
+
+    #[test]
+    fn test_block_serde_shape() {
+        let json = serde_json::to_value(Block::heading(1, "Title")).unwrap();
+        assert_eq!(json["type"], "heading");
+        assert_eq!(json["level"], 1);
+    }
+
+    #[test]
+    fn test_paragraphs_from_text() {
+        let blocks = paragraphs_from_text("a\nb\n\n  \nc");
+        assert_eq!(
+            blocks,
+            vec![Block::paragraph("a\nb"), Block::paragraph("c")]
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use super::{parsers, DocumentFormat};
+use std::io::Cursor;
+
+/// How many leading bytes are inspected when deciding whether content is text.
+const TEXT_SNIFF_LEN: usize = 8192;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+const UTF16_LE_BOM: &[u8] = b"\xFF\xFE";
+const UTF16_BE_BOM: &[u8] = b"\xFE\xFF";
+
+impl DocumentFormat {
+    /// Detect format from the content itself, using `hint_name` (a file name or
+    /// URL) only where the bytes are ambiguous.
+    ///
+    /// Binary signatures (`%PDF`, ZIP containers) always win over the hint, so a
+    /// `.docx` that is really a PDF is routed to the PDF parser. For text content
+    /// a textual extension such as `.csv` or `.md` is trusted as long as it does
+    /// not contradict what the content looks like.
+    pub fn detect(bytes: &[u8], hint_name: Option<&str>) -> Option<Self> {
+        let hinted = hint_name.and_then(Self::from_extension);
+
+        if is_pdf(bytes) {
+            return Some(Self::Pdf);
+        }
+        if bytes.starts_with(b"PK\x03\x04") {
+            return sniff_zip(bytes);
+        }
+        if parsers::image_kind(bytes).is_some() {
+            return Some(Self::Image);
+        }
+
+        let text = decode_text_prefix(bytes)?;
+        let sniffed = sniff_text(&text);
+
+        match hinted {
+            Some(hint) if hint_agrees(hint, sniffed) => Some(hint),
+            _ => Some(sniffed.unwrap_or(Self::Txt)),
+        }
+    }
+}
+
+/// PDF files must start with `%PDF-`, but readers tolerate up to 1 KiB of junk
+/// before the header, so we do too as long as the junk is binary. Text that
+/// merely mentions `%PDF-` near its start is not a PDF.
+fn is_pdf(bytes: &[u8]) -> bool {
+    let head = &bytes[..bytes.len().min(1024)];
+    match head.windows(5).position(|w| w == b"%PDF-") {
+        Some(0) => true,
+        Some(at) => decode_text_prefix(&head[..at]).is_none(),
+        None => false,
+    }
+}
+
+/// Tell OOXML containers apart by their main part.
+fn sniff_zip(bytes: &[u8]) -> Option<DocumentFormat> {
+    let archive = zip::ZipArchive::new(Cursor::new(bytes)).ok()?;
+
+    if archive.index_for_name("word/document.xml").is_some() {
+        Some(DocumentFormat::Docx)
+    } else if archive.index_for_name("xl/workbook.xml").is_some() {
+        Some(DocumentFormat::Xlsx)
+    } else if archive.index_for_name("ppt/presentation.xml").is_some() {
+        Some(DocumentFormat::Pptx)
+    } else {
+        None
+    }
+}
+
+/// Decode the leading bytes as text, honouring BOMs. Returns `None` for content
+/// that looks binary.
+fn decode_text_prefix(bytes: &[u8]) -> Option<String> {
+    let head = &bytes[..bytes.len().min(TEXT_SNIFF_LEN)];
+
+    if let Some(rest) = head.strip_prefix(UTF16_LE_BOM) {
+        return Some(decode_utf16(rest, u16::from_le_bytes));
+    }
+    if let Some(rest) = head.strip_prefix(UTF16_BE_BOM) {
+        return Some(decode_utf16(rest, u16::from_be_bytes));
+    }
+
+    let head = head.strip_prefix(UTF8_BOM).unwrap_or(head);
+    if head.contains(&0) {
+        return None;
+    }
+
+    match std::str::from_utf8(head) {
+        Ok(s) => Some(s.to_string()),
+        // The prefix may cut a multi-byte character in half; that is still text.
+        Err(e) if e.error_len().is_none() => {
+            Some(String::from_utf8_lossy(&head[..e.valid_up_to()]).into_owned())
+        }
+        Err(_) => None,
+    }
+}
+
+fn decode_utf16(bytes: &[u8], to_unit: fn([u8; 2]) -> u16) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| to_unit([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// Recognise the text formats that announce themselves. CSV, Markdown and
+/// plain text have no reliable signature and yield `None`.
+fn sniff_text(text: &str) -> Option<DocumentFormat> {
+    let trimmed = text.trim_start();
+    let head: String = trimmed.chars().take(512).collect::<String>().to_lowercase();
+
+    if head.starts_with("<?xml") {
+        if head.contains("<!doctype html") || head.contains("<html") {
+            return Some(DocumentFormat::Html);
+        }
+        return Some(DocumentFormat::Xml);
+    }
+    if head.starts_with("<!doctype html") || head.starts_with("<html") {
+        return Some(DocumentFormat::Html);
+    }
+    if head.starts_with('{') || head.starts_with('[') {
+        // Only the prefix is available here, so accept anything that is valid
+        // JSON so far rather than requiring a complete document.
+        if looks_like_json(trimmed) {
+            return Some(DocumentFormat::Json);
+        }
+    }
+    None
+}
+
+fn looks_like_json(text: &str) -> bool {
+    match serde_json::from_str::<serde_json::Value>(text) {
+        Ok(_) => true,
+        Err(e) => e.is_eof(),
+    }
+}
+
+/// Whether a format guessed from the file name is consistent with the content.
+fn hint_agrees(hint: DocumentFormat, sniffed: Option<DocumentFormat>) -> bool {
+    use DocumentFormat::*;
+
+    match hint {
+        Pdf | Docx | Xlsx | Pptx | Image => false,
+        Txt | Csv | Markdown => true,
+        Json => sniffed == Some(Json),
+        Xml => matches!(sniffed, Some(Xml | Html)),
+        Html => matches!(sniffed, None | Some(Html | Xml)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn create_zip(entry: &str) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file(entry, zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"<xml/>").unwrap();
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_detect_pdf_ignores_misleading_name() {
+        let bytes = b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n";
+        assert_eq!(
+            DocumentFormat::detect(bytes, Some("contract.docx")),
+            Some(DocumentFormat::Pdf)
+        );
+    }
+
+    #[test]
+    fn test_detect_pdf_after_binary_junk_only() {
+        let mut bytes = vec![0, 0, 7, 0x82];
+        bytes.extend_from_slice(b"%PDF-1.4\n");
+        assert_eq!(
+            DocumentFormat::detect(&bytes, None),
+            Some(DocumentFormat::Pdf)
+        );
+
+        let notes = b"Files start with the %PDF- header, then a version.\n";
+        assert_eq!(
+            DocumentFormat::detect(notes, Some("notes.txt")),
+            Some(DocumentFormat::Txt)
+        );
+        let markdown = b"# Formats\n\nA PDF begins with `%PDF-1.7`.\n";
+        assert_eq!(
+            DocumentFormat::detect(markdown, Some("formats.md")),
+            Some(DocumentFormat::Markdown)
+        );
+    }
+
+    #[test]
+    fn test_detect_ooxml_containers() {
+        let cases = [
+            ("word/document.xml", DocumentFormat::Docx),
+            ("xl/workbook.xml", DocumentFormat::Xlsx),
+            ("ppt/presentation.xml", DocumentFormat::Pptx),
+        ];
+        for (entry, expected) in cases {
+            let bytes = create_zip(entry);
+            assert_eq!(
+                DocumentFormat::detect(&bytes, Some("data.bin")),
+                Some(expected)
+            );
+        }
+    }
+
+    #[test]
+    fn test_detect_unknown_zip() {
+        let bytes = create_zip("readme.txt");
+        assert_eq!(DocumentFormat::detect(&bytes, Some("archive.docx")), None);
+    }
+
+    #[test]
+    fn test_detect_markup_without_name() {
+        assert_eq!(
+            DocumentFormat::detect(b"<?xml version=\"1.0\"?><root/>", None),
+            Some(DocumentFormat::Xml)
+        );
+        assert_eq!(
+            DocumentFormat::detect(
+                b"\n<!DOCTYPE html><html><body></body></html>",
+                Some("report")
+            ),
+            Some(DocumentFormat::Html)
+        );
+    }
+
+    #[test]
+    fn test_detect_json_with_bom() {
+        let bytes = b"\xEF\xBB\xBF{\"name\": \"Alice\"}";
+        assert_eq!(
+            DocumentFormat::detect(bytes, None),
+            Some(DocumentFormat::Json)
+        );
+    }
+
+    #[test]
+    fn test_detect_utf16_xml() {
+        let mut bytes = UTF16_LE_BOM.to_vec();
+        for unit in "<?xml version=\"1.0\"?><a/>".encode_utf16() {
+            bytes.extend_from_slice(&unit.to_le_bytes());
+        }
+        assert_eq!(
+            DocumentFormat::detect(&bytes, None),
+            Some(DocumentFormat::Xml)
+        );
+    }
+
+    #[test]
+    fn test_detect_text_uses_hint() {
+        let csv = b"name,age\nAlice,30";
+        assert_eq!(
+            DocumentFormat::detect(csv, Some("people.csv")),
+            Some(DocumentFormat::Csv)
+        );
+        assert_eq!(
+            DocumentFormat::detect(csv, Some("people")),
+            Some(DocumentFormat::Txt)
+        );
+        assert_eq!(
+            DocumentFormat::detect(csv, Some("people.pdf")),
+            Some(DocumentFormat::Txt)
+        );
+    }
+
+    #[test]
+    fn test_detect_hint_contradicted_by_content() {
+        assert_eq!(
+            DocumentFormat::detect(b"<?xml version=\"1.0\"?><a/>", Some("data.json")),
+            Some(DocumentFormat::Xml)
+        );
+    }
+
+    #[test]
+    fn test_detect_binary() {
+        assert_eq!(
+            DocumentFormat::detect(b"\x00\x01\xFE\xFF\x80\x81\0\0", Some("x.txt")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_detect_image() {
+        assert_eq!(
+            DocumentFormat::detect(b"\x89PNG\r\n\x1a\n\0\0\0", Some("x.txt")),
+            Some(DocumentFormat::Image)
+        );
+        assert_eq!(
+            DocumentFormat::detect(b"\xFF\xD8\xFF\xE0", None),
+            Some(DocumentFormat::Image)
+        );
+    }
+}
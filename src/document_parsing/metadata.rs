@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+
+/// Version of the [`DocumentMetadata`] JSON shape. Bumped whenever a field is
+/// renamed, removed or changes type.
+pub const METADATA_SCHEMA_VERSION: u32 = 1;
+
+/// Metadata common to every format.
+///
+/// Every field is always serialised (absent values as `null` or `[]`), so the
+/// JSON shape does not depend on the source format. Anything format-specific
+/// lives in `extra`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DocumentMetadata {
+    pub schema_version: u32,
+    /// Format id of the parser that produced the document, e.g. `"pdf"`.
+    pub format: String,
+    pub title: Option<String>,
+    pub authors: Vec<String>,
+    /// Creation timestamp, RFC 3339.
+    pub created: Option<String>,
+    /// Last modification timestamp, RFC 3339.
+    pub modified: Option<String>,
+    /// BCP 47 language tag, e.g. `"en-US"`.
+    pub language: Option<String>,
+    pub page_count: Option<usize>,
+    pub slide_count: Option<usize>,
+    pub sheet_count: Option<usize>,
+    /// Whitespace-separated words in the rendered text.
+    pub word_count: usize,
+    /// Unicode scalar values in the rendered text.
+    pub character_count: usize,
+    /// Tokens in the rendered text, set by [`ParsedDocument::count_tokens`].
+    ///
+    /// [`ParsedDocument::count_tokens`]: super::ParsedDocument::count_tokens
+    pub token_count: Option<usize>,
+    /// Size of the source document in bytes.
+    pub byte_size: usize,
+    /// Hex-encoded SHA-256 of the source bytes.
+    pub content_hash: String,
+    /// Format-specific fields such as `row_count` or `sheet_names`.
+    pub extra: Map<String, Value>,
+}
+
+impl DocumentMetadata {
+    pub fn new(format: impl Into<String>) -> Self {
+        Self {
+            format: format.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Fill in the counts and hash derived from the source bytes and the
+    /// rendered text.
+    pub(crate) fn fill_content_stats(&mut self, source: &[u8], text: &str) {
+        self.word_count = text.split_whitespace().count();
+        self.character_count = text.chars().count();
+        self.byte_size = source.len();
+        self.content_hash = sha256_hex(source);
+    }
+}
+
+impl Default for DocumentMetadata {
+    fn default() -> Self {
+        Self {
+            schema_version: METADATA_SCHEMA_VERSION,
+            format: String::new(),
+            title: None,
+            authors: Vec::new(),
+            created: None,
+            modified: None,
+            language: None,
+            page_count: None,
+            slide_count: None,
+            sheet_count: None,
+            word_count: 0,
+            character_count: 0,
+            token_count: None,
+            byte_size: 0,
+            content_hash: String::new(),
+            extra: Map::new(),
+        }
+    }
+}
+
+pub(crate) fn sha256_hex(bytes: &[u8]) -> String {
+    hex(&Sha256::digest(bytes))
+}
+
+/// Lowercase hex of `bytes`.
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The non-empty items of a list such as `"Ada; Grace"`, trimmed.
+pub(crate) fn split_list(value: &str, separators: &[char]) -> Vec<String> {
+    value
+        .split(separators)
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Convert an XMP (ISO 8601) date, which may omit the time, the seconds or
+/// the time zone, to RFC 3339. Dates without a time zone are taken as UTC.
+pub(crate) fn iso_date_to_rfc3339(date: &str) -> Option<String> {
+    let date = date.trim();
+    let (day, time) = date.split_once('T').unwrap_or((date, ""));
+    let mut parts = day.split('-');
+    let year: u32 = parts.next()?.parse().ok()?;
+    let month: u32 = parts.next().map_or(Some(1), |m| m.parse().ok())?;
+    let day: u32 = parts.next().map_or(Some(1), |d| d.parse().ok())?;
+
+    let zone_start = time.find(['Z', '+', '-']).unwrap_or(time.len());
+    let (clock, zone) = time.split_at(zone_start);
+    let mut clock = clock.split(':');
+    let hour: u32 = clock
+        .next()
+        .filter(|h| !h.is_empty())
+        .map_or(Some(0), |h| h.parse().ok())?;
+    let minute: u32 = clock.next().map_or(Some(0), |m| m.parse().ok())?;
+    // Drop fractional seconds.
+    let second: u32 = clock
+        .next()
+        .map_or(Some(0), |s| s.split('.').next()?.parse().ok())?;
+    let zone = match zone {
+        "" | "Z" => "Z",
+        zone => zone,
+    };
+
+    Some(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}",
+        year, month, day, hour, minute, second, zone
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_iso_date_to_rfc3339() {
+        assert_eq!(
+            iso_date_to_rfc3339("2023-04-15T10:30+02:00").as_deref(),
+            Some("2023-04-15T10:30:00+02:00")
+        );
+        assert_eq!(
+            iso_date_to_rfc3339("2023-04-15").as_deref(),
+            Some("2023-04-15T00:00:00Z")
+        );
+        assert_eq!(
+            iso_date_to_rfc3339("2023-04-15T10:30:05.25Z").as_deref(),
+            Some("2023-04-15T10:30:05Z")
+        );
+    }
+
+    #[test]
+    fn test_split_list() {
+        assert_eq!(split_list(" Ada ;; Grace;", &[';']), ["Ada", "Grace"]);
+    }
+
+    #[test]
+    fn test_metadata_stable_shape() {
+        let json = serde_json::to_value(DocumentMetadata::new("txt")).unwrap();
+        let mut keys: Vec<&str> = json
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        let mut expected = [
+            "schema_version",
+            "format",
+            "title",
+            "authors",
+            "created",
+            "modified",
+            "language",
+            "page_count",
+            "slide_count",
+            "sheet_count",
+            "word_count",
+            "character_count",
+            "token_count",
+            "byte_size",
+            "content_hash",
+            "extra",
+        ];
+        keys.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(keys, expected);
+        assert!(json["title"].is_null());
+    }
+
+    #[test]
+    fn test_metadata_content_stats() {
+        let mut metadata = DocumentMetadata::new("txt");
+        metadata.fill_content_stats(b"abc", "héllo  world");
+        assert_eq!(metadata.word_count, 2);
+        assert_eq!(metadata.character_count, 12);
+        assert_eq!(metadata.byte_size, 3);
+        assert_eq!(
+            metadata.content_hash,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_metadata_deserialize_partial() {
+        let metadata: DocumentMetadata =
+            serde_json::from_str(r#"{"format": "pdf", "page_count": 3}"#).unwrap();
+        assert_eq!(metadata.page_count, Some(3));
+        assert_eq!(metadata.schema_version, METADATA_SCHEMA_VERSION);
+    }
+}
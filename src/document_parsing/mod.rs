@@ -1,5 +1,25 @@
+mod annotations;
+mod async_parse;
+mod blocks;
+mod detect;
+mod metadata;
+mod ocr;
+mod options;
 pub mod parsers;
+mod registry;
 
+pub use annotations::{Annotation, Attachment, FieldKind, FormField, Link};
+pub use async_parse::{parse_async, parse_path_async, AsyncParseOptions, CancellationToken};
+pub use blocks::{render_markdown, render_text, Block, OutlineItem, SectionKind, Table};
+pub(crate) use blocks::{render_segments, Segment, SegmentKind};
+pub use metadata::{DocumentMetadata, METADATA_SCHEMA_VERSION};
+pub use ocr::{OcrEngine, StubOcrEngine, TesseractEngine};
+pub use options::{MediaMode, PageRange, ParseOptions, ReadingOrder, TrackedChanges};
+pub use parsers::{DocumentParser, ParseError};
+pub use registry::ParserRegistry;
+
+use crate::tokenizer::Tokenizer;
+use serde::Serialize;
 use std::path::Path;
 
 /// Supported document formats.
@@ -15,31 +35,33 @@ pub enum DocumentFormat {
     Xml,
     Txt,
     Markdown,
+    /// PNG, JPEG, TIFF and other pictures, read with OCR when enabled.
+    Image,
 }
 
 impl DocumentFormat {
+    /// Every supported format, in declaration order.
+    pub const ALL: [DocumentFormat; 11] = [
+        Self::Pdf,
+        Self::Docx,
+        Self::Xlsx,
+        Self::Pptx,
+        Self::Html,
+        Self::Csv,
+        Self::Json,
+        Self::Xml,
+        Self::Txt,
+        Self::Markdown,
+        Self::Image,
+    ];
+
     /// Detect format from file extension. Returns None for unsupported formats.
     pub fn from_extension(path: &str) -> Option<Self> {
-        let clean_path = path.split('?').next().unwrap_or(path);
-
-        let ext = Path::new(clean_path)
-            .extension()
-            .and_then(|e| e.to_str())
-            .map(|e| e.to_lowercase())?;
-
-        match ext.as_str() {
-            "pdf" => Some(Self::Pdf),
-            "docx" => Some(Self::Docx),
-            "xlsx" => Some(Self::Xlsx),
-            "pptx" => Some(Self::Pptx),
-            "html" | "htm" => Some(Self::Html),
-            "csv" => Some(Self::Csv),
-            "json" => Some(Self::Json),
-            "xml" => Some(Self::Xml),
-            "txt" | "text" | "log" => Some(Self::Txt),
-            "md" | "markdown" => Some(Self::Markdown),
-            _ => None,
-        }
+        let ext = extension_of(path)?;
+
+        Self::ALL
+            .into_iter()
+            .find(|f| f.parser().extensions().contains(&ext.as_str()))
     }
 
     pub fn from_str_name(name: &str) -> Option<Self> {
@@ -59,15 +81,200 @@ impl DocumentFormat {
             Self::Xml => "xml",
             Self::Txt => "txt",
             Self::Markdown => "markdown",
+            Self::Image => "image",
+        }
+    }
+
+    /// The built-in parser for this format.
+    pub fn parser(&self) -> &'static dyn DocumentParser {
+        match self {
+            Self::Pdf => &parsers::PdfParser,
+            Self::Docx => &parsers::DocxParser,
+            Self::Xlsx => &parsers::XlsxParser,
+            Self::Pptx => &parsers::PptxParser,
+            Self::Html => &parsers::HtmlParser,
+            Self::Csv => &parsers::CsvParser,
+            Self::Json => &parsers::JsonParser,
+            Self::Xml => &parsers::XmlParser,
+            Self::Txt => &parsers::TextParser,
+            Self::Markdown => &parsers::MarkdownParser,
+            Self::Image => &parsers::ImageParser,
         }
     }
 }
 
+/// Lowercase extension of a file name or URL, ignoring any query string.
+fn extension_of(path: &str) -> Option<String> {
+    let clean_path = path.split('?').next().unwrap_or(path);
+
+    Path::new(clean_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+}
+
+/// Parse `bytes` as `format` using the parser registered for it in
+/// [`ParserRegistry::global`].
+pub fn parse(bytes: &[u8], format: DocumentFormat) -> Result<ParsedDocument, ParseError> {
+    ParserRegistry::read_global().parse(format.as_str(), bytes)
+}
+
+/// Parse `bytes` as `format` using the parser registered for it in
+/// [`ParserRegistry::global`], with `options`.
+pub fn parse_with_options(
+    bytes: &[u8],
+    format: DocumentFormat,
+    options: &ParseOptions,
+) -> Result<ParsedDocument, ParseError> {
+    ParserRegistry::read_global().parse_with_options(format.as_str(), bytes, options)
+}
+
+/// Read and parse the file at `path` with the parser
+/// [`ParserRegistry::global`] picks from its content and file name.
+pub fn parse_path(path: impl AsRef<Path>) -> Result<ParsedDocument, ParseError> {
+    ParserRegistry::read_global().parse_path(path)
+}
+
+/// Read and parse the file at `path` as [`parse_path`] does, with `options`.
+pub fn parse_path_with_options(
+    path: impl AsRef<Path>,
+    options: &ParseOptions,
+) -> Result<ParsedDocument, ParseError> {
+    ParserRegistry::read_global().parse_path_with_options(path, options)
+}
+
 /// The result of parsing a document.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ParsedDocument {
-    /// Extracted text content.
+    /// Extracted text content, rendered from `blocks`. Plain text keeps the
+    /// source text as it is.
     pub text: String,
-    /// Optional metadata (page count, sheet names, etc.).
-    pub metadata: serde_json::Value,
+    /// Document structure: headings, paragraphs, tables, slides, sheets.
+    pub blocks: Vec<Block>,
+    /// Common metadata plus format-specific extras.
+    pub metadata: DocumentMetadata,
+    /// The document's own table of contents, e.g. PDF bookmarks. Empty when
+    /// the source has none.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub outline: Vec<OutlineItem>,
+    /// Hyperlinks to outside the document.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<Link>,
+    /// Notes and comments attached to the document.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
+    /// Fillable form fields and their values.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub form_fields: Vec<FormField>,
+    /// Embedded pictures and other media, when asked for with
+    /// [`ParseOptions::media`].
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+}
+
+/// A [`ParsedDocument`] as serialized by the command line and batch output.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(untagged)]
+pub enum DocumentView<'a> {
+    /// Every field of the document.
+    Full(&'a ParsedDocument),
+    /// Only the `metadata` field, for listings that leave out the content.
+    MetadataOnly { metadata: &'a DocumentMetadata },
+}
+
+impl ParsedDocument {
+    /// Build a document parsed from `source`. The text is rendered from
+    /// `blocks`, and the word/character counts, byte size and content hash in
+    /// `metadata` are filled in.
+    pub fn new(source: &[u8], blocks: Vec<Block>, metadata: DocumentMetadata) -> Self {
+        let text = render_text(&blocks);
+        Self::with_text(source, text, blocks, metadata)
+    }
+
+    /// Like [`ParsedDocument::new`], but with `text` as given instead of
+    /// rendered from `blocks`.
+    pub fn with_text(
+        source: &[u8],
+        text: String,
+        blocks: Vec<Block>,
+        mut metadata: DocumentMetadata,
+    ) -> Self {
+        metadata.fill_content_stats(source, &text);
+        Self {
+            text,
+            blocks,
+            metadata,
+            outline: Vec::new(),
+            links: Vec::new(),
+            annotations: Vec::new(),
+            form_fields: Vec::new(),
+            attachments: Vec::new(),
+        }
+    }
+
+    /// The document as JSON output writes it: all of it, or only its
+    /// metadata when `include_content` is false.
+    pub fn view(&self, include_content: bool) -> DocumentView<'_> {
+        if include_content {
+            DocumentView::Full(self)
+        } else {
+            DocumentView::MetadataOnly {
+                metadata: &self.metadata,
+            }
+        }
+    }
+
+    /// Count the tokens of `text` with `tokenizer` and record the result in
+    /// `metadata.token_count`.
+    pub fn count_tokens(&mut self, tokenizer: &dyn Tokenizer) -> usize {
+        let count = tokenizer.count_tokens(&self.text);
+        self.metadata.token_count = Some(count);
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parser_ids_match_formats() {
+        for format in DocumentFormat::ALL {
+            assert_eq!(format.parser().id(), format.as_str());
+            for ext in format.parser().extensions() {
+                assert_eq!(DocumentFormat::from_str_name(ext), Some(format));
+            }
+        }
+    }
+
+    #[test]
+    fn test_document_view() {
+        let doc = parse(b"# Title\n\nBody", DocumentFormat::Markdown).unwrap();
+        let full = serde_json::to_value(doc.view(true)).unwrap();
+        assert_eq!(full, serde_json::to_value(&doc).unwrap());
+        let metadata = serde_json::to_value(doc.view(false)).unwrap();
+        assert_eq!(
+            metadata,
+            serde_json::json!({ "metadata": serde_json::to_value(&doc.metadata).unwrap() })
+        );
+    }
+
+    #[test]
+    fn test_parse_dispatch() {
+        let doc = parse(b"name,age\nAlice,30", DocumentFormat::Csv).expect("Failed to parse");
+        assert!(doc.text.contains("Alice\t30"));
+    }
+
+    #[test]
+    fn test_parse_path() {
+        let doc = parse_path("test_files/sample.json").expect("Failed to parse file");
+        assert_eq!(doc.metadata.format, "json");
+        assert!(doc.metadata.byte_size > 0);
+    }
+
+    #[test]
+    fn test_parse_path_missing_file() {
+        let result = parse_path("test_files/does_not_exist.txt");
+        assert!(matches!(result, Err(ParseError::Io(_))));
+    }
 }
@@ -0,0 +1,186 @@
+//! Optical character recognition for pages and pictures without a text layer.
+//!
+//! Parsers never run OCR on their own: set [`ParseOptions::ocr`] to an engine
+//! and the PDF parser hands it the images of scanned pages, and the image
+//! parser the picture itself.
+//!
+//! [`ParseOptions::ocr`]: super::ParseOptions::ocr
+
+use super::ParseError;
+use std::fmt;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Turns a picture of text into text.
+pub trait OcrEngine: Send + Sync {
+    /// Short name of the engine, recorded in the metadata of documents it
+    /// read, e.g. `"tesseract"`.
+    fn id(&self) -> &str;
+
+    /// Recognise the text of `image`, an encoded image file: PNG, JPEG,
+    /// TIFF, JPEG 2000 or binary PNM (the PDF parser writes raw bitmaps as
+    /// PNM). Returns the text with one line per line of the image.
+    fn recognize(&self, image: &[u8]) -> Result<String, ParseError>;
+}
+
+impl fmt::Debug for dyn OcrEngine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "OcrEngine({})", self.id())
+    }
+}
+
+/// Engines are equal only to themselves.
+impl PartialEq for dyn OcrEngine {
+    fn eq(&self, other: &Self) -> bool {
+        ptr::addr_eq(self, other)
+    }
+}
+
+/// Runs a locally installed [Tesseract](https://github.com/tesseract-ocr/tesseract)
+/// (version 4 or later), passing the image on standard input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TesseractEngine {
+    /// The `tesseract` executable; found on `PATH` by default.
+    pub program: PathBuf,
+    /// Languages to recognise, as for `tesseract -l`, e.g. `"eng+deu"`.
+    /// Tesseract's default (English) when `None`.
+    pub languages: Option<String>,
+}
+
+impl TesseractEngine {
+    pub fn new() -> Self {
+        Self {
+            program: PathBuf::from("tesseract"),
+            languages: None,
+        }
+    }
+
+    pub fn with_languages(mut self, languages: impl Into<String>) -> Self {
+        self.languages = Some(languages.into());
+        self
+    }
+}
+
+impl Default for TesseractEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OcrEngine for TesseractEngine {
+    fn id(&self) -> &str {
+        "tesseract"
+    }
+
+    fn recognize(&self, image: &[u8]) -> Result<String, ParseError> {
+        let mut command = Command::new(&self.program);
+        command.args(["stdin", "stdout"]);
+        if let Some(languages) = &self.languages {
+            command.args(["-l", languages]);
+        }
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                ParseError::Ocr(format!("could not run {}: {}", self.program.display(), e))
+            })?;
+
+        // Write from another thread so a large image cannot deadlock against
+        // output the child has not been able to flush.
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let output = thread::scope(|scope| {
+            let writer = scope.spawn(move || stdin.write_all(image));
+            let output = child.wait_with_output();
+            // A write error is explained by the exit status below.
+            let _ = writer.join();
+            output
+        })?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(ParseError::Ocr(format!(
+                "{} failed ({}): {}",
+                self.program.display(),
+                output.status,
+                stderr.trim()
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+/// An engine that "recognises" the same text in every image, for tests.
+#[derive(Debug, Default)]
+pub struct StubOcrEngine {
+    text: String,
+    calls: AtomicUsize,
+}
+
+impl StubOcrEngine {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            calls: AtomicUsize::new(0),
+        }
+    }
+
+    /// How many images the engine has been given.
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::Relaxed)
+    }
+}
+
+impl OcrEngine for StubOcrEngine {
+    fn id(&self) -> &str {
+        "stub"
+    }
+
+    fn recognize(&self, _image: &[u8]) -> Result<String, ParseError> {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        Ok(self.text.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document_parsing::ParseOptions;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_stub_engine() {
+        let engine = StubOcrEngine::new("Hello");
+        assert_eq!(engine.recognize(b"P5 1 1 255 \x00").unwrap(), "Hello");
+        assert_eq!(engine.calls(), 1);
+    }
+
+    #[test]
+    fn test_engines_compare_by_identity() {
+        let a: Arc<dyn OcrEngine> = Arc::new(StubOcrEngine::new("a"));
+        let b: Arc<dyn OcrEngine> = Arc::new(StubOcrEngine::new("a"));
+        let options = |ocr: &Arc<dyn OcrEngine>| ParseOptions {
+            ocr: Some(ocr.clone()),
+            ..ParseOptions::default()
+        };
+        assert_eq!(options(&a), options(&a));
+        assert_ne!(options(&a), options(&b));
+        assert_eq!(format!("{:?}", a), "OcrEngine(stub)");
+    }
+
+    #[test]
+    fn test_missing_tesseract() {
+        let engine = TesseractEngine {
+            program: PathBuf::from("/nonexistent/tesseract"),
+            languages: None,
+        };
+        let err = engine.recognize(b"").unwrap_err();
+        assert!(matches!(err, ParseError::Ocr(_)));
+        assert_eq!(err.kind(), "Ocr");
+    }
+}
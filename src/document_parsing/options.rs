@@ -0,0 +1,266 @@
+use super::OcrEngine;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Options that change how a document is parsed.
+///
+/// Each option applies to some formats only; parsers ignore the ones that do
+/// not concern them. `ParseOptions::default()` gives the same result as
+/// [`DocumentParser::parse`](super::DocumentParser::parse).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParseOptions {
+    /// Only extract these pages (PDF).
+    pub pages: Option<PageRange>,
+    /// The order in which the text of a page is read (PDF).
+    pub reading_order: ReadingOrder,
+    /// User or owner password of an encrypted document (PDF).
+    pub password: Option<String>,
+    /// Engine used to read scanned PDF pages and images (PDF, images).
+    /// Without one, scanned pages are only flagged in the metadata.
+    pub ocr: Option<Arc<dyn OcrEngine>>,
+    /// What to do with insertions and deletions recorded by change tracking
+    /// (DOCX).
+    pub tracked_changes: TrackedChanges,
+    /// Whether to return embedded pictures and other media as
+    /// [`ParsedDocument::attachments`](super::ParsedDocument::attachments)
+    /// (DOCX, PPTX, XLSX).
+    pub media: MediaMode,
+}
+
+/// How the text of a page is put in order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReadingOrder {
+    /// The order in which the file draws the text. Fast, and right for most
+    /// single-column documents.
+    #[default]
+    Content,
+    /// The order a reader would follow, from the positions of the text:
+    /// columns are read one after the other, and repeated page headers,
+    /// footers and page numbers are removed.
+    Layout,
+}
+
+impl fmt::Display for ReadingOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ReadingOrder::Content => "content",
+            ReadingOrder::Layout => "layout",
+        })
+    }
+}
+
+impl FromStr for ReadingOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "content" => Ok(ReadingOrder::Content),
+            "layout" => Ok(ReadingOrder::Layout),
+            other => Err(format!(
+                "unknown reading order {:?}, expected \"content\" or \"layout\"",
+                other
+            )),
+        }
+    }
+}
+
+/// How tracked changes are read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TrackedChanges {
+    /// The text as it reads with every change accepted: insertions are kept
+    /// and deletions dropped.
+    #[default]
+    Accept,
+    /// The text as it read before the changes: insertions are dropped and
+    /// deletions kept.
+    Reject,
+    /// Both, in [CriticMarkup](https://criticmarkup.com): `{++inserted++}`
+    /// and `{--deleted--}`, each followed by `{>>author, date<<}` when the
+    /// change records them.
+    Show,
+}
+
+impl fmt::Display for TrackedChanges {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TrackedChanges::Accept => "accept",
+            TrackedChanges::Reject => "reject",
+            TrackedChanges::Show => "show",
+        })
+    }
+}
+
+impl FromStr for TrackedChanges {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "accept" => Ok(TrackedChanges::Accept),
+            "reject" => Ok(TrackedChanges::Reject),
+            "show" => Ok(TrackedChanges::Show),
+            other => Err(format!(
+                "unknown tracked changes mode {:?}, expected \"accept\", \"reject\" or \"show\"",
+                other
+            )),
+        }
+    }
+}
+
+/// How much of the embedded media to return.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MediaMode {
+    /// None of it.
+    #[default]
+    Off,
+    /// The name, type, size and hash of each file, and where it is shown.
+    Hash,
+    /// All of that and the content.
+    Bytes,
+}
+
+impl fmt::Display for MediaMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MediaMode::Off => "off",
+            MediaMode::Hash => "hash",
+            MediaMode::Bytes => "bytes",
+        })
+    }
+}
+
+impl FromStr for MediaMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "off" => Ok(MediaMode::Off),
+            "hash" => Ok(MediaMode::Hash),
+            "bytes" => Ok(MediaMode::Bytes),
+            other => Err(format!(
+                "unknown media mode {:?}, expected \"off\", \"hash\" or \"bytes\"",
+                other
+            )),
+        }
+    }
+}
+
+/// A 1-based, inclusive range of pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageRange {
+    pub start: usize,
+    /// Last page to include; `None` runs to the end of the document.
+    pub end: Option<usize>,
+}
+
+impl PageRange {
+    /// Pages `start` to `end`, both included.
+    pub fn new(start: usize, end: usize) -> Self {
+        Self {
+            start,
+            end: Some(end),
+        }
+    }
+
+    /// The first `count` pages.
+    pub fn first(count: usize) -> Self {
+        Self::new(1, count)
+    }
+
+    /// Page `start` to the end of the document.
+    pub fn from(start: usize) -> Self {
+        Self { start, end: None }
+    }
+
+    pub fn contains(&self, page: usize) -> bool {
+        page >= self.start && self.end.is_none_or(|end| page <= end)
+    }
+}
+
+impl fmt::Display for PageRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.end {
+            Some(end) if end == self.start => write!(f, "{}", end),
+            Some(end) => write!(f, "{}-{}", self.start, end),
+            None => write!(f, "{}-", self.start),
+        }
+    }
+}
+
+/// Parses `"7"`, `"10-20"`, `"-5"` (the first five pages) and `"10-"` (page
+/// 10 onwards).
+impl FromStr for PageRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let page = |n: &str| -> Result<usize, String> {
+            match n.trim().parse::<usize>() {
+                Ok(0) | Err(_) => Err(format!("invalid page number {:?}", n.trim())),
+                Ok(n) => Ok(n),
+            }
+        };
+
+        let range = match s.split_once('-') {
+            None => {
+                let n = page(s)?;
+                Self::new(n, n)
+            }
+            Some((start, end)) if start.trim().is_empty() => Self::first(page(end)?),
+            Some((start, end)) if end.trim().is_empty() => Self::from(page(start)?),
+            Some((start, end)) => Self::new(page(start)?, page(end)?),
+        };
+
+        if range.end.is_some_and(|end| end < range.start) {
+            return Err(format!("page range {:?} ends before it starts", s));
+        }
+        Ok(range)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_range_from_str() {
+        assert_eq!("7".parse(), Ok(PageRange::new(7, 7)));
+        assert_eq!("10-20".parse(), Ok(PageRange::new(10, 20)));
+        assert_eq!("-5".parse(), Ok(PageRange::first(5)));
+        assert_eq!("10-".parse(), Ok(PageRange::from(10)));
+        assert!("0-3".parse::<PageRange>().is_err());
+        assert!("5-2".parse::<PageRange>().is_err());
+        assert!("a".parse::<PageRange>().is_err());
+    }
+
+    #[test]
+    fn test_page_range_contains() {
+        assert!(PageRange::first(5).contains(5));
+        assert!(!PageRange::first(5).contains(6));
+        assert!(PageRange::from(10).contains(300));
+        assert!(!PageRange::from(10).contains(9));
+    }
+
+    #[test]
+    fn test_reading_order_from_str() {
+        assert_eq!("layout".parse(), Ok(ReadingOrder::Layout));
+        assert_eq!("Content".parse(), Ok(ReadingOrder::Content));
+        assert!("columns".parse::<ReadingOrder>().is_err());
+        assert_eq!(ReadingOrder::Layout.to_string(), "layout");
+    }
+
+    #[test]
+    fn test_tracked_changes_from_str() {
+        assert_eq!("show".parse(), Ok(TrackedChanges::Show));
+        assert_eq!(" Reject".parse(), Ok(TrackedChanges::Reject));
+        assert!("merge".parse::<TrackedChanges>().is_err());
+        assert_eq!(TrackedChanges::Accept.to_string(), "accept");
+    }
+
+    #[test]
+    fn test_media_mode_from_str() {
+        assert_eq!("bytes".parse(), Ok(MediaMode::Bytes));
+        assert_eq!("Hash".parse(), Ok(MediaMode::Hash));
+        assert!("all".parse::<MediaMode>().is_err());
+        assert_eq!(MediaMode::Off.to_string(), "off");
+    }
+}
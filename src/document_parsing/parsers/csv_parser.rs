@@ -1,6 +1,26 @@
-use super::ParseError;
-use crate::document_parsing::ParsedDocument;
-use serde_json::json;
+use super::{DocumentParser, ParseError};
+use crate::document_parsing::{Block, DocumentFormat, DocumentMetadata, ParsedDocument, Table};
+
+/// CSV parser.
+pub struct CsvParser;
+
+impl DocumentParser for CsvParser {
+    fn id(&self) -> &str {
+        DocumentFormat::Csv.as_str()
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["csv"]
+    }
+
+    fn mime_types(&self) -> &[&str] {
+        &["text/csv"]
+    }
+
+    fn parse(&self, bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
+        parse_csv(bytes)
+    }
+}
 
 pub fn parse_csv(bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
     let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(bytes);
@@ -11,23 +31,26 @@ pub fn parse_csv(bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
         .clone();
 
     let mut rows = Vec::new();
-    rows.push(headers.iter().collect::<Vec<_>>().join("\t"));
+    rows.push(headers.iter().map(String::from).collect::<Vec<_>>());
 
     let mut row_count = 0;
     for result in reader.records() {
         let record = result.map_err(|e| ParseError::Format(format!("CSV row error: {}", e)))?;
-        rows.push(record.iter().collect::<Vec<_>>().join("\t"));
+        rows.push(record.iter().map(String::from).collect());
         row_count += 1;
     }
 
-    Ok(ParsedDocument {
-        text: rows.join("\n"),
-        metadata: json!({
-            "format": "csv",
-            "column_count": headers.len(),
-            "row_count": row_count,
-        }),
-    })
+    let mut metadata = DocumentMetadata::new(DocumentFormat::Csv.as_str());
+    metadata
+        .extra
+        .insert("column_count".into(), headers.len().into());
+    metadata.extra.insert("row_count".into(), row_count.into());
+
+    Ok(ParsedDocument::new(
+        bytes,
+        vec![Block::Table(Table { rows, header: true })],
+        metadata,
+    ))
 }
 
 #[cfg(test)]
@@ -43,6 +66,7 @@ mod tests {
         assert_eq!(
             result
                 .metadata
+                .extra
                 .get("row_count")
                 .unwrap()
                 .as_u64()
@@ -52,6 +76,7 @@ mod tests {
         assert_eq!(
             result
                 .metadata
+                .extra
                 .get("column_count")
                 .unwrap()
                 .as_u64()
@@ -68,6 +93,7 @@ mod tests {
         assert_eq!(
             result
                 .metadata
+                .extra
                 .get("row_count")
                 .unwrap()
                 .as_u64()
@@ -0,0 +1,992 @@
+//! The blocks of a WordprocessingML story such as `word/document.xml`.
+
+use super::fields::Field;
+use super::ooxml::Relationship;
+use super::stories::NoteKind;
+use super::styles::{Emphasis, Numbering, NumberingRef, Styles};
+use super::tables::TableBuilder;
+use super::{attr, toggle};
+use crate::document_parsing::{Block, Link, ParseError, TrackedChanges};
+use quick_xml::events::{BytesStart, Event};
+use std::collections::HashMap;
+
+/// Document-wide definitions the body refers to.
+#[derive(Debug, Default)]
+pub(super) struct Context {
+    pub styles: Styles,
+    pub numbering: Numbering,
+    pub tracked_changes: TrackedChanges,
+}
+
+/// A stretch of text with the same emphasis.
+#[derive(Debug, Default, PartialEq)]
+struct Span {
+    text: String,
+    bold: bool,
+    italic: bool,
+}
+
+/// Paragraph being collected from `w:p`.
+#[derive(Default)]
+struct Paragraph {
+    spans: Vec<Span>,
+    style: Option<String>,
+    outline_level: Option<u8>,
+    numbering: Option<NumberingRef>,
+    images: Vec<Block>,
+    /// The blocks of text boxes anchored in the paragraph.
+    text_boxes: Vec<Block>,
+    page_break: bool,
+}
+
+impl Paragraph {
+    fn push(&mut self, text: &str, bold: bool, italic: bool) {
+        match self.spans.last_mut() {
+            Some(last) if last.bold == bold && last.italic == italic => last.text.push_str(text),
+            _ => self.spans.push(Span {
+                text: text.to_string(),
+                bold,
+                italic,
+            }),
+        }
+    }
+
+    /// The text with Markdown emphasis, or without when `plain`.
+    fn text(&self, plain: bool) -> String {
+        let mut out = String::new();
+        for span in &self.spans {
+            let marker = match (span.bold, span.italic) {
+                _ if plain => "",
+                (true, true) => "***",
+                (true, false) => "**",
+                (false, true) => "*",
+                (false, false) => "",
+            };
+            // Markers must hug the text for Markdown to see them.
+            let core = span.text.trim();
+            if marker.is_empty() || core.is_empty() {
+                out.push_str(&span.text);
+                continue;
+            }
+            let start = span.text.len() - span.text.trim_start().len();
+            let end = start + core.len();
+            out.push_str(&span.text[..start]);
+            out.push_str(marker);
+            out.push_str(core);
+            out.push_str(marker);
+            out.push_str(&span.text[end..]);
+        }
+        out.trim().to_string()
+    }
+
+    fn into_blocks(self, context: &Context, blocks: &mut Vec<Block>) {
+        let style = self.style.as_deref();
+        let heading = self
+            .outline_level
+            .map(|level| level + 1)
+            .or_else(|| style.and_then(|s| context.styles.heading_level(s)));
+        let numbering = self.numbering.as_ref().map(|own| {
+            let inherited = style
+                .and_then(|s| context.styles.numbering(s))
+                .unwrap_or_default();
+            NumberingRef {
+                num_id: own.num_id.clone().or(inherited.num_id),
+                level: own.level.or(inherited.level),
+            }
+        });
+        let numbering = numbering
+            .or_else(|| style.and_then(|s| context.styles.numbering(s)))
+            // List 0 switches numbering off.
+            .filter(|n| n.num_id.as_deref() != Some("0"));
+
+        let text = self.text(heading.is_some());
+        if !text.is_empty() {
+            blocks.push(match (heading, numbering) {
+                (Some(level), _) => Block::heading(level, text),
+                (None, Some(numbering)) => {
+                    let level = numbering.level.unwrap_or(0);
+                    Block::ListItem {
+                        ordered: numbering
+                            .num_id
+                            .is_some_and(|id| context.numbering.is_ordered(&id, level)),
+                        level,
+                        text,
+                    }
+                }
+                (None, None) => Block::paragraph(text),
+            });
+        }
+        blocks.extend(self.images);
+        blocks.extend(self.text_boxes);
+        if self.page_break {
+            blocks.push(Block::PageBreak);
+        }
+    }
+}
+
+/// Where in a paragraph the reader is.
+#[derive(Default)]
+struct RunState {
+    in_paragraph_properties: bool,
+    in_run_properties: bool,
+    in_text: bool,
+    /// Direct formatting of the current run.
+    emphasis: Emphasis,
+    /// Character style of the current run.
+    style: Option<String>,
+}
+
+/// A tracked change: `w:ins` or `w:del`, or `w:moveTo` or `w:moveFrom` for
+/// text moved elsewhere.
+struct Change {
+    deleted: bool,
+    author: Option<String>,
+    date: Option<String>,
+    /// Whether its opening mark has been written.
+    shown: bool,
+}
+
+/// A field being read.
+#[derive(Default)]
+struct OpenField {
+    instruction: String,
+    /// The field, once its instruction has been read.
+    field: Option<Field>,
+    /// Whether Word's result is replaced by the evaluated one.
+    hides_result: bool,
+    /// Whether the field opened the current link.
+    linked: bool,
+    /// Whether any text of the result has been written.
+    has_result: bool,
+}
+
+/// A text box being read: `w:txbxContent`, which holds whole paragraphs
+/// and tables although it sits inside a run of another paragraph.
+struct TextBox {
+    /// The paragraph the box is anchored in, set aside until the box ends.
+    outer: Option<Paragraph>,
+    run: RunState,
+    /// Tables open outside the box.
+    tables: usize,
+    blocks: Vec<Block>,
+}
+
+/// A hyperlink being read.
+struct OpenLink {
+    target: String,
+    text: String,
+    /// Whether its opening bracket has been written in this paragraph.
+    shown: bool,
+}
+
+/// A header or footer of one of the document's sections:
+/// `w:headerReference` or `w:footerReference`.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct HeaderFooterRef {
+    pub footer: bool,
+    /// The pages it is shown on: `"default"`, `"first"` or `"even"`.
+    pub kind: Option<String>,
+    /// Relationship id of the part.
+    pub id: String,
+}
+
+/// What was read from a story.
+#[derive(Debug, Default)]
+pub(super) struct Story {
+    pub blocks: Vec<Block>,
+    /// The text each comment is anchored to, by comment id.
+    pub comment_anchors: HashMap<String, String>,
+    /// Headers and footers of the document's sections, in document order.
+    pub headers_and_footers: Vec<HeaderFooterRef>,
+    /// Tracked insertions and deletions.
+    pub insertions: usize,
+    pub deletions: usize,
+    /// Hyperlinks out of the document.
+    pub links: Vec<Link>,
+}
+
+/// Reads a story one event at a time, so stories nested in another part,
+/// such as each `w:footnote` of `word/footnotes.xml`, can be read apart.
+pub(super) struct StoryReader<'a> {
+    context: &'a Context,
+    story: Story,
+    paragraph: Option<Paragraph>,
+    run: RunState,
+    /// Open tables, innermost last.
+    tables: Vec<TableBuilder>,
+    /// Open text boxes, innermost last.
+    text_boxes: Vec<TextBox>,
+    /// For each open `mc:AlternateContent`, innermost last, whether one of
+    /// its choices has been read.
+    alternates: Vec<bool>,
+    /// Depth of the element being skipped, such as the fallback of content
+    /// already read from a choice.
+    skipping: usize,
+    /// Comments whose range is open, with the text it covers so far.
+    open_comments: Vec<(String, String)>,
+    /// Tracked changes the reader is in, innermost last.
+    changes: Vec<Change>,
+    /// Relationships of the part the story is in.
+    relationships: &'a HashMap<String, Relationship>,
+    link: Option<OpenLink>,
+    /// Fields the reader is in, innermost last.
+    fields: Vec<OpenField>,
+    in_instruction: bool,
+    /// Bookmarks whose range is open: their id, name and text so far.
+    open_bookmarks: Vec<(String, String, String)>,
+    /// The text of each closed bookmark, by name.
+    bookmarks: HashMap<String, String>,
+    /// Anchors of bookmarks that start before the next paragraph.
+    pending_anchors: Vec<String>,
+    /// The page being read, as last laid out by Word.
+    page: usize,
+    /// Whether nothing has been read since a hard page break, so the break
+    /// Word rendered for it is not counted again.
+    after_page_break: bool,
+}
+
+impl<'a> StoryReader<'a> {
+    /// A reader for a story of the part whose relationships are
+    /// `relationships`.
+    pub fn new(context: &'a Context, relationships: &'a HashMap<String, Relationship>) -> Self {
+        Self {
+            context,
+            story: Story::default(),
+            paragraph: None,
+            run: RunState::default(),
+            tables: Vec::new(),
+            text_boxes: Vec::new(),
+            alternates: Vec::new(),
+            skipping: 0,
+            open_comments: Vec::new(),
+            changes: Vec::new(),
+            relationships,
+            link: None,
+            fields: Vec::new(),
+            in_instruction: false,
+            open_bookmarks: Vec::new(),
+            bookmarks: HashMap::new(),
+            pending_anchors: Vec::new(),
+            page: 1,
+            after_page_break: false,
+        }
+    }
+
+    pub fn event(&mut self, event: &Event) {
+        if self.skipping > 0 {
+            match event {
+                Event::Start(_) => self.skipping += 1,
+                Event::End(_) => self.skipping -= 1,
+                _ => {}
+            }
+            return;
+        }
+        match event {
+            Event::Start(e) => match e.name().as_ref() {
+                b"mc:AlternateContent" => self.alternates.push(false),
+                // The same content for older readers, or a second choice.
+                b"mc:Choice" | b"mc:Fallback" if self.alternates.last() == Some(&true) => {
+                    self.skipping = 1;
+                }
+                b"mc:Choice" => {
+                    if let Some(read) = self.alternates.last_mut() {
+                        *read = true;
+                    }
+                }
+                b"w:txbxContent" => {
+                    let mut outer = self.paragraph.take();
+                    // The drawing holding the box is not a picture.
+                    if let Some(p) = outer.as_mut() {
+                        if matches!(p.images.last(), Some(Block::Image { source: None, .. })) {
+                            p.images.pop();
+                        }
+                    }
+                    self.text_boxes.push(TextBox {
+                        outer,
+                        run: std::mem::take(&mut self.run),
+                        tables: self.tables.len(),
+                        blocks: Vec::new(),
+                    });
+                }
+                b"w:p" => {
+                    let mut paragraph = Paragraph::default();
+                    for name in self.pending_anchors.drain(..) {
+                        paragraph.push(&anchor(&name), false, false);
+                    }
+                    self.paragraph = Some(paragraph);
+                }
+                b"w:pPr" => self.run.in_paragraph_properties = true,
+                b"w:rPr" => self.run.in_run_properties = true,
+                b"w:r" => {
+                    self.run.emphasis = Emphasis::default();
+                    self.run.style = None;
+                }
+                b"w:t" | b"w:delText" => self.run.in_text = true,
+                b"w:instrText" => self.in_instruction = true,
+                b"w:hyperlink" => {
+                    let url = attr(e, b"r:id")
+                        .and_then(|id| self.relationships.get(&id))
+                        .map(|r| r.target.clone());
+                    self.open_link(url, attr(e, b"w:anchor"));
+                }
+                b"w:fldSimple" => {
+                    self.fields.push(OpenField {
+                        instruction: attr(e, b"w:instr").unwrap_or_default(),
+                        ..OpenField::default()
+                    });
+                    self.evaluate_field();
+                }
+                name @ (b"w:ins" | b"w:moveTo" | b"w:del" | b"w:moveFrom") => {
+                    let deleted = matches!(name, b"w:del" | b"w:moveFrom");
+                    if deleted {
+                        self.story.deletions += 1;
+                    } else {
+                        self.story.insertions += 1;
+                    }
+                    self.changes.push(Change {
+                        deleted,
+                        author: attr(e, b"w:author").filter(|a| !a.is_empty()),
+                        date: attr(e, b"w:date"),
+                        shown: false,
+                    });
+                }
+                b"w:numPr" => {
+                    if let Some(p) = self.paragraph.as_mut() {
+                        p.numbering.get_or_insert_with(Default::default);
+                    }
+                }
+                b"w:tbl" => self.tables.push(TableBuilder::default()),
+                b"w:tr" => {
+                    if let Some(table) = self.tables.last_mut() {
+                        table.start_row();
+                    }
+                }
+                b"w:tc" => {
+                    if let Some(table) = self.tables.last_mut() {
+                        table.start_cell();
+                    }
+                }
+                _ => self.element(e),
+            },
+            Event::Empty(e) if e.name().as_ref() == b"w:fldSimple" => {
+                self.fields.push(OpenField {
+                    instruction: attr(e, b"w:instr").unwrap_or_default(),
+                    ..OpenField::default()
+                });
+                self.end_field();
+            }
+            Event::Empty(e) => self.element(e),
+            Event::Text(e) if self.in_instruction => {
+                if let (Some(field), Ok(text)) = (self.fields.last_mut(), e.unescape()) {
+                    field.instruction.push_str(&text);
+                }
+            }
+            Event::Text(e) if self.run.in_text => {
+                if let Ok(text) = e.unescape() {
+                    self.push_text(&text);
+                }
+            }
+            Event::End(e) => match e.name().as_ref() {
+                b"mc:AlternateContent" => {
+                    self.alternates.pop();
+                }
+                b"w:txbxContent" => self.close_text_box(),
+                b"w:t" | b"w:delText" => self.run.in_text = false,
+                b"w:instrText" => self.in_instruction = false,
+                b"w:hyperlink" => self.close_link(),
+                b"w:fldSimple" => self.end_field(),
+                b"w:ins" | b"w:moveTo" | b"w:del" | b"w:moveFrom" => {
+                    if let Some(change) = self.changes.pop() {
+                        self.close_change(change);
+                    }
+                }
+                b"w:pPr" => self.run.in_paragraph_properties = false,
+                b"w:rPr" => self.run.in_run_properties = false,
+                b"w:p" => {
+                    // A link running on into the next paragraph is written
+                    // in two parts.
+                    if let (Some(link), Some(p)) = (self.link.as_mut(), self.paragraph.as_mut()) {
+                        if std::mem::take(&mut link.shown) {
+                            p.push(&format!("]({})", destination(&link.target)), false, false);
+                        }
+                    }
+                    if let Some(p) = self.paragraph.take() {
+                        p.into_blocks(self.context, self.container());
+                        for (_, text) in &mut self.open_comments {
+                            text.push(' ');
+                        }
+                    }
+                }
+                b"w:tc" => {
+                    if let Some(table) = self.tables.last_mut() {
+                        table.end_cell();
+                    }
+                }
+                b"w:tr" => {
+                    if let Some(table) = self.tables.last_mut() {
+                        table.end_row();
+                    }
+                }
+                b"w:tbl" => {
+                    if let Some(table) = self.tables.pop() {
+                        let table = Block::Table(table.finish());
+                        self.container().push(table);
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    pub fn finish(mut self) -> Story {
+        for (id, text) in std::mem::take(&mut self.open_comments) {
+            self.close_comment(id, text);
+        }
+        self.close_link();
+        self.story
+    }
+
+    /// Where finished blocks go: the innermost open table cell or text box,
+    /// or the story.
+    fn container(&mut self) -> &mut Vec<Block> {
+        let outside = self.text_boxes.last().map_or(0, |b| b.tables);
+        if self.tables.len() > outside {
+            if let Some(table) = self.tables.last_mut() {
+                return &mut table.cell;
+            }
+        }
+        match self.text_boxes.last_mut() {
+            Some(text_box) => &mut text_box.blocks,
+            None => &mut self.story.blocks,
+        }
+    }
+
+    /// Go back to the paragraph the innermost text box is anchored in, which
+    /// gets the blocks of the box after its own.
+    fn close_text_box(&mut self) {
+        let Some(outside) = self.text_boxes.last().map(|b| b.tables) else {
+            return;
+        };
+        if let Some(p) = self.paragraph.take() {
+            p.into_blocks(self.context, self.container());
+        }
+        while self.tables.len() > outside {
+            if let Some(table) = self.tables.pop() {
+                let table = Block::Table(table.finish());
+                self.container().push(table);
+            }
+        }
+        let Some(text_box) = self.text_boxes.pop() else {
+            return;
+        };
+        self.run = text_box.run;
+        self.paragraph = text_box.outer;
+        match self.paragraph.as_mut() {
+            Some(p) => p.text_boxes.extend(text_box.blocks),
+            None => self.container().extend(text_box.blocks),
+        }
+    }
+
+    /// Close the markup of a change shown in the text.
+    fn close_change(&mut self, change: Change) {
+        let Some(p) = self.paragraph.as_mut().filter(|_| change.shown) else {
+            return;
+        };
+        p.push(if change.deleted { "--}" } else { "++}" }, false, false);
+        let by: Vec<String> = change.author.into_iter().chain(change.date).collect();
+        if !by.is_empty() {
+            p.push(&format!("{{>>{}<<}}", by.join(", ")), false, false);
+        }
+    }
+
+    /// Start a link to `url`, to the bookmark `anchor`, or to both. Links to
+    /// hidden bookmarks are left out, like the bookmarks themselves.
+    fn open_link(&mut self, url: Option<String>, anchor: Option<String>) -> bool {
+        let url = url.filter(|u| !u.is_empty());
+        let anchor = anchor.filter(|a| !a.is_empty());
+        let target = match (url, anchor) {
+            (Some(url), Some(anchor)) => format!("{}#{}", url, anchor),
+            (Some(url), None) => url,
+            (None, Some(anchor)) if !is_hidden_bookmark(&anchor) => format!("#{}", anchor),
+            _ => return false,
+        };
+        self.close_link();
+        self.link = Some(OpenLink {
+            target,
+            text: String::new(),
+            shown: false,
+        });
+        true
+    }
+
+    fn close_link(&mut self) {
+        let Some(link) = self.link.take() else {
+            return;
+        };
+        if link.shown {
+            if let Some(p) = self.paragraph.as_mut() {
+                p.push(&format!("]({})", destination(&link.target)), false, false);
+            }
+        }
+        if !link.target.starts_with('#') {
+            let text = link.text.split_whitespace().collect::<Vec<_>>().join(" ");
+            self.story.links.push(Link {
+                uri: link.target,
+                text: Some(text).filter(|t| !t.is_empty()),
+                page: None,
+            });
+        }
+    }
+
+    /// Evaluate the innermost field once its instruction is complete.
+    fn evaluate_field(&mut self) {
+        let Some(open) = self.fields.last_mut() else {
+            return;
+        };
+        let field = Field::parse(&open.instruction);
+        open.field = Some(field.clone());
+        let linked = match field {
+            Field::Hyperlink { url, anchor } => self.open_link(url, anchor),
+            Field::Ref { bookmark } => self.open_link(None, Some(bookmark)),
+            Field::Page => {
+                self.push_text(&self.page.to_string());
+                if let Some(open) = self.fields.last_mut() {
+                    open.hides_result = true;
+                }
+                false
+            }
+            Field::Other => false,
+        };
+        if let Some(open) = self.fields.last_mut() {
+            open.linked = linked;
+        }
+    }
+
+    /// Close the innermost field. A reference whose result is missing shows
+    /// the text of its bookmark.
+    fn end_field(&mut self) {
+        if self.fields.last().is_some_and(|f| f.field.is_none()) {
+            self.evaluate_field();
+        }
+        let Some(open) = self.fields.last() else {
+            return;
+        };
+        if let (Some(Field::Ref { bookmark }), false) = (&open.field, open.has_result) {
+            if let Some(text) = self.bookmarks.get(bookmark).cloned() {
+                self.push_text(&text);
+            }
+        }
+        if self.fields.pop().is_some_and(|f| f.linked) {
+            self.close_link();
+        }
+    }
+
+    fn close_comment(&mut self, id: String, text: String) {
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !text.is_empty() {
+            self.story.comment_anchors.insert(id, text);
+        }
+    }
+
+    /// Handle an element that is not a container: a marker that may stand
+    /// between paragraphs, a property of the paragraph, run or table being
+    /// read, or inline content such as a tab.
+    fn element(&mut self, e: &BytesStart) {
+        match e.name().as_ref() {
+            b"w:commentRangeStart" => {
+                if let Some(id) = attr(e, b"w:id") {
+                    self.open_comments.push((id, String::new()));
+                }
+                return;
+            }
+            b"w:commentRangeEnd" => {
+                let id = attr(e, b"w:id");
+                if let Some(i) = self
+                    .open_comments
+                    .iter()
+                    .position(|(open, _)| Some(open) == id.as_ref())
+                {
+                    let (id, text) = self.open_comments.remove(i);
+                    self.close_comment(id, text);
+                }
+                return;
+            }
+            b"w:bookmarkStart" => {
+                let (Some(id), Some(name)) = (attr(e, b"w:id"), attr(e, b"w:name")) else {
+                    return;
+                };
+                if !is_hidden_bookmark(&name) {
+                    match self.paragraph.as_mut() {
+                        Some(p) => p.push(&anchor(&name), false, false),
+                        None => self.pending_anchors.push(name.clone()),
+                    }
+                }
+                self.open_bookmarks.push((id, name, String::new()));
+                return;
+            }
+            b"w:bookmarkEnd" => {
+                let id = attr(e, b"w:id");
+                if let Some(i) = self
+                    .open_bookmarks
+                    .iter()
+                    .position(|(open, _, _)| Some(open) == id.as_ref())
+                {
+                    let (_, name, text) = self.open_bookmarks.remove(i);
+                    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                    self.bookmarks.insert(name, text);
+                }
+                return;
+            }
+            b"w:fldChar" => {
+                match attr(e, b"w:fldCharType").as_deref() {
+                    Some("begin") => self.fields.push(OpenField::default()),
+                    Some("separate") => self.evaluate_field(),
+                    Some("end") => self.end_field(),
+                    _ => {}
+                }
+                return;
+            }
+            b"w:lastRenderedPageBreak" => {
+                if !std::mem::take(&mut self.after_page_break) {
+                    self.page += 1;
+                }
+                return;
+            }
+            name @ (b"w:headerReference" | b"w:footerReference") => {
+                if let Some(id) = attr(e, b"r:id") {
+                    self.story.headers_and_footers.push(HeaderFooterRef {
+                        footer: name == b"w:footerReference",
+                        kind: attr(e, b"w:type"),
+                        id,
+                    });
+                }
+                return;
+            }
+            _ => {}
+        }
+
+        let run = &mut self.run;
+        let Some(p) = self.paragraph.as_mut() else {
+            if let Some(table) = self.tables.last_mut() {
+                table.property(e);
+            }
+            return;
+        };
+        if run.in_paragraph_properties {
+            match e.name().as_ref() {
+                // The paragraph mark's formatting does not apply to the text.
+                _ if run.in_run_properties => {}
+                b"w:pStyle" => p.style = attr(e, b"w:val"),
+                b"w:outlineLvl" => {
+                    p.outline_level = attr(e, b"w:val")
+                        .and_then(|v| v.parse().ok())
+                        .filter(|&level| level < 9);
+                }
+                b"w:numId" => {
+                    p.numbering.get_or_insert_with(Default::default).num_id = attr(e, b"w:val")
+                }
+                b"w:ilvl" => {
+                    p.numbering.get_or_insert_with(Default::default).level =
+                        attr(e, b"w:val").and_then(|v| v.parse().ok());
+                }
+                _ => {}
+            }
+            return;
+        }
+        if run.in_run_properties {
+            match e.name().as_ref() {
+                b"w:rStyle" => run.style = attr(e, b"w:val"),
+                b"w:b" => run.emphasis.bold = Some(toggle(e)),
+                b"w:i" => run.emphasis.italic = Some(toggle(e)),
+                _ => {}
+            }
+            return;
+        }
+        let note = match e.name().as_ref() {
+            b"w:footnoteReference" => Some(NoteKind::Footnote),
+            b"w:endnoteReference" => Some(NoteKind::Endnote),
+            b"w:commentReference" => Some(NoteKind::Comment),
+            _ => None,
+        };
+        if let (Some(kind), Some(id)) = (note, attr(e, b"w:id")) {
+            p.push(&kind.reference(&id), false, false);
+            return;
+        }
+        match e.name().as_ref() {
+            b"w:tab" => p.push("\t", false, false),
+            b"w:br" if attr(e, b"w:type").as_deref() == Some("page") => {
+                p.page_break = true;
+                self.page += 1;
+                self.after_page_break = true;
+            }
+            b"w:br" | b"w:cr" => p.push("\n", false, false),
+            b"wp:docPr" => p.images.push(Block::Image {
+                alt: attr(e, b"descr").filter(|d| !d.is_empty()),
+                source: None,
+            }),
+            // The picture of the drawing `wp:docPr` described, embedded in
+            // the package or linked.
+            b"a:blip" => {
+                if let Some(Block::Image {
+                    source: source @ None,
+                    ..
+                }) = p.images.last_mut()
+                {
+                    *source = attr(e, b"r:embed")
+                        .or_else(|| attr(e, b"r:link"))
+                        .and_then(|id| self.relationships.get(&id))
+                        .map(|r| r.target.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Append `text` to the paragraph with the emphasis of the current run,
+    /// unless it is left out by a tracked change or is the result of a field
+    /// that has been evaluated instead.
+    fn push_text(&mut self, text: &str) {
+        let Some(paragraph) = self.paragraph.as_mut() else {
+            return;
+        };
+        let visible = match self.context.tracked_changes {
+            TrackedChanges::Accept => !self.changes.iter().any(|c| c.deleted),
+            TrackedChanges::Reject => self.changes.iter().all(|c| c.deleted),
+            TrackedChanges::Show => true,
+        };
+        // Fields nested in an instruction are part of the instruction.
+        let in_result = self
+            .fields
+            .iter()
+            .all(|f| f.field.is_some() && !f.hides_result);
+        if !visible || !in_result {
+            return;
+        }
+        if let Some(field) = self.fields.last_mut() {
+            field.has_result = true;
+        }
+        self.after_page_break = false;
+        if let Some(link) = self.link.as_mut() {
+            if !link.shown {
+                paragraph.push("[", false, false);
+                link.shown = true;
+            }
+            link.text.push_str(text);
+        }
+        if let Some(change) = self.changes.last_mut() {
+            if self.context.tracked_changes == TrackedChanges::Show && !change.shown {
+                paragraph.push(if change.deleted { "{--" } else { "{++" }, false, false);
+                change.shown = true;
+            }
+        }
+        let style = self
+            .run
+            .style
+            .as_deref()
+            .map(|s| self.context.styles.emphasis(s))
+            .unwrap_or_default();
+        paragraph.push(
+            text,
+            self.run.emphasis.bold.or(style.bold).unwrap_or(false),
+            self.run.emphasis.italic.or(style.italic).unwrap_or(false),
+        );
+        for (_, anchor) in &mut self.open_comments {
+            anchor.push_str(text);
+        }
+        for (_, _, bookmark) in &mut self.open_bookmarks {
+            bookmark.push_str(text);
+        }
+    }
+}
+
+/// Word's own bookmarks, such as `_Toc…` for table of contents entries and
+/// `_GoBack`, start with an underscore and are not shown to users.
+fn is_hidden_bookmark(name: &str) -> bool {
+    name.starts_with('_')
+}
+
+/// The target of links to bookmark `name`.
+fn anchor(name: &str) -> String {
+    format!("<a id=\"{}\"></a>", name)
+}
+
+/// A link target as a Markdown link destination.
+fn destination(target: &str) -> String {
+    if target.contains(|c: char| c.is_whitespace() || c == '(' || c == ')') {
+        format!("<{}>", target)
+    } else {
+        target.to_string()
+    }
+}
+
+/// Read a whole story, such as `word/document.xml` or a header, from a part
+/// whose relationships are `relationships`.
+pub(super) fn read_story(
+    xml: &str,
+    context: &Context,
+    relationships: &HashMap<String, Relationship>,
+) -> Result<Story, ParseError> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut story = StoryReader::new(context, relationships);
+    let mut buf = Vec::new();
+
+    loop {
+        let event = reader
+            .read_event_into(&mut buf)
+            .map_err(|e| ParseError::Format(format!("XML parse error: {}", e)))?;
+        if let Event::Eof = event {
+            break;
+        }
+        story.event(&event);
+        buf.clear();
+    }
+
+    Ok(story.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocks(body: &str) -> Vec<Block> {
+        read_story(body, &Context::default(), &HashMap::new())
+            .unwrap()
+            .blocks
+    }
+
+    #[test]
+    fn test_emphasis_becomes_markdown() {
+        let body = r#"<w:p>
+  <w:pPr><w:rPr><w:b/></w:rPr></w:pPr>
+  <w:r><w:t xml:space="preserve">Plain </w:t></w:r>
+  <w:r><w:rPr><w:b/></w:rPr><w:t xml:space="preserve">bold </w:t></w:r>
+  <w:r><w:rPr><w:b/></w:rPr><w:t>still</w:t></w:r>
+  <w:r><w:rPr><w:b/><w:i/></w:rPr><w:t xml:space="preserve"> both</w:t></w:r>
+  <w:r><w:rPr><w:i/><w:b w:val="0"/></w:rPr><w:t xml:space="preserve"> italic</w:t></w:r>
+</w:p>"#;
+        assert_eq!(
+            blocks(body),
+            [Block::paragraph("Plain **bold still** ***both*** *italic*")]
+        );
+    }
+
+    #[test]
+    fn test_tracked_changes() {
+        let body = r#"<w:p><w:r><w:t xml:space="preserve">Payment within </w:t></w:r>
+  <w:del w:id="1" w:author="Counsel" w:date="2024-05-01T09:00:00Z"><w:r><w:delText>30</w:delText></w:r></w:del>
+  <w:ins w:id="2" w:author="Counsel" w:date="2024-05-01T09:00:00Z"><w:r><w:rPr><w:b/></w:rPr><w:t>60</w:t></w:r></w:ins>
+  <w:r><w:t xml:space="preserve"> days.</w:t></w:r></w:p>
+<w:p><w:ins w:id="3"><w:r><w:t>New clause.</w:t></w:r></w:ins></w:p>"#;
+        let read = |tracked_changes| {
+            let context = Context {
+                tracked_changes,
+                ..Context::default()
+            };
+            let story = read_story(body, &context, &HashMap::new()).unwrap();
+            assert_eq!((story.insertions, story.deletions), (2, 1));
+            story.blocks
+        };
+        assert_eq!(
+            read(TrackedChanges::Accept),
+            [
+                Block::paragraph("Payment within **60** days."),
+                Block::paragraph("New clause.")
+            ]
+        );
+        assert_eq!(
+            read(TrackedChanges::Reject),
+            [Block::paragraph("Payment within 30 days.")]
+        );
+        assert_eq!(
+            read(TrackedChanges::Show),
+            [
+                Block::paragraph(
+                    "Payment within {--30--}{>>Counsel, 2024-05-01T09:00:00Z<<}\
+                     {++**60**++}{>>Counsel, 2024-05-01T09:00:00Z<<} days."
+                ),
+                Block::paragraph("{++New clause.++}")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_links_bookmarks_and_fields() {
+        let body = r#"<w:p><w:bookmarkStart w:id="0" w:name="Annex_A"/><w:r><w:t>Annex A</w:t></w:r><w:bookmarkEnd w:id="0"/>
+  <w:bookmarkStart w:id="1" w:name="_GoBack"/><w:bookmarkEnd w:id="1"/></w:p>
+<w:p><w:r><w:t xml:space="preserve">See </w:t></w:r><w:hyperlink r:id="rId9"><w:r><w:rPr><w:b/></w:rPr><w:t>the site</w:t></w:r></w:hyperlink>
+  <w:r><w:t xml:space="preserve"> and </w:t></w:r><w:hyperlink w:anchor="Annex_A"><w:r><w:t>the annex</w:t></w:r></w:hyperlink>
+  <w:r><w:t xml:space="preserve">, or </w:t></w:r><w:r><w:fldChar w:fldCharType="begin"/></w:r>
+  <w:r><w:instrText xml:space="preserve"> HYPERLINK "https://example.org/faq" </w:instrText></w:r><w:r><w:fldChar w:fldCharType="separate"/></w:r>
+  <w:r><w:t>FAQ</w:t></w:r><w:r><w:fldChar w:fldCharType="end"/></w:r><w:r><w:t>.</w:t></w:r></w:p>
+<w:p><w:r><w:br w:type="page"/></w:r></w:p>
+<w:p><w:r><w:lastRenderedPageBreak/><w:t xml:space="preserve">Page </w:t></w:r><w:fldSimple w:instr=" PAGE "><w:r><w:t>1</w:t></w:r></w:fldSimple>
+  <w:r><w:t xml:space="preserve">, see </w:t></w:r><w:fldSimple w:instr=" REF Annex_A \h "/></w:p>"#;
+        let relationships = HashMap::from([(
+            "rId9".to_string(),
+            Relationship {
+                kind: "hyperlink".into(),
+                target: "https://example.com/".into(),
+                external: true,
+            },
+        )]);
+        let story = read_story(body, &Context::default(), &relationships).unwrap();
+        assert_eq!(
+            story.blocks,
+            [
+                Block::paragraph(r#"<a id="Annex_A"></a>Annex A"#),
+                Block::paragraph(
+                    "See [**the site**](https://example.com/) and [the annex](#Annex_A), \
+                     or [FAQ](https://example.org/faq)."
+                ),
+                Block::PageBreak,
+                Block::paragraph("Page 2, see [Annex A](#Annex_A)"),
+            ]
+        );
+        assert_eq!(
+            story.links,
+            [
+                Link {
+                    uri: "https://example.com/".into(),
+                    text: Some("the site".into()),
+                    page: None
+                },
+                Link {
+                    uri: "https://example.org/faq".into(),
+                    text: Some("FAQ".into()),
+                    page: None
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_text_box_keeps_outer_paragraph() {
+        let body = r#"<w:p><w:r><w:t xml:space="preserve">Before box </w:t></w:r>
+  <w:r><mc:AlternateContent><mc:Choice Requires="wps"><w:drawing><wp:anchor>
+    <wp:docPr id="1" name="Text Box 1"/>
+    <a:graphic><a:graphicData><wps:wsp><wps:txbx><w:txbxContent>
+      <w:p><w:r><w:rPr><w:b/></w:rPr><w:t>Boxed</w:t></w:r></w:p>
+      <w:tbl><w:tr><w:tc><w:p><w:r><w:t>Cell</w:t></w:r></w:p></w:tc></w:tr></w:tbl>
+    </w:txbxContent></wps:txbx></wps:wsp></a:graphicData></a:graphic>
+  </wp:anchor></w:drawing></mc:Choice>
+  <mc:Fallback><w:pict><v:shape><v:textbox><w:txbxContent>
+    <w:p><w:r><w:t>Boxed</w:t></w:r></w:p>
+  </w:txbxContent></v:textbox></v:shape></w:pict></mc:Fallback></mc:AlternateContent></w:r>
+  <w:r><w:t>after box.</w:t></w:r></w:p>
+<w:p><w:r><w:t>Next.</w:t></w:r></w:p>"#;
+        let blocks = blocks(body);
+        assert_eq!(blocks.len(), 4, "{:?}", blocks);
+        assert_eq!(blocks[0], Block::paragraph("Before box after box."));
+        assert_eq!(blocks[1], Block::paragraph("**Boxed**"));
+        assert!(matches!(&blocks[2], Block::Table(_)));
+        assert_eq!(blocks[3], Block::paragraph("Next."));
+    }
+
+    #[test]
+    fn test_heading_text_is_plain() {
+        let body = r#"<w:p><w:pPr><w:outlineLvl w:val="2"/></w:pPr>
+  <w:r><w:rPr><w:b/></w:rPr><w:t>Terms</w:t></w:r></w:p>"#;
+        assert_eq!(blocks(body), [Block::heading(3, "Terms")]);
+    }
+}
@@ -0,0 +1,114 @@
+//! Field codes: the instruction of a simple field (`w:fldSimple`) or of a
+//! complex one (`w:instrText` between `w:fldChar` begin and separate marks),
+//! such as `HYPERLINK "https://example.com/" \o "tip"`.
+
+/// The fields that are evaluated. Any other field shows the result Word last
+/// computed for it.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Field {
+    /// A link to `url`, or to the bookmark `anchor` in the document, or to
+    /// both.
+    Hyperlink {
+        url: Option<String>,
+        anchor: Option<String>,
+    },
+    /// The number of the page the field is on.
+    Page,
+    /// The text of a bookmark.
+    Ref {
+        bookmark: String,
+    },
+    Other,
+}
+
+impl Field {
+    pub fn parse(instruction: &str) -> Self {
+        let tokens = tokens(instruction);
+        let Some((name, arguments)) = tokens.split_first() else {
+            return Field::Other;
+        };
+        match name.to_ascii_uppercase().as_str() {
+            "HYPERLINK" => {
+                let mut url = None;
+                let mut anchor = None;
+                let mut arguments = arguments.iter();
+                while let Some(argument) = arguments.next() {
+                    match argument.as_str() {
+                        "\\l" => anchor = arguments.next().cloned(),
+                        // Tooltip and target frame.
+                        "\\o" | "\\t" => {
+                            arguments.next();
+                        }
+                        switch if switch.starts_with('\\') => {}
+                        argument => {
+                            url.get_or_insert_with(|| argument.to_string());
+                        }
+                    }
+                }
+                Field::Hyperlink { url, anchor }
+            }
+            "PAGE" => Field::Page,
+            "REF" => match arguments.iter().find(|a| !a.starts_with('\\')) {
+                Some(bookmark) => Field::Ref {
+                    bookmark: bookmark.clone(),
+                },
+                None => Field::Other,
+            },
+            _ => Field::Other,
+        }
+    }
+}
+
+/// The words of an instruction, with quotes removed from quoted ones.
+fn tokens(instruction: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = instruction.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            tokens.push(chars.by_ref().take_while(|&c| c != '"').collect());
+        } else {
+            let mut token = String::new();
+            while let Some(&c) = chars.peek().filter(|c| !c.is_whitespace()) {
+                token.push(c);
+                chars.next();
+            }
+            tokens.push(token);
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fields() {
+        assert_eq!(
+            Field::parse(r#" HYPERLINK "https://example.com/a b" \o "Open it" "#),
+            Field::Hyperlink {
+                url: Some("https://example.com/a b".into()),
+                anchor: None
+            }
+        );
+        assert_eq!(
+            Field::parse(r#"HYPERLINK \l "Annex_A""#),
+            Field::Hyperlink {
+                url: None,
+                anchor: Some("Annex_A".into())
+            }
+        );
+        assert_eq!(Field::parse(" PAGE   \\* MERGEFORMAT "), Field::Page);
+        assert_eq!(
+            Field::parse(" REF _Ref123 \\h "),
+            Field::Ref {
+                bookmark: "_Ref123".into()
+            }
+        );
+        assert_eq!(Field::parse(" TOC \\o \"1-3\" "), Field::Other);
+        assert_eq!(Field::parse(""), Field::Other);
+    }
+}
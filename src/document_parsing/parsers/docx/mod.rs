@@ -0,0 +1,451 @@
+mod body;
+mod fields;
+mod stories;
+mod styles;
+mod tables;
+
+use super::ooxml::{self, attr, read_part};
+use super::{DocumentParser, ParseError};
+use crate::document_parsing::{DocumentFormat, DocumentMetadata, ParseOptions, ParsedDocument};
+use body::Context;
+use quick_xml::events::BytesStart;
+use std::io::Cursor;
+use styles::{Numbering, Styles};
+
+/// DOCX parser.
+pub struct DocxParser;
+
+impl DocumentParser for DocxParser {
+    fn id(&self) -> &str {
+        DocumentFormat::Docx.as_str()
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["docx"]
+    }
+
+    fn mime_types(&self) -> &[&str] {
+        &["application/vnd.openxmlformats-officedocument.wordprocessingml.document"]
+    }
+
+    fn parse(&self, bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
+        parse_docx(bytes)
+    }
+
+    fn parse_with_options(
+        &self,
+        bytes: &[u8],
+        options: &ParseOptions,
+    ) -> Result<ParsedDocument, ParseError> {
+        parse_docx_with_options(bytes, options)
+    }
+}
+
+pub fn parse_docx(bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
+    parse_docx_with_options(bytes, &ParseOptions::default())
+}
+
+/// Parse a DOCX, reading tracked changes as `options.tracked_changes` says
+/// and returning the files of `word/media` as `options.media` says.
+/// The number of tracked insertions and deletions in the main text is
+/// recorded in the metadata as `tracked_insertions` and `tracked_deletions`.
+pub fn parse_docx_with_options(
+    bytes: &[u8],
+    options: &ParseOptions,
+) -> Result<ParsedDocument, ParseError> {
+    let cursor = Cursor::new(bytes);
+    let mut archive = zip::ZipArchive::new(cursor)
+        .map_err(|e| ParseError::Format(format!("Not a valid DOCX/ZIP: {}", e)))?;
+
+    let xml_content = read_part(&mut archive, "word/document.xml")?
+        .ok_or_else(|| ParseError::Format("Missing word/document.xml".into()))?;
+
+    let mut context = Context {
+        tracked_changes: options.tracked_changes,
+        ..Context::default()
+    };
+    if let Some(xml) = read_part(&mut archive, "word/styles.xml")? {
+        context.styles = Styles::parse(&xml)?;
+    }
+    if let Some(xml) = read_part(&mut archive, "word/numbering.xml")? {
+        context.numbering = Numbering::parse(&xml)?;
+    }
+
+    let relationships = ooxml::read_relationships(&mut archive, "word/document.xml");
+    let story = body::read_story(&xml_content, &context, &relationships)?;
+    let stories = stories::read_stories(&mut archive, &story, &relationships, &context)?;
+    let mut blocks = story.blocks;
+    blocks.extend(stories.sections);
+
+    let mut metadata = DocumentMetadata::new(DocumentFormat::Docx.as_str());
+    ooxml::read_properties(&mut archive, &mut metadata);
+    for (name, count) in [
+        ("tracked_insertions", story.insertions),
+        ("tracked_deletions", story.deletions),
+    ] {
+        if count > 0 {
+            metadata.extra.insert(name.into(), count.into());
+        }
+    }
+
+    let mut parsed = ParsedDocument::new(bytes, blocks, metadata);
+    parsed.links = story.links;
+    parsed.links.extend(stories.links);
+    parsed.annotations = stories.annotations;
+    parsed.attachments =
+        ooxml::read_media(&mut archive, "word/media/", &parsed.blocks, options.media);
+    Ok(parsed)
+}
+
+/// The value of an on/off property such as `<w:b/>` or `<w:b w:val="0"/>`.
+fn toggle(e: &BytesStart) -> bool {
+    !matches!(
+        attr(e, b"w:val").as_deref(),
+        Some("0" | "false" | "off" | "none")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document_parsing::{
+        render_markdown, Annotation, Block, Link, MediaMode, SectionKind, Table, TrackedChanges,
+    };
+    use std::io::Write;
+
+    fn create_docx(body: &str) -> Vec<u8> {
+        create_docx_with_parts(body, &[])
+    }
+
+    /// A package with `body` in `word/document.xml` and the given extra parts.
+    fn create_docx_with_parts(body: &str, parts: &[(&str, &str)]) -> Vec<u8> {
+        let buf = Vec::new();
+        let cursor = Cursor::new(buf);
+        let mut zip = zip::ZipWriter::new(cursor);
+
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, content) in parts {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.start_file("word/document.xml", options).unwrap();
+        let xml = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+  <w:body>
+    {}
+  </w:body>
+</w:document>"#,
+            body
+        );
+        zip.write_all(xml.as_bytes()).unwrap();
+
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn create_minimal_docx(text: &str) -> Vec<u8> {
+        create_docx(&format!("<w:p><w:r><w:t>{}</w:t></w:r></w:p>", text))
+    }
+
+    #[test]
+    fn test_parse_docx_basic() {
+        let docx_bytes = create_minimal_docx("Hello World");
+        let result = parse_docx(&docx_bytes).expect("Failed to parse DOCX");
+        assert!(result.text.contains("Hello World"));
+    }
+
+    #[test]
+    fn test_parse_docx_invalid() {
+        let result = parse_docx(b"not a zip");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_docx_structure() {
+        let docx_bytes = create_docx(
+            r#"<w:p><w:pPr><w:pStyle w:val="Heading2"/></w:pPr><w:r><w:t>Scope</w:t></w:r></w:p>
+    <w:p><w:pPr><w:numPr><w:ilvl w:val="1"/><w:numId w:val="1"/></w:numPr></w:pPr><w:r><w:t>item</w:t></w:r></w:p>
+    <w:tbl><w:tr>
+      <w:tc><w:p><w:r><w:t>a</w:t></w:r></w:p></w:tc>
+      <w:tc><w:p><w:r><w:t>b</w:t></w:r></w:p></w:tc>
+    </w:tr></w:tbl>
+    <w:p><w:r><w:t>end</w:t><w:br w:type="page"/></w:r></w:p>"#,
+        );
+        let result = parse_docx(&docx_bytes).expect("Failed to parse DOCX");
+        assert_eq!(
+            result.blocks,
+            vec![
+                Block::heading(2, "Scope"),
+                Block::ListItem {
+                    ordered: false,
+                    level: 1,
+                    text: "item".into()
+                },
+                Block::Table(Table {
+                    rows: vec![vec!["a".into(), "b".into()]],
+                    header: false,
+                }),
+                Block::paragraph("end"),
+                Block::PageBreak,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_docx_styles_and_numbering() {
+        let styles = r#"<w:styles xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+  <w:style w:type="paragraph" w:styleId="Titel"><w:name w:val="Title"/></w:style>
+  <w:style w:type="paragraph" w:styleId="berschrift1"><w:name w:val="heading 1"/></w:style>
+  <w:style w:type="paragraph" w:styleId="ListNumber"><w:name w:val="List Number"/>
+    <w:pPr><w:numPr><w:numId w:val="2"/></w:numPr></w:pPr></w:style>
+  <w:style w:type="character" w:styleId="Emph"><w:rPr><w:i/></w:rPr></w:style>
+</w:styles>"#;
+        let numbering = r#"<w:numbering xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+  <w:abstractNum w:abstractNumId="10"><w:lvl w:ilvl="0"><w:numFmt w:val="bullet"/></w:lvl></w:abstractNum>
+  <w:abstractNum w:abstractNumId="20">
+    <w:lvl w:ilvl="0"><w:numFmt w:val="decimal"/></w:lvl>
+    <w:lvl w:ilvl="1"><w:numFmt w:val="lowerLetter"/></w:lvl>
+  </w:abstractNum>
+  <w:num w:numId="1"><w:abstractNumId w:val="10"/></w:num>
+  <w:num w:numId="2"><w:abstractNumId w:val="20"/></w:num>
+</w:numbering>"#;
+        let docx_bytes = create_docx_with_parts(
+            r#"<w:p><w:pPr><w:pStyle w:val="Titel"/></w:pPr><w:r><w:t>Agreement</w:t></w:r></w:p>
+    <w:p><w:pPr><w:pStyle w:val="berschrift1"/></w:pPr><w:r><w:t>Parties</w:t></w:r></w:p>
+    <w:p><w:pPr><w:pStyle w:val="ListNumber"/></w:pPr><w:r><w:t>First</w:t></w:r></w:p>
+    <w:p><w:pPr><w:pStyle w:val="ListNumber"/><w:numPr><w:ilvl w:val="1"/></w:numPr></w:pPr><w:r><w:t>Sub</w:t></w:r></w:p>
+    <w:p><w:pPr><w:numPr><w:ilvl w:val="0"/><w:numId w:val="1"/></w:numPr></w:pPr>
+      <w:r><w:t xml:space="preserve">A </w:t></w:r><w:r><w:rPr><w:rStyle w:val="Emph"/></w:rPr><w:t>bullet</w:t></w:r></w:p>
+    <w:p><w:pPr><w:pStyle w:val="ListNumber"/><w:numPr><w:numId w:val="0"/></w:numPr></w:pPr><w:r><w:t>Not a list</w:t></w:r></w:p>"#,
+            &[
+                ("word/styles.xml", styles),
+                ("word/numbering.xml", numbering),
+            ],
+        );
+        let result = parse_docx(&docx_bytes).expect("Failed to parse DOCX");
+        assert_eq!(
+            result.blocks,
+            vec![
+                Block::heading(1, "Agreement"),
+                Block::heading(1, "Parties"),
+                Block::ListItem {
+                    ordered: true,
+                    level: 0,
+                    text: "First".into()
+                },
+                Block::ListItem {
+                    ordered: true,
+                    level: 1,
+                    text: "Sub".into()
+                },
+                Block::ListItem {
+                    ordered: false,
+                    level: 0,
+                    text: "A *bullet*".into()
+                },
+                Block::paragraph("Not a list"),
+            ]
+        );
+        assert_eq!(
+            render_markdown(&result.blocks),
+            "# Agreement\n\n# Parties\n\n1. First\n   1. Sub\n- A *bullet*\n\nNot a list"
+        );
+    }
+
+    #[test]
+    fn test_parse_docx_properties() {
+        let docx_bytes = create_docx_with_parts(
+            "<w:p><w:r><w:t>Body</w:t></w:r></w:p>",
+            &crate::document_parsing::parsers::ooxml::test_support::PROPERTY_PARTS,
+        );
+        let result = parse_docx(&docx_bytes).expect("Failed to parse DOCX");
+        assert_eq!(
+            result.metadata.title.as_deref(),
+            Some("Master Services Agreement")
+        );
+        assert_eq!(result.metadata.page_count, Some(3));
+        assert_eq!(result.metadata.extra["last_modified_by"], "Grace Hopper");
+        assert_eq!(result.metadata.word_count, 1);
+    }
+
+    #[test]
+    fn test_parse_docx_headers_notes_and_comments() {
+        let rels = r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+  <Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/header" Target="header1.xml"/>
+  <Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/header" Target="header2.xml"/>
+  <Relationship Id="rId3" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/footer" Target="footer1.xml"/>
+  <Relationship Id="rId4" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/footnotes" Target="footnotes.xml"/>
+  <Relationship Id="rId5" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/comments" Target="comments.xml"/>
+</Relationships>"#;
+        let footnotes = r#"<w:footnotes>
+  <w:footnote w:type="separator" w:id="-1"><w:p><w:r><w:separator/></w:r></w:p></w:footnote>
+  <w:footnote w:id="1"><w:p><w:r><w:footnoteRef/></w:r><w:r><w:t xml:space="preserve"> See Annex A.</w:t></w:r></w:p></w:footnote>
+</w:footnotes>"#;
+        let comments = r#"<w:comments>
+  <w:comment w:id="0" w:author="Grace Hopper" w:date="2024-03-01T10:00:00Z"><w:p><w:r><w:annotationRef/></w:r><w:r><w:t>Too vague.</w:t></w:r></w:p></w:comment>
+</w:comments>"#;
+        let docx_bytes = create_docx_with_parts(
+            r#"<w:p><w:r><w:t xml:space="preserve">The </w:t></w:r><w:commentRangeStart w:id="0"/><w:r><w:t>services</w:t></w:r>
+      <w:r><w:rPr><w:rStyle w:val="FootnoteReference"/></w:rPr><w:footnoteReference w:id="1"/></w:r>
+      <w:r><w:t xml:space="preserve"> are listed</w:t></w:r><w:commentRangeEnd w:id="0"/><w:r><w:commentReference w:id="0"/></w:r><w:r><w:t>.</w:t></w:r></w:p>
+    <w:sectPr>
+      <w:headerReference w:type="default" r:id="rId1"/>
+      <w:headerReference w:type="first" r:id="rId2"/>
+      <w:footerReference w:type="default" r:id="rId3"/>
+      <w:footerReference w:type="first" r:id="rId3"/>
+    </w:sectPr>"#,
+            &[
+                ("word/_rels/document.xml.rels", rels),
+                (
+                    "word/header1.xml",
+                    "<w:hdr><w:p><w:r><w:t>ACME Corp</w:t></w:r></w:p></w:hdr>",
+                ),
+                ("word/header2.xml", "<w:hdr><w:p/></w:hdr>"),
+                (
+                    "word/footer1.xml",
+                    "<w:ftr><w:p><w:r><w:t>Confidential</w:t></w:r></w:p></w:ftr>",
+                ),
+                ("word/footnotes.xml", footnotes),
+                ("word/comments.xml", comments),
+            ],
+        );
+        let result = parse_docx(&docx_bytes).expect("Failed to parse DOCX");
+        let section = |kind, title: Option<&str>, text: &str| Block::Section {
+            kind,
+            number: 1,
+            title: title.map(String::from),
+            blocks: vec![Block::paragraph(text)],
+        };
+        assert_eq!(
+            result.blocks,
+            vec![
+                Block::paragraph("The services[^1] are listed[^c0]."),
+                section(SectionKind::Header, None, "ACME Corp"),
+                section(SectionKind::Footer, None, "Confidential"),
+                section(SectionKind::Footnotes, None, "[^1]: See Annex A."),
+                section(
+                    SectionKind::Comments,
+                    None,
+                    "[^c0]: Grace Hopper: Too vague."
+                ),
+            ]
+        );
+        assert_eq!(
+            result.annotations,
+            [Annotation {
+                kind: "comment".into(),
+                text: "Too vague.".into(),
+                author: Some("Grace Hopper".into()),
+                modified: Some("2024-03-01T10:00:00Z".into()),
+                page: None,
+                anchor: Some("services are listed".into()),
+            }]
+        );
+        assert!(result
+            .text
+            .contains("--- Header 1 ---\nACME Corp\n\n--- Footer 1 ---\nConfidential"));
+    }
+
+    #[test]
+    fn test_parse_docx_tracked_changes() {
+        let docx_bytes = create_docx(
+            r#"<w:p><w:r><w:t xml:space="preserve">Term: </w:t></w:r><w:del w:id="1" w:author="Ada"><w:r><w:delText>one</w:delText></w:r></w:del><w:ins w:id="2" w:author="Ada"><w:r><w:t>two</w:t></w:r></w:ins><w:r><w:t xml:space="preserve"> years</w:t></w:r></w:p>"#,
+        );
+        let parse = |tracked_changes| {
+            let options = ParseOptions {
+                tracked_changes,
+                ..ParseOptions::default()
+            };
+            DocxParser
+                .parse_with_options(&docx_bytes, &options)
+                .unwrap()
+        };
+        assert_eq!(parse_docx(&docx_bytes).unwrap().text, "Term: two years");
+        assert_eq!(parse(TrackedChanges::Reject).text, "Term: one years");
+        let shown = parse(TrackedChanges::Show);
+        assert_eq!(
+            shown.text,
+            "Term: {--one--}{>>Ada<<}{++two++}{>>Ada<<} years"
+        );
+        assert_eq!(shown.metadata.extra["tracked_insertions"], 1);
+        assert_eq!(shown.metadata.extra["tracked_deletions"], 1);
+    }
+
+    #[test]
+    fn test_parse_docx_hyperlinks() {
+        let rels = r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+  <Relationship Id="rId4" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink" Target="https://example.com/terms (v2)" TargetMode="External"/>
+</Relationships>"#;
+        let docx_bytes = create_docx_with_parts(
+            r#"<w:p><w:r><w:t xml:space="preserve">Read the </w:t></w:r><w:hyperlink r:id="rId4" w:history="1"><w:r><w:t>terms</w:t></w:r></w:hyperlink></w:p>"#,
+            &[("word/_rels/document.xml.rels", rels)],
+        );
+        let result = parse_docx(&docx_bytes).expect("Failed to parse DOCX");
+        assert_eq!(
+            result.text,
+            "Read the [terms](<https://example.com/terms (v2)>)"
+        );
+        assert_eq!(
+            result.links,
+            [Link {
+                uri: "https://example.com/terms (v2)".into(),
+                text: Some("terms".into()),
+                page: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_docx_media() {
+        let rels = r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+  <Relationship Id="rId7" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/image" Target="media/image1.png"/>
+</Relationships>"#;
+        let docx_bytes = create_docx_with_parts(
+            r#"<w:p><w:r><w:t>Intro</w:t></w:r></w:p>
+<w:p><w:r><w:drawing><wp:inline><wp:docPr id="1" name="Picture 1" descr="Site plan"/>
+  <a:graphic><a:graphicData><pic:pic><pic:blipFill><a:blip r:embed="rId7"/></pic:blipFill></pic:pic></a:graphicData></a:graphic>
+</wp:inline></w:drawing></w:r></w:p>"#,
+            &[
+                ("word/_rels/document.xml.rels", rels),
+                ("word/media/image1.png", "GIF89a\x01\x00\x01\x00"),
+                ("word/media/unused.svg", "<svg/>"),
+            ],
+        );
+
+        let off = parse_docx(&docx_bytes).expect("Failed to parse DOCX");
+        assert!(off.attachments.is_empty());
+
+        let options = ParseOptions {
+            media: MediaMode::Hash,
+            ..Default::default()
+        };
+        let hashed = parse_docx_with_options(&docx_bytes, &options).expect("Failed to parse DOCX");
+        let [image, unused] = hashed.attachments.as_slice() else {
+            panic!("expected two attachments, got {:?}", hashed.attachments);
+        };
+        assert_eq!(image.name, "word/media/image1.png");
+        assert_eq!(image.mime_type, "image/gif");
+        assert_eq!(image.size, 10);
+        assert_eq!(
+            image.sha256,
+            crate::document_parsing::metadata::sha256_hex(b"GIF89a\x01\x00\x01\x00")
+        );
+        assert_eq!(image.data, None);
+        assert_eq!(image.alt.as_deref(), Some("Site plan"));
+        assert_eq!(image.offset, hashed.text.find("[Image: Site plan]"));
+        assert!(image.offset.is_some());
+        assert_eq!(unused.name, "word/media/unused.svg");
+        assert_eq!(unused.mime_type, "image/svg+xml");
+        assert_eq!((unused.offset, unused.alt.as_deref()), (None, None));
+
+        let options = ParseOptions {
+            media: MediaMode::Bytes,
+            ..Default::default()
+        };
+        let bytes = parse_docx_with_options(&docx_bytes, &options).expect("Failed to parse DOCX");
+        assert_eq!(
+            bytes.attachments[0].data.as_deref(),
+            Some(&b"GIF89a\x01\x00\x01\x00"[..])
+        );
+    }
+}
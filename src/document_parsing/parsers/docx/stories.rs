@@ -0,0 +1,243 @@
+//! Stories outside the main text: headers and footers, footnotes, endnotes
+//! and comments.
+//!
+//! Each is returned as a [`Block::Section`] to follow the main text. A
+//! reference to a note or comment is written into the text as a Markdown
+//! footnote reference (`[^1]` for footnote 1, `[^e1]` for endnote 1, `[^c1]`
+//! for comment 1), and the note itself opens with the matching label, so
+//! `[^1]: ` in the footnotes section.
+
+use super::attr;
+use super::body::{read_story, Context, HeaderFooterRef, Story, StoryReader};
+use super::ooxml::{read_part, read_relationships, Relationship};
+use crate::document_parsing::metadata::iso_date_to_rfc3339;
+use crate::document_parsing::{render_text, Annotation, Block, Link, ParseError, SectionKind};
+use quick_xml::events::Event;
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Seek};
+use zip::ZipArchive;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum NoteKind {
+    Footnote,
+    Endnote,
+    Comment,
+}
+
+impl NoteKind {
+    /// The reference to note `id` written into the text, e.g. `[^e2]`.
+    pub fn reference(self, id: &str) -> String {
+        let prefix = match self {
+            NoteKind::Footnote => "",
+            NoteKind::Endnote => "e",
+            NoteKind::Comment => "c",
+        };
+        format!("[^{}{}]", prefix, id)
+    }
+
+    /// The part holding the notes, its relationship type and its default
+    /// name, and the element of each note.
+    fn part(self) -> (&'static str, &'static str, &'static [u8]) {
+        match self {
+            NoteKind::Footnote => ("footnotes", "word/footnotes.xml", b"w:footnote"),
+            NoteKind::Endnote => ("endnotes", "word/endnotes.xml", b"w:endnote"),
+            NoteKind::Comment => ("comments", "word/comments.xml", b"w:comment"),
+        }
+    }
+
+    fn section(self) -> SectionKind {
+        match self {
+            NoteKind::Footnote => SectionKind::Footnotes,
+            NoteKind::Endnote => SectionKind::Endnotes,
+            NoteKind::Comment => SectionKind::Comments,
+        }
+    }
+}
+
+/// A footnote, endnote or comment.
+struct Note {
+    id: String,
+    author: Option<String>,
+    date: Option<String>,
+    blocks: Vec<Block>,
+}
+
+/// What was read from the stories outside the main one.
+#[derive(Debug, Default)]
+pub(super) struct Stories {
+    pub sections: Vec<Block>,
+    /// The comments, anchored to the text they were made on.
+    pub annotations: Vec<Annotation>,
+    pub links: Vec<Link>,
+}
+
+/// Read the stories outside `main`, the document's main story.
+/// `relationships` are those of the main part.
+pub(super) fn read_stories<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    main: &Story,
+    relationships: &HashMap<String, Relationship>,
+    context: &Context,
+) -> Result<Stories, ParseError> {
+    let mut stories = Stories::default();
+    for footer in [false, true] {
+        let references = main
+            .headers_and_footers
+            .iter()
+            .filter(|r| r.footer == footer);
+        read_headers_or_footers(archive, references, relationships, context, &mut stories)?;
+    }
+
+    for kind in [NoteKind::Footnote, NoteKind::Endnote, NoteKind::Comment] {
+        let (relationship, default, _) = kind.part();
+        let name = relationships
+            .values()
+            .find(|r| r.kind == relationship && !r.external)
+            .map_or(default, |r| r.target.as_str());
+        let Some(xml) = read_part(archive, name)? else {
+            continue;
+        };
+        let part_relationships = read_relationships(archive, name);
+        let notes = read_notes(&xml, kind, context, &part_relationships, &mut stories.links)?;
+        if kind == NoteKind::Comment {
+            stories
+                .annotations
+                .extend(notes.iter().map(|note| Annotation {
+                    kind: "comment".into(),
+                    text: render_text(&note.blocks),
+                    author: note.author.clone(),
+                    modified: note.date.as_deref().and_then(iso_date_to_rfc3339),
+                    page: None,
+                    anchor: main.comment_anchors.get(&note.id).cloned(),
+                }));
+        }
+
+        let blocks: Vec<Block> = notes
+            .into_iter()
+            .flat_map(|note| labelled(kind, note))
+            .collect();
+        if !blocks.is_empty() {
+            stories.sections.push(Block::Section {
+                kind: kind.section(),
+                number: 1,
+                title: None,
+                blocks,
+            });
+        }
+    }
+    Ok(stories)
+}
+
+/// Add a section for each distinct header (or footer) part referenced, in
+/// document order, leaving out empty ones.
+fn read_headers_or_footers<'a, R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    references: impl Iterator<Item = &'a HeaderFooterRef>,
+    relationships: &HashMap<String, Relationship>,
+    context: &Context,
+    stories: &mut Stories,
+) -> Result<(), ParseError> {
+    let mut seen = HashSet::new();
+    let mut number = 0;
+    for reference in references {
+        let Some(relationship) = relationships.get(&reference.id).filter(|r| !r.external) else {
+            continue;
+        };
+        if !seen.insert(relationship.target.as_str()) {
+            continue;
+        }
+        let Some(xml) = read_part(archive, &relationship.target)? else {
+            continue;
+        };
+        let part_relationships = read_relationships(archive, &relationship.target);
+        let story = read_story(&xml, context, &part_relationships)?;
+        stories.links.extend(story.links);
+        if story.blocks.is_empty() {
+            continue;
+        }
+        number += 1;
+        stories.sections.push(Block::Section {
+            kind: if reference.footer {
+                SectionKind::Footer
+            } else {
+                SectionKind::Header
+            },
+            number,
+            title: match reference.kind.as_deref() {
+                Some("first") => Some("first page".into()),
+                Some("even") => Some("even pages".into()),
+                _ => None,
+            },
+            blocks: story.blocks,
+        });
+    }
+    Ok(())
+}
+
+/// The notes of a notes part, leaving out the separators Word keeps there.
+fn read_notes(
+    xml: &str,
+    kind: NoteKind,
+    context: &Context,
+    relationships: &HashMap<String, Relationship>,
+    links: &mut Vec<Link>,
+) -> Result<Vec<Note>, ParseError> {
+    let (_, _, element) = kind.part();
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut notes = Vec::new();
+    let mut current: Option<(Note, StoryReader)> = None;
+    let mut buf = Vec::new();
+
+    loop {
+        let event = reader
+            .read_event_into(&mut buf)
+            .map_err(|e| ParseError::Format(format!("XML parse error: {}", e)))?;
+        match &event {
+            Event::Start(e) if e.name().as_ref() == element => {
+                let separator = attr(e, b"w:type").is_some_and(|t| t != "normal");
+                current = attr(e, b"w:id").filter(|_| !separator).map(|id| {
+                    let note = Note {
+                        id,
+                        author: attr(e, b"w:author").filter(|a| !a.is_empty()),
+                        date: attr(e, b"w:date"),
+                        blocks: Vec::new(),
+                    };
+                    (note, StoryReader::new(context, relationships))
+                });
+            }
+            Event::End(e) if e.name().as_ref() == element => {
+                if let Some((mut note, story)) = current.take() {
+                    let story = story.finish();
+                    links.extend(story.links);
+                    note.blocks = story.blocks;
+                    notes.push(note);
+                }
+            }
+            Event::Eof => break,
+            event => {
+                if let Some((_, story)) = current.as_mut() {
+                    story.event(event);
+                }
+            }
+        }
+        buf.clear();
+    }
+    Ok(notes)
+}
+
+/// The blocks of a note, opening with its label; none for an empty note.
+fn labelled(kind: NoteKind, note: Note) -> Vec<Block> {
+    if note.blocks.is_empty() {
+        return Vec::new();
+    }
+    let mut label = format!("{}:", kind.reference(&note.id));
+    if let (NoteKind::Comment, Some(author)) = (kind, &note.author) {
+        label = format!("{} {}:", label, author);
+    }
+    let mut blocks = note.blocks;
+    match blocks.first_mut() {
+        Some(Block::Paragraph { text }) => *text = format!("{} {}", label, text),
+        _ => blocks.insert(0, Block::paragraph(label)),
+    }
+    blocks
+}
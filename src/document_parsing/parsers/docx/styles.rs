@@ -0,0 +1,299 @@
+//! Paragraph and character styles (`word/styles.xml`) and list definitions
+//! (`word/numbering.xml`).
+
+use super::{attr, toggle};
+use crate::document_parsing::ParseError;
+use quick_xml::events::Event;
+use std::collections::HashMap;
+
+/// Styles are looked up through at most this many `w:basedOn` links.
+const MAX_BASED_ON: usize = 16;
+
+/// A list a paragraph belongs to: `w:numPr`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(super) struct NumberingRef {
+    pub num_id: Option<String>,
+    pub level: Option<u8>,
+}
+
+/// Character formatting that is rendered as emphasis.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(super) struct Emphasis {
+    pub bold: Option<bool>,
+    pub italic: Option<bool>,
+}
+
+#[derive(Debug, Default)]
+struct Style {
+    name: Option<String>,
+    based_on: Option<String>,
+    outline_level: Option<u8>,
+    numbering: Option<NumberingRef>,
+    emphasis: Emphasis,
+}
+
+/// The styles of a document, by style id.
+#[derive(Debug, Default)]
+pub(super) struct Styles {
+    styles: HashMap<String, Style>,
+}
+
+impl Styles {
+    pub fn parse(xml: &str) -> Result<Self, ParseError> {
+        let mut reader = quick_xml::Reader::from_str(xml);
+        let mut styles = HashMap::new();
+        let mut current: Option<(String, Style)> = None;
+        let mut in_run_properties = false;
+        let mut buf = Vec::new();
+
+        loop {
+            let event = reader
+                .read_event_into(&mut buf)
+                .map_err(|e| ParseError::Format(format!("styles.xml: {}", e)))?;
+            match event {
+                Event::Start(ref e) | Event::Empty(ref e) => {
+                    let empty = matches!(event, Event::Empty(_));
+                    match e.name().as_ref() {
+                        b"w:style" if !empty => {
+                            current = attr(e, b"w:styleId").map(|id| (id, Style::default()));
+                        }
+                        b"w:rPr" if !empty => in_run_properties = true,
+                        _ => {
+                            if let Some((_, style)) = current.as_mut() {
+                                style.read_property(e, in_run_properties);
+                            }
+                        }
+                    }
+                }
+                Event::End(ref e) => match e.name().as_ref() {
+                    b"w:style" => {
+                        if let Some((id, style)) = current.take() {
+                            styles.insert(id, style);
+                        }
+                    }
+                    b"w:rPr" => in_run_properties = false,
+                    _ => {}
+                },
+                Event::Eof => break,
+                _ => {}
+            }
+            buf.clear();
+        }
+        Ok(Self { styles })
+    }
+
+    /// The styles `id` is based on, starting with itself.
+    fn chain<'a>(&'a self, id: &'a str) -> impl Iterator<Item = &'a Style> + 'a {
+        let mut next = Some(id);
+        std::iter::from_fn(move || {
+            let style = self.styles.get(next?)?;
+            next = style.based_on.as_deref();
+            Some(style)
+        })
+        .take(MAX_BASED_ON)
+    }
+
+    /// The heading level of paragraph style `id`, from its id or name
+    /// ("heading 1", which stays English in localised documents) or its
+    /// outline level, looking through the styles it is based on.
+    pub fn heading_level(&self, id: &str) -> Option<u8> {
+        if let Some(level) = heading_level_of_name(id) {
+            return Some(level);
+        }
+        self.chain(id).find_map(|style| {
+            style
+                .name
+                .as_deref()
+                .and_then(heading_level_of_name)
+                .or(style.outline_level.map(|level| level + 1))
+        })
+    }
+
+    /// The list of paragraph style `id`, e.g. for "List Number" styles.
+    pub fn numbering(&self, id: &str) -> Option<NumberingRef> {
+        self.chain(id).find_map(|style| style.numbering.clone())
+    }
+
+    /// The emphasis of character style `id`.
+    pub fn emphasis(&self, id: &str) -> Emphasis {
+        let mut emphasis = Emphasis::default();
+        for style in self.chain(id) {
+            emphasis.bold = emphasis.bold.or(style.emphasis.bold);
+            emphasis.italic = emphasis.italic.or(style.emphasis.italic);
+        }
+        emphasis
+    }
+}
+
+impl Style {
+    fn read_property(&mut self, e: &quick_xml::events::BytesStart, in_run_properties: bool) {
+        match e.name().as_ref() {
+            b"w:name" => self.name = attr(e, b"w:val"),
+            b"w:basedOn" => self.based_on = attr(e, b"w:val"),
+            b"w:outlineLvl" => {
+                // Level 9 is body text.
+                self.outline_level = attr(e, b"w:val")
+                    .and_then(|v| v.parse().ok())
+                    .filter(|&level| level < 9);
+            }
+            b"w:numId" => {
+                self.numbering.get_or_insert_with(Default::default).num_id = attr(e, b"w:val");
+            }
+            b"w:ilvl" => {
+                self.numbering.get_or_insert_with(Default::default).level =
+                    attr(e, b"w:val").and_then(|v| v.parse().ok());
+            }
+            b"w:b" if in_run_properties => self.emphasis.bold = Some(toggle(e)),
+            b"w:i" if in_run_properties => self.emphasis.italic = Some(toggle(e)),
+            _ => {}
+        }
+    }
+}
+
+/// `Title`, `Heading3` or `heading 3`.
+fn heading_level_of_name(name: &str) -> Option<u8> {
+    let name = name.to_ascii_lowercase();
+    if name == "title" {
+        return Some(1);
+    }
+    name.strip_prefix("heading")?
+        .trim_start()
+        .parse()
+        .ok()
+        .filter(|level| (1..=9).contains(level))
+}
+
+/// List definitions: whether each level of each list is numbered or bulleted.
+#[derive(Debug, Default)]
+pub(super) struct Numbering {
+    /// Number format of each level of each abstract list.
+    abstract_formats: HashMap<String, HashMap<u8, String>>,
+    /// Abstract list of each list instance, and its per-level overrides.
+    instances: HashMap<String, (String, HashMap<u8, String>)>,
+}
+
+impl Numbering {
+    pub fn parse(xml: &str) -> Result<Self, ParseError> {
+        let mut reader = quick_xml::Reader::from_str(xml);
+        let mut numbering = Self::default();
+        let mut abstract_id: Option<String> = None;
+        let mut num_id: Option<String> = None;
+        let mut level: Option<u8> = None;
+        let mut buf = Vec::new();
+
+        loop {
+            let event = reader
+                .read_event_into(&mut buf)
+                .map_err(|e| ParseError::Format(format!("numbering.xml: {}", e)))?;
+            match event {
+                Event::Start(ref e) | Event::Empty(ref e) => match e.name().as_ref() {
+                    b"w:abstractNum" => abstract_id = attr(e, b"w:abstractNumId"),
+                    b"w:num" => {
+                        num_id = attr(e, b"w:numId");
+                        if let Some(id) = &num_id {
+                            numbering.instances.entry(id.clone()).or_default();
+                        }
+                    }
+                    b"w:abstractNumId" => {
+                        if let (Some(id), Some(target)) = (&num_id, attr(e, b"w:val")) {
+                            numbering.instances.entry(id.clone()).or_default().0 = target;
+                        }
+                    }
+                    b"w:lvl" | b"w:lvlOverride" => {
+                        level = attr(e, b"w:ilvl").and_then(|v| v.parse().ok());
+                    }
+                    b"w:numFmt" => {
+                        let (Some(level), Some(format)) = (level, attr(e, b"w:val")) else {
+                            continue;
+                        };
+                        let formats = match (&num_id, &abstract_id) {
+                            (Some(id), _) => {
+                                &mut numbering.instances.entry(id.clone()).or_default().1
+                            }
+                            (None, Some(id)) => {
+                                numbering.abstract_formats.entry(id.clone()).or_default()
+                            }
+                            (None, None) => continue,
+                        };
+                        formats.insert(level, format);
+                    }
+                    _ => {}
+                },
+                Event::End(ref e) => match e.name().as_ref() {
+                    b"w:abstractNum" => abstract_id = None,
+                    b"w:num" => num_id = None,
+                    _ => {}
+                },
+                Event::Eof => break,
+                _ => {}
+            }
+            buf.clear();
+        }
+        Ok(numbering)
+    }
+
+    /// Whether level `level` of list `num_id` is numbered rather than
+    /// bulleted. Unknown lists count as bulleted.
+    pub fn is_ordered(&self, num_id: &str, level: u8) -> bool {
+        let Some((abstract_id, overrides)) = self.instances.get(num_id) else {
+            return false;
+        };
+        let format = overrides.get(&level).or_else(|| {
+            self.abstract_formats
+                .get(abstract_id)
+                .and_then(|formats| formats.get(&level))
+        });
+        format.is_some_and(|format| format != "bullet" && format != "none")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heading_levels_from_styles() {
+        let styles = Styles::parse(
+            r#"<w:styles xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+  <w:style w:type="paragraph" w:styleId="berschrift2"><w:name w:val="heading 2"/></w:style>
+  <w:style w:type="paragraph" w:styleId="Chapter"><w:name w:val="Chapter"/><w:pPr><w:outlineLvl w:val="0"/></w:pPr></w:style>
+  <w:style w:type="paragraph" w:styleId="ChapterNote"><w:basedOn w:val="Chapter"/></w:style>
+  <w:style w:type="paragraph" w:styleId="Body"><w:pPr><w:outlineLvl w:val="9"/></w:pPr></w:style>
+  <w:style w:type="character" w:styleId="Strong"><w:rPr><w:b/><w:i w:val="0"/></w:rPr></w:style>
+</w:styles>"#,
+        )
+        .unwrap();
+        assert_eq!(styles.heading_level("berschrift2"), Some(2));
+        assert_eq!(styles.heading_level("ChapterNote"), Some(1));
+        assert_eq!(styles.heading_level("Heading4"), Some(4));
+        assert_eq!(styles.heading_level("Body"), None);
+        assert_eq!(
+            styles.emphasis("Strong"),
+            Emphasis {
+                bold: Some(true),
+                italic: Some(false)
+            }
+        );
+    }
+
+    #[test]
+    fn test_numbered_and_bulleted_levels() {
+        let numbering = Numbering::parse(
+            r#"<w:numbering xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+  <w:abstractNum w:abstractNumId="0">
+    <w:lvl w:ilvl="0"><w:numFmt w:val="decimal"/></w:lvl>
+    <w:lvl w:ilvl="1"><w:numFmt w:val="bullet"/></w:lvl>
+  </w:abstractNum>
+  <w:num w:numId="1"><w:abstractNumId w:val="0"/></w:num>
+  <w:num w:numId="2"><w:abstractNumId w:val="0"/>
+    <w:lvlOverride w:ilvl="0"><w:lvl w:ilvl="0"><w:numFmt w:val="bullet"/></w:lvl></w:lvlOverride>
+  </w:num>
+</w:numbering>"#,
+        )
+        .unwrap();
+        assert!(numbering.is_ordered("1", 0));
+        assert!(!numbering.is_ordered("1", 1));
+        assert!(!numbering.is_ordered("2", 0));
+        assert!(!numbering.is_ordered("9", 0));
+    }
+}
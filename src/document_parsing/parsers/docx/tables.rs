@@ -0,0 +1,182 @@
+//! Tables: `w:tbl`, its rows `w:tr` and cells `w:tc`.
+//!
+//! Rows are laid out on the table grid, so a cell spanning columns
+//! (`w:gridSpan`) is followed by empty cells for the columns it covers, and a
+//! cell continuing a vertical merge (`w:vMerge`) is left empty below the cell
+//! that holds the text. A table nested in a cell is written into the cell's
+//! text, one row per line with ` | ` between cells.
+
+use super::attr;
+use crate::document_parsing::{render_text, Block, Table};
+use quick_xml::events::BytesStart;
+
+/// A table being read.
+#[derive(Debug, Default)]
+pub(super) struct TableBuilder {
+    table: Table,
+    row: Vec<String>,
+    /// Blocks of the current cell.
+    pub cell: Vec<Block>,
+    /// Grid columns the current cell spans.
+    span: usize,
+    /// Whether the current cell continues a vertical merge from the row
+    /// above.
+    merged: bool,
+    /// Empty grid columns before the first cell of the row.
+    skipped: usize,
+    repeated_header: bool,
+}
+
+impl TableBuilder {
+    /// Handle a table, row or cell property.
+    pub fn property(&mut self, e: &BytesStart) {
+        let number = || attr(e, b"w:val").and_then(|v| v.parse::<usize>().ok());
+        match e.name().as_ref() {
+            b"w:gridSpan" => self.span = number().unwrap_or(1).max(1),
+            b"w:vMerge" => self.merged = attr(e, b"w:val").as_deref() != Some("restart"),
+            b"w:gridBefore" => self.skipped = number().unwrap_or(0),
+            // Only a repeated first row is a header; a header row further down
+            // marks nothing.
+            b"w:tblHeader" if self.table.rows.is_empty() => {
+                self.repeated_header = super::toggle(e);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn start_row(&mut self) {
+        self.row.clear();
+        self.skipped = 0;
+    }
+
+    pub fn start_cell(&mut self) {
+        self.cell.clear();
+        self.span = 1;
+        self.merged = false;
+    }
+
+    pub fn end_cell(&mut self) {
+        if self.row.is_empty() {
+            self.row
+                .extend(std::iter::repeat_n(String::new(), self.skipped));
+        }
+        let text = if self.merged {
+            String::new()
+        } else {
+            cell_text(&std::mem::take(&mut self.cell))
+        };
+        self.row.push(text);
+        self.row
+            .extend(std::iter::repeat_n(String::new(), self.span - 1));
+    }
+
+    pub fn end_row(&mut self) {
+        if self.row.is_empty() && self.skipped == 0 {
+            return;
+        }
+        if self.table.rows.is_empty() {
+            self.table.header = self.repeated_header;
+        }
+        self.table.rows.push(std::mem::take(&mut self.row));
+    }
+
+    pub fn finish(self) -> Table {
+        self.table
+    }
+}
+
+/// The text of a cell, with nested tables flattened.
+fn cell_text(blocks: &[Block]) -> String {
+    let parts: Vec<String> = blocks
+        .iter()
+        .map(|block| match block {
+            Block::Table(table) => table
+                .rows
+                .iter()
+                .map(|row| row.join(" | "))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            block => render_text(std::slice::from_ref(block)),
+        })
+        .filter(|part| !part.is_empty())
+        .collect();
+    parts.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::body::{read_story, Context};
+    use super::*;
+
+    fn table(xml: &str) -> Table {
+        match read_story(xml, &Context::default(), &Default::default())
+            .unwrap()
+            .blocks
+            .as_slice()
+        {
+            [Block::Table(table)] => table.clone(),
+            other => panic!("expected one table, got {:?}", other),
+        }
+    }
+
+    fn cell(text: &str, properties: &str) -> String {
+        format!(
+            "<w:tc><w:tcPr>{}</w:tcPr><w:p><w:r><w:t>{}</w:t></w:r></w:p></w:tc>",
+            properties, text
+        )
+    }
+
+    #[test]
+    fn test_merged_cells_keep_the_grid() {
+        let xml = format!(
+            "<w:tbl>
+  <w:tr><w:trPr><w:tblHeader/></w:trPr>{}{}</w:tr>
+  <w:tr>{}{}{}</w:tr>
+  <w:tr>{}{}{}</w:tr>
+  <w:tr><w:trPr><w:gridBefore w:val=\"1\"/></w:trPr>{}{}</w:tr>
+</w:tbl>",
+            cell("Region", "<w:vMerge w:val=\"restart\"/>"),
+            cell("Sales", "<w:gridSpan w:val=\"2\"/>"),
+            cell("North", "<w:vMerge w:val=\"restart\"/>"),
+            cell("Q1", ""),
+            cell("10", ""),
+            cell("", "<w:vMerge/>"),
+            cell("Q2", ""),
+            cell("12", ""),
+            cell("Total", ""),
+            cell("22", ""),
+        );
+        assert_eq!(
+            table(&xml),
+            Table {
+                rows: vec![
+                    vec!["Region".into(), "Sales".into(), "".into()],
+                    vec!["North".into(), "Q1".into(), "10".into()],
+                    vec!["".into(), "Q2".into(), "12".into()],
+                    vec!["".into(), "Total".into(), "22".into()],
+                ],
+                header: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_nested_table_is_flattened_into_its_cell() {
+        let inner = format!(
+            "<w:tbl><w:tr>{}{}</w:tr><w:tr>{}{}</w:tr></w:tbl>",
+            cell("a", ""),
+            cell("b", ""),
+            cell("c", ""),
+            cell("d", ""),
+        );
+        let xml = format!(
+            "<w:tbl><w:tr>{}<w:tc><w:p><w:r><w:t>Grid:</w:t></w:r></w:p>{}<w:p/></w:tc></w:tr></w:tbl>",
+            cell("Outer", ""),
+            inner
+        );
+        assert_eq!(
+            table(&xml).rows,
+            vec![vec!["Outer".to_string(), "Grid:\na | b\nc | d".to_string()]]
+        );
+    }
+}
@@ -1,15 +1,266 @@
-use super::ParseError;
-use crate::document_parsing::ParsedDocument;
-use serde_json::json;
+use super::{DocumentParser, ParseError};
+use crate::document_parsing::{Block, DocumentFormat, DocumentMetadata, ParsedDocument, Table};
+use ego_tree::NodeRef;
+use scraper::{Html, Node};
+
+/// HTML parser.
+pub struct HtmlParser;
+
+impl DocumentParser for HtmlParser {
+    fn id(&self) -> &str {
+        DocumentFormat::Html.as_str()
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["html", "htm"]
+    }
+
+    fn mime_types(&self) -> &[&str] {
+        &["text/html", "application/xhtml+xml"]
+    }
+
+    fn parse(&self, bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
+        parse_html(bytes)
+    }
+}
 
 pub fn parse_html(bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
-    let text = html2text::from_read(bytes, 120)
-        .map_err(|e| ParseError::Format(format!("HTML parse error: {}", e)))?;
+    // html5ever never rejects input, so invalid UTF-8 is replaced rather than
+    // reported.
+    let source = String::from_utf8_lossy(bytes);
+    let html = Html::parse_document(&source);
+
+    let mut walker = HtmlWalker::default();
+    walker.walk_container(*html.root_element());
+    walker.flush();
+
+    let root = html.root_element();
+    let mut metadata = DocumentMetadata::new(DocumentFormat::Html.as_str());
+    metadata.language = root
+        .value()
+        .attr("lang")
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(String::from);
+    metadata.title = root
+        .descendants()
+        .find(|n| element_name(*n) == Some("title"))
+        .map(|n| collapse_whitespace(&raw_text(n)))
+        .filter(|t| !t.is_empty());
+
+    Ok(ParsedDocument::new(bytes, walker.blocks, metadata))
+}
+
+/// Elements whose content is never document text.
+const SKIPPED: &[&str] = &["head", "script", "style", "noscript", "template", "svg"];
+
+/// Elements that flow within a paragraph rather than starting a new block.
+const INLINE: &[&str] = &[
+    "a", "abbr", "b", "bdi", "bdo", "cite", "code", "data", "del", "dfn", "em", "font", "i", "ins",
+    "kbd", "label", "mark", "q", "s", "samp", "small", "span", "strong", "sub", "sup", "time", "u",
+    "var",
+];
+
+/// Stands in for `<br>` while whitespace is collapsed.
+const LINE_BREAK: char = '\u{0}';
+
+/// Collects blocks from the DOM, buffering loose inline text until the next
+/// block-level element closes the paragraph.
+#[derive(Default)]
+struct HtmlWalker {
+    blocks: Vec<Block>,
+    inline: String,
+}
+
+impl HtmlWalker {
+    fn walk_container(&mut self, node: NodeRef<Node>) {
+        for child in node.children() {
+            match child.value() {
+                Node::Text(text) => self.inline.push_str(&text[..]),
+                Node::Element(el) => self.walk_element(child, el.name()),
+                _ => {}
+            }
+        }
+    }
+
+    fn walk_element(&mut self, node: NodeRef<Node>, name: &str) {
+        match name {
+            _ if SKIPPED.contains(&name) => {}
+            "br" => self.inline.push(LINE_BREAK),
+            _ if INLINE.contains(&name) => self.inline.push_str(&raw_inline_text(node)),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.flush();
+                let level = name[1..].parse().unwrap_or(1);
+                let text = inline_text(node);
+                if !text.is_empty() {
+                    self.blocks.push(Block::heading(level, text));
+                }
+            }
+            "img" => {
+                self.flush();
+                self.blocks.push(image(node));
+            }
+            "pre" => {
+                self.flush();
+                self.blocks.push(Block::CodeBlock {
+                    language: code_language(node),
+                    text: raw_text(node).trim_matches('\n').to_string(),
+                });
+            }
+            "ul" | "ol" => {
+                self.flush();
+                self.walk_list(node, name == "ol", 0);
+            }
+            "table" => {
+                self.flush();
+                self.blocks.push(Block::Table(table(node)));
+            }
+            "hr" => self.flush(),
+            _ => {
+                self.flush();
+                self.walk_container(node);
+                self.flush();
+            }
+        }
+    }
+
+    fn walk_list(&mut self, list: NodeRef<Node>, ordered: bool, level: u8) {
+        for item in list.children().filter(|n| element_name(*n) == Some("li")) {
+            let mut text = String::new();
+            let mut nested = Vec::new();
+            for child in item.children() {
+                match child.value() {
+                    Node::Text(t) => text.push_str(&t[..]),
+                    Node::Element(el) if matches!(el.name(), "ul" | "ol") => nested.push(child),
+                    Node::Element(el) if !SKIPPED.contains(&el.name()) => {
+                        text.push(' ');
+                        text.push_str(&raw_inline_text(child));
+                        text.push(' ');
+                    }
+                    _ => {}
+                }
+            }
+
+            let text = collapse_whitespace(&text);
+            if !text.is_empty() {
+                self.blocks.push(Block::ListItem {
+                    ordered,
+                    level,
+                    text,
+                });
+            }
+            for list in nested {
+                let ordered = element_name(list) == Some("ol");
+                self.walk_list(list, ordered, level.saturating_add(1));
+            }
+        }
+    }
 
-    Ok(ParsedDocument {
-        text,
-        metadata: json!({ "format": "html" }),
-    })
+    /// Close the pending paragraph, if it has any text.
+    fn flush(&mut self) {
+        let text = collapse_whitespace(&self.inline);
+        self.inline.clear();
+        if !text.is_empty() {
+            self.blocks.push(Block::paragraph(text));
+        }
+    }
+}
+
+fn element_name<'a>(node: NodeRef<'a, Node>) -> Option<&'a str> {
+    node.value().as_element().map(|el| el.name())
+}
+
+/// Descendant text with `<br>` kept as [`LINE_BREAK`], whitespace untouched.
+fn raw_inline_text(node: NodeRef<Node>) -> String {
+    let mut out = String::new();
+    for child in node.descendants() {
+        match child.value() {
+            Node::Text(text) => {
+                let hidden = child
+                    .ancestors()
+                    .any(|a| element_name(a).is_some_and(|n| SKIPPED.contains(&n)));
+                if !hidden {
+                    out.push_str(&text[..]);
+                }
+            }
+            Node::Element(el) if el.name() == "br" => out.push(LINE_BREAK),
+            _ => {}
+        }
+    }
+    out
+}
+
+fn inline_text(node: NodeRef<Node>) -> String {
+    collapse_whitespace(&raw_inline_text(node))
+}
+
+/// Descendant text exactly as written, for `<pre>`.
+fn raw_text(node: NodeRef<Node>) -> String {
+    node.descendants()
+        .filter_map(|n| n.value().as_text().map(|t| t.to_string()))
+        .collect()
+}
+
+/// Collapse runs of HTML whitespace to one space, turning [`LINE_BREAK`]s into
+/// newlines.
+fn collapse_whitespace(text: &str) -> String {
+    text.split(LINE_BREAK)
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+fn image(node: NodeRef<Node>) -> Block {
+    let el = node.value().as_element();
+    let attr = |name: &str| {
+        el.and_then(|el| el.attr(name))
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(String::from)
+    };
+    Block::Image {
+        alt: attr("alt"),
+        source: attr("src"),
+    }
+}
+
+/// Language from a `language-*` / `lang-*` class on the `<pre>` or its `<code>`.
+fn code_language(pre: NodeRef<Node>) -> Option<String> {
+    pre.descendants()
+        .filter_map(|n| n.value().as_element())
+        .flat_map(|el| el.classes())
+        .find_map(|class| {
+            class
+                .strip_prefix("language-")
+                .or_else(|| class.strip_prefix("lang-"))
+        })
+        .map(String::from)
+}
+
+fn table(node: NodeRef<Node>) -> Table {
+    let mut rows = Vec::new();
+    let mut header = false;
+
+    let row_parents = std::iter::once(node).chain(
+        node.children()
+            .filter(|n| matches!(element_name(*n), Some("thead" | "tbody" | "tfoot"))),
+    );
+    for parent in row_parents {
+        for tr in parent.children().filter(|n| element_name(*n) == Some("tr")) {
+            let cells: Vec<NodeRef<Node>> = tr
+                .children()
+                .filter(|n| matches!(element_name(*n), Some("td" | "th")))
+                .collect();
+            if rows.is_empty() {
+                header = !cells.is_empty() && cells.iter().all(|c| element_name(*c) == Some("th"));
+            }
+            rows.push(cells.into_iter().map(inline_text).collect());
+        }
+    }
+
+    Table { rows, header }
 }
 
 #[cfg(test)]
@@ -30,4 +281,53 @@ mod tests {
         let result = parse_html(html).expect("Failed to parse HTML");
         assert!(result.text.contains("Link"));
     }
+
+    #[test]
+    fn test_parse_html_blocks() {
+        let html = b"<html lang=\"en\"><head><title>T</title><style>p{}</style></head><body>
+            <h2>Intro</h2>
+            <p>Hello <b>bold</b><br>world</p>
+            <ul><li>one<ul><li>nested</li></ul></li><li>two</li></ul>
+            <table><tr><th>k</th><th>v</th></tr><tr><td>a</td><td>1</td></tr></table>
+            <pre><code class=\"language-rust\">fn main() {}</code></pre>
+            <img src=\"fig.png\" alt=\"Figure 1\">
+        </body></html>";
+        let result = parse_html(html).expect("Failed to parse HTML");
+        assert_eq!(result.metadata.title.as_deref(), Some("T"));
+        assert_eq!(result.metadata.language.as_deref(), Some("en"));
+        assert_eq!(
+            result.blocks,
+            vec![
+                Block::heading(2, "Intro"),
+                Block::paragraph("Hello bold\nworld"),
+                Block::ListItem {
+                    ordered: false,
+                    level: 0,
+                    text: "one".into()
+                },
+                Block::ListItem {
+                    ordered: false,
+                    level: 1,
+                    text: "nested".into()
+                },
+                Block::ListItem {
+                    ordered: false,
+                    level: 0,
+                    text: "two".into()
+                },
+                Block::Table(Table {
+                    rows: vec![vec!["k".into(), "v".into()], vec!["a".into(), "1".into()]],
+                    header: true,
+                }),
+                Block::CodeBlock {
+                    language: Some("rust".into()),
+                    text: "fn main() {}".into(),
+                },
+                Block::Image {
+                    alt: Some("Figure 1".into()),
+                    source: Some("fig.png".into()),
+                },
+            ]
+        );
+    }
 }
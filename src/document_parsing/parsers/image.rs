@@ -0,0 +1,171 @@
+use super::{DocumentParser, ParseError};
+use crate::document_parsing::blocks::paragraphs_from_text;
+use crate::document_parsing::{
+    Block, DocumentFormat, DocumentMetadata, ParseOptions, ParsedDocument,
+};
+
+/// Image parser. Images have no text of their own; with an OCR engine in
+/// [`ParseOptions::ocr`] the text in the picture is read instead.
+pub struct ImageParser;
+
+impl DocumentParser for ImageParser {
+    fn id(&self) -> &str {
+        DocumentFormat::Image.as_str()
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["png", "jpg", "jpeg", "tif", "tiff", "gif", "bmp", "webp"]
+    }
+
+    fn mime_types(&self) -> &[&str] {
+        &[
+            "image/png",
+            "image/jpeg",
+            "image/tiff",
+            "image/gif",
+            "image/bmp",
+            "image/webp",
+        ]
+    }
+
+    fn parse(&self, bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
+        parse_image(bytes, &ParseOptions::default())
+    }
+
+    fn parse_with_options(
+        &self,
+        bytes: &[u8],
+        options: &ParseOptions,
+    ) -> Result<ParsedDocument, ParseError> {
+        parse_image(bytes, options)
+    }
+}
+
+/// Parse an image into a single [`Block::Image`], or into the paragraphs
+/// `options.ocr` reads from it.
+pub fn parse_image(bytes: &[u8], options: &ParseOptions) -> Result<ParsedDocument, ParseError> {
+    let kind =
+        image_kind(bytes).ok_or_else(|| ParseError::Format("unrecognised image data".into()))?;
+
+    let mut metadata = DocumentMetadata::new(DocumentFormat::Image.as_str());
+    metadata.extra.insert("image_type".into(), kind.into());
+    if let Some((width, height)) = dimensions(bytes) {
+        metadata.extra.insert("width".into(), width.into());
+        metadata.extra.insert("height".into(), height.into());
+    }
+
+    let blocks = match &options.ocr {
+        Some(engine) => {
+            metadata
+                .extra
+                .insert("ocr_engine".into(), engine.id().into());
+            paragraphs_from_text(engine.recognize(bytes)?.trim())
+        }
+        None => vec![Block::Image {
+            alt: None,
+            source: None,
+        }],
+    };
+    Ok(ParsedDocument::new(bytes, blocks, metadata))
+}
+
+/// The image type announced by the file signature.
+pub(crate) fn image_kind(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("png")
+    } else if bytes.starts_with(b"\xFF\xD8\xFF") {
+        Some("jpeg")
+    } else if bytes.starts_with(b"II*\0") || bytes.starts_with(b"MM\0*") {
+        Some("tiff")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("gif")
+    } else if bytes.len() >= 12 && bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WEBP" {
+        Some("webp")
+    } else if bytes.len() >= 26 && bytes.starts_with(b"BM") && bytes[6..10] == [0; 4] {
+        // The reserved bytes of the header keep text starting "BM" out.
+        Some("bmp")
+    } else {
+        None
+    }
+}
+
+/// Width and height in pixels, for the formats whose header is simple to
+/// read.
+fn dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let be32 = |at: usize| Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+    let le16 = |at: usize| Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?));
+    let be16 = |at: usize| Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?));
+
+    match image_kind(bytes)? {
+        "png" if bytes.get(12..16)? == b"IHDR" => Some((be32(16)?, be32(20)?)),
+        "gif" => Some((le16(6)? as u32, le16(8)? as u32)),
+        "jpeg" => {
+            // Walk the segments up to the first start-of-frame marker.
+            let mut at = 2;
+            while *bytes.get(at)? == 0xFF {
+                let marker = *bytes.get(at + 1)?;
+                let length = be16(at + 2)? as usize;
+                let start_of_frame =
+                    matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC);
+                if start_of_frame {
+                    return Some((be16(at + 7)? as u32, be16(at + 5)? as u32));
+                }
+                at += 2 + length;
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document_parsing::{OcrEngine, StubOcrEngine};
+    use std::sync::Arc;
+
+    /// The signature and header of a 3x2 PNG; nothing reads further.
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR\0\0\0\x03\0\0\0\x02\x08\x00\x00\x00\x00";
+
+    #[test]
+    fn test_parse_image_without_ocr() {
+        let doc = parse_image(PNG, &ParseOptions::default()).unwrap();
+        assert_eq!(
+            doc.blocks,
+            [Block::Image {
+                alt: None,
+                source: None
+            }]
+        );
+        assert_eq!(doc.metadata.format, "image");
+        assert_eq!(doc.metadata.extra["image_type"], "png");
+        assert_eq!(doc.metadata.extra["width"], 3);
+        assert_eq!(doc.metadata.extra["height"], 2);
+    }
+
+    #[test]
+    fn test_parse_image_with_ocr() {
+        let engine = Arc::new(StubOcrEngine::new("INVOICE\nTotal due: 42\n"));
+        let options = ParseOptions {
+            ocr: Some(engine.clone() as Arc<dyn OcrEngine>),
+            ..ParseOptions::default()
+        };
+        let doc = parse_image(PNG, &options).unwrap();
+        assert_eq!(doc.text, "INVOICE\nTotal due: 42");
+        assert_eq!(doc.metadata.extra["ocr_engine"], "stub");
+        assert_eq!(engine.calls(), 1);
+    }
+
+    #[test]
+    fn test_jpeg_dimensions() {
+        // SOI, an APP0 segment, then a baseline SOF0 for 640x480.
+        let mut jpeg = b"\xFF\xD8\xFF\xE0\x00\x04\x00\x00".to_vec();
+        jpeg.extend_from_slice(b"\xFF\xC0\x00\x11\x08\x01\xE0\x02\x80\x03");
+        assert_eq!(dimensions(&jpeg), Some((640, 480)));
+    }
+
+    #[test]
+    fn test_not_an_image() {
+        assert!(parse_image(b"BMX plain text", &ParseOptions::default()).is_err());
+    }
+}
@@ -1,12 +1,32 @@
-use super::ParseError;
-use crate::document_parsing::ParsedDocument;
-use serde_json::json;
+use super::{DocumentParser, ParseError};
+use crate::document_parsing::{Block, DocumentFormat, DocumentMetadata, ParsedDocument};
+
+/// JSON parser.
+pub struct JsonParser;
+
+impl DocumentParser for JsonParser {
+    fn id(&self) -> &str {
+        DocumentFormat::Json.as_str()
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["json"]
+    }
+
+    fn mime_types(&self) -> &[&str] {
+        &["application/json"]
+    }
+
+    fn parse(&self, bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
+        parse_json(bytes)
+    }
+}
 
 pub fn parse_json(bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
     let text = String::from_utf8(bytes.to_vec())?;
 
-    let value: serde_json::Value =
-        serde_json::from_str(&text).map_err(|e| ParseError::Format(format!("Invalid JSON: {}", e)))?;
+    let value: serde_json::Value = serde_json::from_str(&text)
+        .map_err(|e| ParseError::Format(format!("Invalid JSON: {}", e)))?;
 
     let pretty = serde_json::to_string_pretty(&value).unwrap_or_else(|_| text.clone());
 
@@ -16,13 +36,15 @@ pub fn parse_json(bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
         _ => "scalar".to_string(),
     };
 
-    Ok(ParsedDocument {
+    let code = Block::CodeBlock {
+        language: Some("json".to_string()),
         text: pretty,
-        metadata: json!({
-            "format": "json",
-            "type": type_info,
-        }),
-    })
+    };
+
+    let mut metadata = DocumentMetadata::new(DocumentFormat::Json.as_str());
+    metadata.extra.insert("type".into(), type_info.into());
+
+    Ok(ParsedDocument::new(bytes, vec![code], metadata))
 }
 
 #[cfg(test)]
@@ -34,14 +56,28 @@ mod tests {
         let data = br#"{"name": "Alice", "age": 30}"#;
         let result = parse_json(data).expect("Failed to parse JSON");
         assert!(result.text.contains("Alice"));
-        assert!(result.metadata.get("type").unwrap().as_str().unwrap().contains("object"));
+        assert!(result
+            .metadata
+            .extra
+            .get("type")
+            .unwrap()
+            .as_str()
+            .unwrap()
+            .contains("object"));
     }
 
     #[test]
     fn test_parse_json_array() {
         let data = b"[1, 2, 3]";
         let result = parse_json(data).expect("Failed to parse JSON");
-        assert!(result.metadata.get("type").unwrap().as_str().unwrap().contains("array[3]"));
+        assert!(result
+            .metadata
+            .extra
+            .get("type")
+            .unwrap()
+            .as_str()
+            .unwrap()
+            .contains("array[3]"));
     }
 
     #[test]
@@ -1,17 +1,220 @@
-use super::ParseError;
-use crate::document_parsing::ParsedDocument;
-use serde_json::json;
+use super::{DocumentParser, ParseError};
+use crate::document_parsing::{Block, DocumentFormat, DocumentMetadata, ParsedDocument, Table};
+
+/// Markdown parser.
+pub struct MarkdownParser;
+
+impl DocumentParser for MarkdownParser {
+    fn id(&self) -> &str {
+        DocumentFormat::Markdown.as_str()
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["md", "markdown"]
+    }
+
+    fn mime_types(&self) -> &[&str] {
+        &["text/markdown", "text/x-markdown"]
+    }
+
+    fn parse(&self, bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
+        parse_markdown(bytes)
+    }
+}
 
 pub fn parse_markdown(bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
     let text = String::from_utf8(bytes.to_vec())?;
     let line_count = text.lines().count();
 
-    Ok(ParsedDocument {
-        text,
-        metadata: json!({
-            "format": "markdown",
-            "line_count": line_count,
-        }),
+    let mut metadata = DocumentMetadata::new(DocumentFormat::Markdown.as_str());
+    metadata
+        .extra
+        .insert("line_count".into(), line_count.into());
+
+    Ok(ParsedDocument::new(bytes, markdown_blocks(&text), metadata))
+}
+
+/// Line-based block parser covering the common CommonMark/GFM constructs:
+/// ATX and setext headings, fenced code, lists, pipe tables and standalone
+/// images. Inline markup is kept verbatim in the block text.
+fn markdown_blocks(text: &str) -> Vec<Block> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut blocks = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim();
+
+        if trimmed.is_empty() {
+            flush_paragraph(&mut paragraph, &mut blocks);
+            i += 1;
+            continue;
+        }
+
+        if let Some(fence) = code_fence(trimmed) {
+            flush_paragraph(&mut paragraph, &mut blocks);
+            let language = trimmed
+                .trim_start_matches(fence.chars().next().unwrap())
+                .trim();
+            let mut code = Vec::new();
+            i += 1;
+            while i < lines.len() && !lines[i].trim_start().starts_with(fence) {
+                code.push(lines[i]);
+                i += 1;
+            }
+            i += 1;
+            blocks.push(Block::CodeBlock {
+                language: (!language.is_empty()).then(|| language.to_string()),
+                text: code.join("\n"),
+            });
+            continue;
+        }
+
+        if !paragraph.is_empty() {
+            if let Some(level) = setext_level(trimmed) {
+                let title = paragraph.join(" ");
+                paragraph.clear();
+                blocks.push(Block::heading(level, title));
+                i += 1;
+                continue;
+            }
+        }
+
+        if let Some((level, title)) = atx_heading(trimmed) {
+            flush_paragraph(&mut paragraph, &mut blocks);
+            blocks.push(Block::heading(level, title));
+        } else if is_thematic_break(trimmed) {
+            flush_paragraph(&mut paragraph, &mut blocks);
+        } else if let Some(item) = list_item(line) {
+            flush_paragraph(&mut paragraph, &mut blocks);
+            blocks.push(item);
+        } else if is_table_row(trimmed) && lines.get(i + 1).is_some_and(|l| is_table_separator(l)) {
+            flush_paragraph(&mut paragraph, &mut blocks);
+            let mut rows = vec![table_cells(trimmed)];
+            i += 2;
+            while i < lines.len() && is_table_row(lines[i].trim()) {
+                rows.push(table_cells(lines[i].trim()));
+                i += 1;
+            }
+            blocks.push(Block::Table(Table { rows, header: true }));
+            continue;
+        } else if let Some(image) = standalone_image(trimmed) {
+            flush_paragraph(&mut paragraph, &mut blocks);
+            blocks.push(image);
+        } else {
+            paragraph.push(trimmed.strip_prefix('>').map_or(trimmed, str::trim_start));
+        }
+        i += 1;
+    }
+    flush_paragraph(&mut paragraph, &mut blocks);
+
+    blocks
+}
+
+fn flush_paragraph(paragraph: &mut Vec<&str>, blocks: &mut Vec<Block>) {
+    if !paragraph.is_empty() {
+        blocks.push(Block::paragraph(paragraph.join("\n")));
+        paragraph.clear();
+    }
+}
+
+fn code_fence(line: &str) -> Option<&'static str> {
+    if line.starts_with("```") {
+        Some("```")
+    } else if line.starts_with("~~~") {
+        Some("~~~")
+    } else {
+        None
+    }
+}
+
+fn atx_heading(line: &str) -> Option<(u8, String)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+    let title = rest.trim().trim_end_matches('#').trim_end();
+    Some((level as u8, title.to_string()))
+}
+
+fn setext_level(line: &str) -> Option<u8> {
+    if line.chars().all(|c| c == '=') {
+        Some(1)
+    } else if line.chars().all(|c| c == '-') {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+fn is_thematic_break(line: &str) -> bool {
+    let compact: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    compact.len() >= 3
+        && ["-", "*", "_"]
+            .iter()
+            .any(|m| compact.chars().all(|c| c.to_string() == *m))
+}
+
+fn list_item(line: &str) -> Option<Block> {
+    let indent: usize = line
+        .chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum();
+    let rest = line.trim_start();
+
+    let (ordered, item) = if let Some(item) = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|marker| rest.strip_prefix(marker))
+    {
+        (false, item)
+    } else {
+        let digits = rest.chars().take_while(char::is_ascii_digit).count();
+        let after = &rest[digits..];
+        let item = after
+            .strip_prefix(". ")
+            .or_else(|| after.strip_prefix(") "))
+            .filter(|_| digits > 0)?;
+        (true, item)
+    };
+
+    Some(Block::ListItem {
+        ordered,
+        level: (indent / 2).min(u8::MAX as usize) as u8,
+        text: item.trim().to_string(),
+    })
+}
+
+fn is_table_row(line: &str) -> bool {
+    line.starts_with('|') && line.len() > 1
+}
+
+fn is_table_separator(line: &str) -> bool {
+    let line = line.trim();
+    line.contains('-') && line.chars().all(|c| matches!(c, '|' | ':' | '-' | ' '))
+}
+
+fn table_cells(line: &str) -> Vec<String> {
+    let inner = line.trim_start_matches('|').trim_end_matches('|');
+    inner
+        .split('|')
+        .map(|cell| cell.trim().to_string())
+        .collect()
+}
+
+/// A line consisting only of `![alt](source)`.
+fn standalone_image(line: &str) -> Option<Block> {
+    let rest = line.strip_prefix("![")?.strip_suffix(')')?;
+    let (alt, source) = rest.split_once("](")?;
+    Some(Block::Image {
+        alt: (!alt.is_empty()).then(|| alt.to_string()),
+        source: Some(source.to_string()),
     })
 }
 
@@ -25,6 +228,37 @@ mod tests {
         let result = parse_markdown(data).expect("Failed to parse markdown");
         assert!(result.text.contains("# Title"));
         assert!(result.text.contains("**bold**"));
-        assert_eq!(result.metadata.get("format").unwrap().as_str().unwrap(), "markdown");
+        assert_eq!(result.metadata.format, "markdown");
+    }
+
+    #[test]
+    fn test_parse_markdown_blocks() {
+        let data = b"Title\n=====\n\n## Usage\n\n1. install\n   - with cargo\n\n```rust\nfn main() {}\n```\n\n| a | b |\n|---|---|\n| 1 | 2 |";
+        let result = parse_markdown(data).expect("Failed to parse markdown");
+        assert_eq!(
+            result.blocks,
+            vec![
+                Block::heading(1, "Title"),
+                Block::heading(2, "Usage"),
+                Block::ListItem {
+                    ordered: true,
+                    level: 0,
+                    text: "install".into()
+                },
+                Block::ListItem {
+                    ordered: false,
+                    level: 1,
+                    text: "with cargo".into()
+                },
+                Block::CodeBlock {
+                    language: Some("rust".into()),
+                    text: "fn main() {}".into()
+                },
+                Block::Table(Table {
+                    rows: vec![vec!["a".into(), "b".into()], vec!["1".into(), "2".into()]],
+                    header: true,
+                }),
+            ]
+        );
     }
 }
@@ -1,24 +1,60 @@
 mod csv_parser;
 mod docx;
 mod html;
+mod image;
 mod json_parser;
 mod markdown;
+mod ooxml;
 mod pdf;
 mod plain_text;
 mod pptx;
 mod xlsx;
 mod xml;
 
-pub use csv_parser::parse_csv;
-pub use docx::parse_docx;
-pub use html::parse_html;
-pub use json_parser::parse_json;
-pub use markdown::parse_markdown;
-pub use pdf::parse_pdf;
-pub use plain_text::parse_text;
-pub use pptx::parse_pptx;
-pub use xlsx::parse_xlsx;
-pub use xml::parse_xml;
+pub use csv_parser::{parse_csv, CsvParser};
+pub use docx::{parse_docx, parse_docx_with_options, DocxParser};
+pub use html::{parse_html, HtmlParser};
+pub(crate) use image::image_kind;
+pub use image::{parse_image, ImageParser};
+pub use json_parser::{parse_json, JsonParser};
+pub use markdown::{parse_markdown, MarkdownParser};
+pub use pdf::{parse_pdf, parse_pdf_with_options, PdfParser};
+pub use plain_text::{parse_text, TextParser};
+pub use pptx::{parse_pptx, parse_pptx_with_options, PptxParser};
+pub use xlsx::{parse_xlsx, parse_xlsx_with_options, XlsxParser};
+pub use xml::{parse_xml, XmlParser};
+
+use super::{ParseOptions, ParsedDocument};
+
+/// A parser for one document format.
+///
+/// Every module in `parsers/` exposes a unit struct implementing this trait
+/// alongside its `parse_*` function, so formats can be handled generically.
+pub trait DocumentParser: Send + Sync {
+    /// Stable identifier of the format, e.g. `"pdf"`.
+    fn id(&self) -> &str;
+
+    /// Lowercase file extensions (without the dot) handled by this parser.
+    fn extensions(&self) -> &[&str];
+
+    /// MIME types handled by this parser, without parameters.
+    fn mime_types(&self) -> &[&str] {
+        &[]
+    }
+
+    fn parse(&self, bytes: &[u8]) -> Result<ParsedDocument, ParseError>;
+
+    /// Parse with `options`. Parsers that take no options keep the default,
+    /// which ignores them.
+    fn parse_with_options(
+        &self,
+        bytes: &[u8],
+        options: &ParseOptions,
+    ) -> Result<ParsedDocument, ParseError> {
+        let _ = options;
+        self.parse(bytes)
+    }
+}
 
 /// Error type for document parsing failures.
 #[derive(Debug, thiserror::Error)]
@@ -29,4 +65,36 @@ pub enum ParseError {
     Utf8(#[from] std::string::FromUtf8Error),
     #[error("Parse error: {0}")]
     Format(String),
+    #[error("Unsupported format: {0}")]
+    UnsupportedFormat(String),
+    #[error("Parsing timed out after {0:?}")]
+    Timeout(std::time::Duration),
+    #[error("Parsing was cancelled")]
+    Cancelled,
+    /// The document is encrypted and no password, or a wrong one, was given.
+    #[error("Document is encrypted and the password is missing or incorrect")]
+    Encrypted,
+    /// The OCR engine failed to run or to read an image.
+    #[error("OCR error: {0}")]
+    Ocr(String),
+    /// The input is larger than the caller allows.
+    #[error("Input is {size} bytes, over the limit of {limit}")]
+    TooLarge { size: u64, limit: u64 },
+}
+
+impl ParseError {
+    /// Name of the variant, e.g. `"Format"`, for grouping failures.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Io(_) => "Io",
+            Self::Utf8(_) => "Utf8",
+            Self::Format(_) => "Format",
+            Self::UnsupportedFormat(_) => "UnsupportedFormat",
+            Self::Timeout(_) => "Timeout",
+            Self::Cancelled => "Cancelled",
+            Self::Encrypted => "Encrypted",
+            Self::Ocr(_) => "Ocr",
+            Self::TooLarge { .. } => "TooLarge",
+        }
+    }
 }
@@ -0,0 +1,539 @@
+//! Parts shared by the Office Open XML packages: DOCX, PPTX and XLSX.
+
+use super::image_kind;
+use super::ParseError;
+use crate::document_parsing::metadata::{hex, iso_date_to_rfc3339, split_list};
+use crate::document_parsing::{
+    render_segments, Attachment, Block, DocumentMetadata, MediaMode, Segment,
+};
+use quick_xml::events::{BytesStart, Event};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::{self, Read, Seek};
+use zip::ZipArchive;
+
+/// Media files larger than this once decompressed are left out of the
+/// attachments rather than read.
+const MAX_MEDIA_SIZE: u64 = 64 * 1024 * 1024;
+
+/// The text of the part `name`, or `None` when the package has no such part.
+pub(super) fn read_part<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> Result<Option<String>, ParseError> {
+    let mut file = match archive.by_name(name) {
+        Ok(file) => file,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(ParseError::Format(format!("{}: {}", name, e))),
+    };
+    let mut buf = String::new();
+    file.read_to_string(&mut buf)?;
+    Ok(Some(buf))
+}
+
+/// A media file as read for an attachment.
+#[derive(Debug)]
+struct Media {
+    size: usize,
+    sha256: String,
+    /// The first bytes, enough to tell the image type from.
+    head: Vec<u8>,
+    data: Option<Vec<u8>>,
+}
+
+/// Read the part `name`, hashing it as it is decompressed and keeping its
+/// content only when `keep` is set. `None` when it is missing, cannot be read
+/// or is larger than `limit`.
+fn read_media_part<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+    keep: bool,
+    limit: u64,
+) -> Option<Media> {
+    let file = archive.by_name(name).ok()?;
+    if file.size() > limit {
+        return None;
+    }
+    // The size in the archive may lie, so stop reading past the limit too.
+    let mut reader = file.take(limit + 1);
+    let mut hasher = Sha256::new();
+    let (mut size, mut head, mut data) = (0, Vec::new(), Vec::new());
+    let mut buf = [0; 64 * 1024];
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => return None,
+        };
+        let chunk = &buf[..n];
+        hasher.update(chunk);
+        head.extend(chunk.iter().take(32 - head.len()));
+        if keep {
+            data.extend_from_slice(chunk);
+        }
+        size += n;
+    }
+    if size as u64 > limit {
+        return None;
+    }
+    Some(Media {
+        size,
+        sha256: hex(&hasher.finalize()),
+        head,
+        data: keep.then_some(data),
+    })
+}
+
+/// The media of the package as attachments: the files the images in
+/// `blocks` show, placed where each is first shown, then any other file
+/// under `directory` (such as `word/media/`) in name order. Empty when
+/// `mode` is [`MediaMode::Off`]. Files larger than 64 MiB are left out.
+pub(super) fn read_media<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    directory: &str,
+    blocks: &[Block],
+    mode: MediaMode,
+) -> Vec<Attachment> {
+    if mode == MediaMode::Off {
+        return Vec::new();
+    }
+    let (_, segments) = render_segments(blocks);
+    let mut alts = HashMap::new();
+    image_alts(blocks, &mut alts);
+
+    let mut placed: HashMap<&str, &Segment> = HashMap::new();
+    let mut names: Vec<String> = Vec::new();
+    for segment in &segments {
+        if let Some(source) = segment.source.as_deref() {
+            if !placed.contains_key(source) {
+                placed.insert(source, segment);
+                names.push(source.to_string());
+            }
+        }
+    }
+    let mut rest: Vec<String> = archive
+        .file_names()
+        .filter(|name| name.starts_with(directory) && !name.ends_with('/'))
+        .filter(|name| !placed.contains_key(name))
+        .map(String::from)
+        .collect();
+    rest.sort();
+    names.extend(rest);
+
+    let mut attachments = Vec::new();
+    for name in names {
+        let keep = mode == MediaMode::Bytes;
+        // Linked pictures are outside the package, so there is no part to
+        // read; oversized parts are left out too.
+        let Some(media) = read_media_part(archive, &name, keep, MAX_MEDIA_SIZE) else {
+            continue;
+        };
+        let segment = placed.get(name.as_str());
+        let location = segment.map(|s| s.location.clone()).unwrap_or_default();
+        attachments.push(Attachment {
+            mime_type: mime_type(&name, &media.head),
+            size: media.size,
+            sha256: media.sha256,
+            data: media.data,
+            alt: alts.get(&name).cloned().flatten(),
+            offset: segment.map(|s| s.range.start),
+            page: location.page,
+            slide: location.slide,
+            sheet: location.sheet,
+            name,
+        });
+    }
+    attachments
+}
+
+/// The alternative text of the first image showing each source.
+fn image_alts(blocks: &[Block], alts: &mut HashMap<String, Option<String>>) {
+    for block in blocks {
+        match block {
+            Block::Image {
+                alt,
+                source: Some(source),
+            } => {
+                alts.entry(source.clone()).or_insert_with(|| alt.clone());
+            }
+            Block::Section { blocks, .. } => image_alts(blocks, alts),
+            _ => {}
+        }
+    }
+}
+
+/// The MIME type of a media file, from its signature or else its extension.
+fn mime_type(name: &str, data: &[u8]) -> String {
+    if let Some(kind) = image_kind(data) {
+        return format!("image/{}", kind);
+    }
+    let extension = name.rsplit_once('.').map(|(_, e)| e.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("tif") | Some("tiff") => "image/tiff",
+        Some("bmp") => "image/bmp",
+        Some("svg") => "image/svg+xml",
+        Some("emf") => "image/emf",
+        Some("wmf") => "image/wmf",
+        Some("wdp") => "image/vnd.ms-photo",
+        Some("mp4") => "video/mp4",
+        Some("mov") => "video/quicktime",
+        Some("mp3") => "audio/mpeg",
+        Some("m4a") => "audio/mp4",
+        Some("wav") => "audio/wav",
+        _ => "application/octet-stream",
+    }
+    .to_string()
+}
+
+/// A relationship from a part to another part of the package or to an
+/// external resource.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Relationship {
+    /// The last segment of the relationship type, e.g. `"header"` or
+    /// `"hyperlink"`.
+    pub kind: String,
+    /// The full name of the target part, or the URI of an external target.
+    pub target: String,
+    pub external: bool,
+}
+
+/// The relationships of part `name`, by id, from its `_rels/*.rels` part.
+/// Empty when there is none or it cannot be read.
+pub(super) fn read_relationships<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> HashMap<String, Relationship> {
+    let (directory, file) = name.rsplit_once('/').unwrap_or(("", name));
+    let rels_name = match directory {
+        "" => format!("_rels/{}.rels", file),
+        directory => format!("{}/_rels/{}.rels", directory, file),
+    };
+    let Ok(Some(xml)) = read_part(archive, &rels_name) else {
+        return HashMap::new();
+    };
+
+    let mut reader = quick_xml::Reader::from_str(&xml);
+    let mut relationships = HashMap::new();
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e))
+                if e.local_name().as_ref() == b"Relationship" =>
+            {
+                let (Some(id), Some(target)) = (attr(e, b"Id"), attr(e, b"Target")) else {
+                    continue;
+                };
+                let external = attr(e, b"TargetMode").as_deref() == Some("External");
+                let kind = attr(e, b"Type")
+                    .and_then(|t| t.rsplit('/').next().map(String::from))
+                    .unwrap_or_default();
+                let target = if external {
+                    target
+                } else {
+                    resolve_target(directory, &target)
+                };
+                relationships.insert(
+                    id,
+                    Relationship {
+                        kind,
+                        target,
+                        external,
+                    },
+                );
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    relationships
+}
+
+/// The part `target` names, relative to `directory` unless it starts with `/`.
+fn resolve_target(directory: &str, target: &str) -> String {
+    let mut segments: Vec<&str> = match target.strip_prefix('/') {
+        Some(_) => Vec::new(),
+        None => directory.split('/').filter(|s| !s.is_empty()).collect(),
+    };
+    for segment in target.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+/// Fill `metadata` from the core (`docProps/core.xml`) and extended
+/// (`docProps/app.xml`) properties. Both parts are optional, and unreadable
+/// ones are skipped.
+///
+/// Title, creators, language and timestamps go to the common fields, and the
+/// page count too when the format has no pages of its own; the rest goes to
+/// `extra`. Counts reported by the authoring application are kept as
+/// `reported_word_count` and so on, apart from the counts measured on the
+/// extracted text.
+pub(super) fn read_properties<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    metadata: &mut DocumentMetadata,
+) {
+    let core = read_part(archive, "docProps/core.xml")
+        .ok()
+        .flatten()
+        .map(|xml| simple_properties(&xml))
+        .unwrap_or_default();
+    let app = read_part(archive, "docProps/app.xml")
+        .ok()
+        .flatten()
+        .map(|xml| simple_properties(&xml))
+        .unwrap_or_default();
+
+    if let Some(title) = core.get("title") {
+        metadata.title = Some(title.clone());
+    }
+    if let Some(creator) = core.get("creator") {
+        metadata.authors = split_list(creator, &[';']);
+    }
+    metadata.created = core.get("created").and_then(|d| iso_date_to_rfc3339(d));
+    metadata.modified = core.get("modified").and_then(|d| iso_date_to_rfc3339(d));
+    if let Some(language) = core.get("language") {
+        metadata.language = Some(language.clone());
+    }
+
+    let extra = &mut metadata.extra;
+    for (key, name) in [
+        ("subject", "subject"),
+        ("description", "description"),
+        ("category", "category"),
+        ("contentStatus", "content_status"),
+        ("lastModifiedBy", "last_modified_by"),
+    ] {
+        if let Some(value) = core.get(key) {
+            extra.insert(name.into(), value.clone().into());
+        }
+    }
+    if let Some(keywords) = core.get("keywords") {
+        extra.insert("keywords".into(), split_list(keywords, &[',', ';']).into());
+    }
+    if let Some(revision) = core.get("revision") {
+        let value = match revision.parse::<u64>() {
+            Ok(number) => number.into(),
+            Err(_) => revision.clone().into(),
+        };
+        extra.insert("revision".into(), value);
+    }
+    if let Some(printed) = core.get("lastPrinted").and_then(|d| iso_date_to_rfc3339(d)) {
+        extra.insert("last_printed".into(), printed.into());
+    }
+
+    for (key, name) in [
+        ("Application", "application"),
+        ("AppVersion", "app_version"),
+        ("Company", "company"),
+        ("Manager", "manager"),
+        ("Template", "template"),
+    ] {
+        if let Some(value) = app.get(key) {
+            extra.insert(name.into(), value.clone().into());
+        }
+    }
+    let count = |key: &str| app.get(key).and_then(|v| v.parse::<usize>().ok());
+    if metadata.page_count.is_none() {
+        metadata.page_count = count("Pages");
+    }
+    for (key, name) in [
+        ("Words", "reported_word_count"),
+        ("Characters", "reported_character_count"),
+        ("Paragraphs", "reported_paragraph_count"),
+        ("TotalTime", "total_editing_minutes"),
+    ] {
+        if let Some(value) = count(key) {
+            metadata.extra.insert(name.into(), value.into());
+        }
+    }
+}
+
+/// The non-empty text of each leaf element, by local name. Properties that
+/// hold vectors (`HeadingPairs`, `TitlesOfParts`) are not needed and come out
+/// under the names of their items.
+fn simple_properties(xml: &str) -> HashMap<String, String> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut properties = HashMap::new();
+    let mut current: Option<(String, String)> = None;
+    let mut buf = Vec::new();
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                current = Some((name, String::new()));
+            }
+            Ok(Event::Text(ref e)) => {
+                if let (Some((_, text)), Ok(value)) = (current.as_mut(), e.unescape()) {
+                    text.push_str(&value);
+                }
+            }
+            Ok(Event::End(_)) => {
+                if let Some((name, text)) = current.take() {
+                    let text = text.trim();
+                    if !text.is_empty() {
+                        properties.entry(name).or_insert_with(|| text.to_string());
+                    }
+                }
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    properties
+}
+
+/// The unescaped value of attribute `key` of `e`, matched by its qualified
+/// name.
+pub(super) fn attr(e: &BytesStart, key: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key.as_ref() == key)
+        .and_then(|a| a.unescape_value().ok())
+        .map(|v| v.into_owned())
+}
+
+#[cfg(test)]
+pub(crate) mod test_support {
+    /// `docProps/core.xml` and `docProps/app.xml` for a typical document.
+    pub const PROPERTY_PARTS: [(&str, &str); 2] = [
+        (
+            "docProps/core.xml",
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:dcterms="http://purl.org/dc/terms/" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+  <dc:title>Master Services Agreement</dc:title>
+  <dc:creator>Ada Lovelace; Charles Babbage</dc:creator>
+  <cp:lastModifiedBy>Grace Hopper</cp:lastModifiedBy>
+  <cp:revision>7</cp:revision>
+  <cp:keywords>contract, services</cp:keywords>
+  <dc:language>en-GB</dc:language>
+  <dcterms:created xsi:type="dcterms:W3CDTF">2024-01-02T03:04:05Z</dcterms:created>
+  <dcterms:modified xsi:type="dcterms:W3CDTF">2024-02-03T04:05:06Z</dcterms:modified>
+</cp:coreProperties>"#,
+        ),
+        (
+            "docProps/app.xml",
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Properties xmlns="http://schemas.openxmlformats.org/officeDocument/2006/extended-properties" xmlns:vt="http://schemas.openxmlformats.org/officeDocument/2006/docPropsVTypes">
+  <Template>Normal.dotm</Template>
+  <TotalTime>42</TotalTime>
+  <Pages>3</Pages>
+  <Words>512</Words>
+  <Application>Microsoft Office Word</Application>
+  <HeadingPairs><vt:vector size="2" baseType="variant"><vt:variant><vt:lpstr>Title</vt:lpstr></vt:variant></vt:vector></HeadingPairs>
+</Properties>"#,
+        ),
+    ];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_support::PROPERTY_PARTS;
+    use super::*;
+    use crate::document_parsing::metadata::sha256_hex;
+    use serde_json::json;
+    use std::io::{Cursor, Write};
+
+    fn package(parts: &[(&str, &str)]) -> ZipArchive<Cursor<Vec<u8>>> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in parts {
+            zip.start_file(*name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        ZipArchive::new(zip.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_read_media_part() {
+        let content = "GIF89a".to_string() + &"x".repeat(200);
+        let mut archive = package(&[("word/media/image1.gif", &content)]);
+
+        let media = read_media_part(&mut archive, "word/media/image1.gif", false, 1000).unwrap();
+        assert_eq!(media.size, 206);
+        assert_eq!(media.sha256, sha256_hex(content.as_bytes()));
+        assert_eq!(mime_type("word/media/image1.gif", &media.head), "image/gif");
+        assert_eq!(media.data, None);
+
+        let kept = read_media_part(&mut archive, "word/media/image1.gif", true, 206).unwrap();
+        assert_eq!(kept.data.as_deref(), Some(content.as_bytes()));
+
+        assert!(read_media_part(&mut archive, "word/media/image1.gif", true, 205).is_none());
+        assert!(read_media_part(&mut archive, "word/media/missing.gif", true, 1000).is_none());
+    }
+
+    #[test]
+    fn test_read_properties() {
+        let mut metadata = DocumentMetadata::new("docx");
+        read_properties(&mut package(&PROPERTY_PARTS), &mut metadata);
+
+        assert_eq!(metadata.title.as_deref(), Some("Master Services Agreement"));
+        assert_eq!(metadata.authors, ["Ada Lovelace", "Charles Babbage"]);
+        assert_eq!(metadata.created.as_deref(), Some("2024-01-02T03:04:05Z"));
+        assert_eq!(metadata.modified.as_deref(), Some("2024-02-03T04:05:06Z"));
+        assert_eq!(metadata.language.as_deref(), Some("en-GB"));
+        assert_eq!(metadata.page_count, Some(3));
+        assert_eq!(
+            serde_json::Value::Object(metadata.extra),
+            json!({
+                "last_modified_by": "Grace Hopper",
+                "keywords": ["contract", "services"],
+                "revision": 7,
+                "application": "Microsoft Office Word",
+                "template": "Normal.dotm",
+                "reported_word_count": 512,
+                "total_editing_minutes": 42,
+            })
+        );
+    }
+
+    #[test]
+    fn test_read_relationships() {
+        let mut archive = package(&[(
+            "word/_rels/document.xml.rels",
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+  <Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/header" Target="header1.xml"/>
+  <Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/image" Target="../media/a.png"/>
+  <Relationship Id="rId3" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink" Target="https://example.com/?a=1&amp;b=2" TargetMode="External"/>
+</Relationships>"#,
+        )]);
+        let relationships = read_relationships(&mut archive, "word/document.xml");
+        assert_eq!(relationships.len(), 3);
+        assert_eq!(relationships["rId1"].kind, "header");
+        assert_eq!(relationships["rId1"].target, "word/header1.xml");
+        assert_eq!(relationships["rId2"].target, "media/a.png");
+        assert_eq!(
+            relationships["rId3"],
+            Relationship {
+                kind: "hyperlink".into(),
+                target: "https://example.com/?a=1&b=2".into(),
+                external: true,
+            }
+        );
+        assert!(read_relationships(&mut archive, "word/footnotes.xml").is_empty());
+    }
+
+    #[test]
+    fn test_missing_or_broken_properties_are_skipped() {
+        let mut metadata = DocumentMetadata::new("xlsx");
+        read_properties(
+            &mut package(&[("docProps/core.xml", "<cp:coreProperties><dc:title>")]),
+            &mut metadata,
+        );
+        assert_eq!(metadata, DocumentMetadata::new("xlsx"));
+    }
+}
@@ -0,0 +1,448 @@
+//! Annotations, links and interactive form fields.
+
+use super::info::pdf_date_to_rfc3339;
+use super::text::{self, Glyph, PageLayout};
+use crate::document_parsing::{Annotation, FieldKind, FormField, Link};
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Form field trees deeper than this are treated as malformed.
+const MAX_DEPTH: usize = 32;
+
+/// Button field flags (ISO 32000-2, table 229).
+const FLAG_RADIO: i64 = 1 << 15;
+const FLAG_PUSHBUTTON: i64 = 1 << 16;
+
+/// Read the links and notes of page `number`. Link text is taken from the
+/// glyphs of `layout` under the link.
+pub(super) fn read_page_annotations(
+    doc: &Document,
+    page_id: ObjectId,
+    number: usize,
+    layout: &PageLayout,
+    links: &mut Vec<Link>,
+    annotations: &mut Vec<Annotation>,
+) {
+    for annot in page_annots(doc, page_id) {
+        let Ok(dict) = doc.dereference(annot).and_then(|(_, o)| o.as_dict()) else {
+            continue;
+        };
+        match dict
+            .get(b"Subtype")
+            .and_then(Object::as_name)
+            .unwrap_or(b"")
+        {
+            b"Link" => {
+                if let Some(uri) = link_uri(doc, dict) {
+                    links.push(Link {
+                        uri,
+                        text: rect(dict).and_then(|rect| text_in(layout, rect)),
+                        page: Some(number),
+                    });
+                }
+            }
+            // Form fields are read from the form; popups repeat their parent.
+            b"Widget" | b"Popup" => {}
+            subtype => {
+                let Some(text) = text_string(dict, b"Contents").filter(|t| !t.trim().is_empty())
+                else {
+                    continue;
+                };
+                annotations.push(Annotation {
+                    kind: snake_case(subtype),
+                    text,
+                    author: text_string(dict, b"T").filter(|t| !t.is_empty()),
+                    modified: text_string(dict, b"M").and_then(|m| pdf_date_to_rfc3339(&m)),
+                    page: Some(number),
+                    anchor: None,
+                });
+            }
+        }
+    }
+}
+
+/// Read the fields of the document's interactive form, in form order.
+pub(super) fn read_form_fields(doc: &Document, pages: &BTreeMap<u32, ObjectId>) -> Vec<FormField> {
+    let Some(fields) = doc
+        .catalog()
+        .and_then(|c| c.get_deref(b"AcroForm", doc))
+        .and_then(Object::as_dict)
+        .and_then(|form| form.get_deref(b"Fields", doc))
+        .and_then(Object::as_array)
+        .ok()
+    else {
+        return Vec::new();
+    };
+
+    // Widgets name their page with /P, which is optional; the page's /Annots
+    // always list them.
+    let mut widget_pages = HashMap::new();
+    for (&number, &page_id) in pages {
+        for annot in page_annots(doc, page_id) {
+            if let Ok(id) = annot.as_reference() {
+                widget_pages.insert(id, number as usize);
+            }
+        }
+    }
+    let page_numbers: HashMap<ObjectId, usize> = pages
+        .iter()
+        .map(|(&number, &id)| (id, number as usize))
+        .collect();
+
+    let mut reader = FieldReader {
+        doc,
+        widget_pages,
+        page_numbers,
+        visited: HashSet::new(),
+        fields: Vec::new(),
+    };
+    for field in fields {
+        reader.field(field, "", &Inherited::default(), 0);
+    }
+    reader.fields
+}
+
+fn page_annots(doc: &Document, page_id: ObjectId) -> &[Object] {
+    doc.get_dictionary(page_id)
+        .and_then(|page| page.get_deref(b"Annots", doc))
+        .and_then(Object::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn link_uri(doc: &Document, link: &Dictionary) -> Option<String> {
+    let action = link.get_deref(b"A", doc).and_then(Object::as_dict).ok()?;
+    if action.get(b"S").and_then(Object::as_name).ok()? != b"URI" {
+        return None;
+    }
+    let uri = action
+        .get_deref(b"URI", doc)
+        .and_then(Object::as_str)
+        .ok()?;
+    Some(String::from_utf8_lossy(uri).trim().to_string()).filter(|uri| !uri.is_empty())
+}
+
+/// The annotation rectangle as `(left, bottom, right, top)` in PDF space.
+fn rect(dict: &Dictionary) -> Option<(f64, f64, f64, f64)> {
+    let values: Vec<f64> = dict
+        .get(b"Rect")
+        .and_then(Object::as_array)
+        .ok()?
+        .iter()
+        .filter_map(|v| v.as_float().ok().map(f64::from))
+        .collect();
+    let [x0, y0, x1, y1] = values[..] else {
+        return None;
+    };
+    Some((x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1)))
+}
+
+/// The text drawn inside `rect`.
+fn text_in(
+    layout: &PageLayout,
+    (left, bottom, right, top): (f64, f64, f64, f64),
+) -> Option<String> {
+    let (left, top) = layout.to_page(left, top);
+    let (right, bottom) = layout.to_page(right, bottom);
+    let inside: Vec<Glyph> = layout
+        .glyphs
+        .iter()
+        .filter(|g| {
+            let (x, y) = (g.x + g.width / 2.0, g.y - g.size * 0.3);
+            x >= left && x <= right && y >= top && y <= bottom
+        })
+        .cloned()
+        .collect();
+    let text = text::page_text(&inside)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    Some(text).filter(|t| !t.is_empty())
+}
+
+fn text_string(dict: &Dictionary, key: &[u8]) -> Option<String> {
+    dict.get(key)
+        .ok()
+        .and_then(|value| lopdf::decode_text_string(value).ok())
+        .map(|text| text.trim_start_matches('\u{feff}').to_string())
+}
+
+/// `FreeText` -> `free_text`.
+fn snake_case(name: &[u8]) -> String {
+    let mut out = String::new();
+    for (i, c) in String::from_utf8_lossy(name).chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            out.push('_');
+        }
+        out.extend(c.to_lowercase());
+    }
+    out
+}
+
+/// Field attributes inherited from parent fields.
+#[derive(Default, Clone)]
+struct Inherited<'a> {
+    field_type: Option<&'a [u8]>,
+    flags: i64,
+    value: Option<&'a Object>,
+}
+
+struct FieldReader<'a> {
+    doc: &'a Document,
+    widget_pages: HashMap<ObjectId, usize>,
+    page_numbers: HashMap<ObjectId, usize>,
+    /// Guards against `/Kids` cycles in broken files.
+    visited: HashSet<ObjectId>,
+    fields: Vec<FormField>,
+}
+
+impl<'a> FieldReader<'a> {
+    fn field(&mut self, node: &'a Object, parent: &str, inherited: &Inherited<'a>, depth: usize) {
+        if depth > MAX_DEPTH {
+            return;
+        }
+        let id = node.as_reference().ok();
+        if let Some(id) = id {
+            if !self.visited.insert(id) {
+                return;
+            }
+        }
+        let Ok((_, Object::Dictionary(dict))) = self.doc.dereference(node) else {
+            return;
+        };
+
+        let name = match text_string(dict, b"T") {
+            Some(partial) if !parent.is_empty() => format!("{}.{}", parent, partial),
+            Some(partial) => partial,
+            None => parent.to_string(),
+        };
+        let inherited = Inherited {
+            field_type: dict
+                .get(b"FT")
+                .and_then(Object::as_name)
+                .ok()
+                .or(inherited.field_type),
+            flags: dict
+                .get(b"Ff")
+                .and_then(Object::as_i64)
+                .unwrap_or(inherited.flags),
+            value: dict.get(b"V").ok().or(inherited.value),
+        };
+
+        // Kids with a name are fields; kids without one are the widgets that
+        // show this field.
+        let kids: &[Object] = dict
+            .get(b"Kids")
+            .and_then(Object::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let (fields, widgets): (Vec<&Object>, Vec<&Object>) = kids.iter().partition(|kid| {
+            self.doc
+                .dereference(kid)
+                .and_then(|(_, o)| o.as_dict())
+                .is_ok_and(|kid| kid.has(b"T"))
+        });
+        if !fields.is_empty() {
+            for kid in fields {
+                self.field(kid, &name, &inherited, depth + 1);
+            }
+            return;
+        }
+
+        let Some(kind) = field_kind(inherited.field_type, inherited.flags) else {
+            return;
+        };
+        let page = id
+            .into_iter()
+            .chain(widgets.iter().filter_map(|w| w.as_reference().ok()))
+            .find_map(|widget| self.widget_page(widget));
+        self.fields.push(FormField {
+            name,
+            kind,
+            value: inherited.value.and_then(|v| self.value(v)),
+            page,
+        });
+    }
+
+    fn widget_page(&self, widget: ObjectId) -> Option<usize> {
+        if let Some(&page) = self.widget_pages.get(&widget) {
+            return Some(page);
+        }
+        let page = self
+            .doc
+            .get_dictionary(widget)
+            .and_then(|w| w.get(b"P"))
+            .and_then(Object::as_reference)
+            .ok()?;
+        self.page_numbers.get(&page).copied()
+    }
+
+    fn value(&self, value: &Object) -> Option<String> {
+        let (_, value) = self.doc.dereference(value).ok()?;
+        let text = match value {
+            Object::String(..) => lopdf::decode_text_string(value).ok()?,
+            Object::Name(name) => String::from_utf8_lossy(name).into_owned(),
+            Object::Array(items) => items
+                .iter()
+                .filter_map(|item| self.value(item))
+                .collect::<Vec<_>>()
+                .join(", "),
+            _ => return None,
+        };
+        let text = text.trim_start_matches('\u{feff}').to_string();
+        Some(text).filter(|t| !t.is_empty())
+    }
+}
+
+fn field_kind(field_type: Option<&[u8]>, flags: i64) -> Option<FieldKind> {
+    Some(match field_type? {
+        b"Tx" => FieldKind::Text,
+        b"Ch" => FieldKind::Choice,
+        b"Sig" => FieldKind::Signature,
+        b"Btn" if flags & FLAG_PUSHBUTTON != 0 => FieldKind::Button,
+        b"Btn" if flags & FLAG_RADIO != 0 => FieldKind::Radio,
+        b"Btn" => FieldKind::Checkbox,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parse_pdf;
+    use super::super::test_support::*;
+    use super::*;
+    use lopdf::{dictionary, StringFormat};
+
+    fn literal(text: &str) -> Object {
+        Object::String(text.as_bytes().to_vec(), StringFormat::Literal)
+    }
+
+    fn annotated_pdf() -> Vec<u8> {
+        let pages = [text_content(&[
+            (72.0, 720.0, "Terms apply."),
+            (72.0, 700.0, "Visit example.com today"),
+        ])];
+        build_pdf(&pages, |doc, page_ids| {
+            let page = page_ids[0];
+            let note = doc.add_object(dictionary! {
+                "Type" => "Annot",
+                "Subtype" => "Text",
+                "Rect" => vec![300.into(), 700.into(), 320.into(), 720.into()],
+                "Contents" => literal("Check clause 4"),
+                "T" => literal("Grace"),
+                "M" => literal("D:20240102030405Z"),
+            });
+            let link = doc.add_object(dictionary! {
+                "Type" => "Annot",
+                "Subtype" => "Link",
+                // Around "example.com" on the second line.
+                "Rect" => vec![95.into(), 696.into(), 168.into(), 712.into()],
+                "A" => dictionary! {
+                    "S" => "URI",
+                    "URI" => literal("https://example.com/"),
+                },
+            });
+
+            // "applicant" has a text field "name", shown by its own widget,
+            // and a check box "agree", shown by a separate widget kid.
+            let applicant = doc.new_object_id();
+            let name = doc.add_object(dictionary! {
+                "Type" => "Annot",
+                "Subtype" => "Widget",
+                "Parent" => applicant,
+                "T" => literal("name"),
+                "FT" => "Tx",
+                "V" => literal("Ada Lovelace"),
+                "Rect" => vec![72.into(), 600.into(), 200.into(), 620.into()],
+            });
+            let agree = doc.new_object_id();
+            let agree_widget = doc.add_object(dictionary! {
+                "Type" => "Annot",
+                "Subtype" => "Widget",
+                "Parent" => agree,
+                "P" => page,
+                "Rect" => vec![72.into(), 560.into(), 84.into(), 572.into()],
+            });
+            doc.objects.insert(
+                agree,
+                dictionary! {
+                    "Parent" => applicant,
+                    "T" => literal("agree"),
+                    "FT" => "Btn",
+                    "V" => "Yes",
+                    "Kids" => vec![agree_widget.into()],
+                }
+                .into(),
+            );
+            doc.objects.insert(
+                applicant,
+                dictionary! {
+                    "T" => literal("applicant"),
+                    "Kids" => vec![name.into(), agree.into()],
+                }
+                .into(),
+            );
+            let form = doc.add_object(dictionary! {
+                "Fields" => vec![applicant.into()],
+            });
+
+            doc.get_dictionary_mut(page).unwrap().set(
+                "Annots",
+                vec![note.into(), link.into(), name.into(), agree_widget.into()],
+            );
+            doc.catalog_mut().unwrap().set("AcroForm", form);
+        })
+    }
+
+    #[test]
+    fn test_annotations_and_links() {
+        let doc = parse_pdf(&annotated_pdf()).unwrap();
+        assert_eq!(
+            doc.annotations,
+            [Annotation {
+                kind: "text".into(),
+                text: "Check clause 4".into(),
+                author: Some("Grace".into()),
+                modified: Some("2024-01-02T03:04:05Z".into()),
+                page: Some(1),
+                anchor: None,
+            }]
+        );
+        assert_eq!(
+            doc.links,
+            [Link {
+                uri: "https://example.com/".into(),
+                text: Some("example.com".into()),
+                page: Some(1),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_form_fields() {
+        let doc = parse_pdf(&annotated_pdf()).unwrap();
+        assert_eq!(
+            doc.form_fields,
+            [
+                FormField {
+                    name: "applicant.name".into(),
+                    kind: FieldKind::Text,
+                    value: Some("Ada Lovelace".into()),
+                    page: Some(1),
+                },
+                FormField {
+                    name: "applicant.agree".into(),
+                    kind: FieldKind::Checkbox,
+                    value: Some("Yes".into()),
+                    page: Some(1),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_snake_case() {
+        assert_eq!(snake_case(b"FreeText"), "free_text");
+        assert_eq!(snake_case(b"Highlight"), "highlight");
+    }
+}
@@ -0,0 +1,955 @@
+//! The standard security handler: password checks and decryption of RC4 and
+//! AES encrypted files (ISO 32000-2, 7.6).
+
+use crate::document_parsing::ParseError;
+use aes::cipher::{generic_array::GenericArray, BlockDecrypt, BlockEncrypt, KeyInit};
+use aes::{Aes128, Aes256};
+use lopdf::xref::XrefEntry;
+use lopdf::{Dictionary, Document, Object, ObjectId, ObjectStream, Reader};
+use md5::{Digest, Md5};
+use sha2::{Sha256, Sha384, Sha512};
+
+/// Padding for passwords shorter than 32 bytes (Algorithm 2, step a).
+const PAD: [u8; 32] = [
+    0x28, 0xBF, 0x4E, 0x5E, 0x4E, 0x75, 0x8A, 0x41, 0x64, 0x00, 0x4E, 0x56, 0xFF, 0xFA, 0x01, 0x08,
+    0x2E, 0x2E, 0x00, 0xB6, 0xD0, 0x68, 0x3E, 0x80, 0x2F, 0x0C, 0xA9, 0xFE, 0x64, 0x53, 0x69, 0x7A,
+];
+
+/// Stand-in type for object streams of encrypted files, which cannot be
+/// unpacked until they are decrypted.
+const SEALED_OBJECT_STREAM: &[u8] = b"SealedObjStm";
+
+/// Load a PDF. Object streams of encrypted files are kept packed so that
+/// [`decrypt`] can unpack them.
+pub(super) fn load(bytes: &[u8]) -> Result<Document, ParseError> {
+    let failed = |e: lopdf::Error| ParseError::Format(format!("PDF extraction failed: {}", e));
+    let doc = Document::load_mem(bytes).map_err(failed)?;
+
+    let packed = doc
+        .reference_table
+        .entries
+        .values()
+        .any(|entry| matches!(entry, XrefEntry::Compressed { .. }));
+    if !doc.is_encrypted() || !packed {
+        return Ok(doc);
+    }
+    Reader {
+        buffer: bytes,
+        document: Document::new(),
+    }
+    .read(Some(seal_object_stream))
+    .map_err(failed)
+}
+
+fn seal_object_stream(id: ObjectId, object: &mut Object) -> Option<(ObjectId, Object)> {
+    if let Object::Stream(stream) = object {
+        if stream.dict.type_is(b"ObjStm") {
+            stream
+                .dict
+                .set("Type", Object::Name(SEALED_OBJECT_STREAM.to_vec()));
+        }
+    }
+    // lopdf keeps the object it passed in; the returned copy is only used for
+    // objects inside object streams.
+    Some((id, object.clone()))
+}
+
+/// Decrypt `doc` in place if it is encrypted, trying `password` as the user
+/// and the owner password. Without a password the empty password is tried,
+/// which opens files encrypted only to restrict permissions.
+pub(super) fn decrypt(doc: &mut Document, password: Option<&str>) -> Result<(), ParseError> {
+    let Ok(encrypt_id) = doc.trailer.get(b"Encrypt").and_then(Object::as_reference) else {
+        return Ok(());
+    };
+    let handler = SecurityHandler::new(doc)?;
+    let key = handler
+        .file_key(password.unwrap_or("").as_bytes())
+        .ok_or(ParseError::Encrypted)?;
+
+    for (&id, object) in doc.objects.iter_mut() {
+        if id == encrypt_id {
+            continue;
+        }
+        match object {
+            Object::Stream(stream) => {
+                // Cross-reference streams are never encrypted, and metadata
+                // may be left in the clear.
+                if stream.dict.type_is(b"XRef")
+                    || (stream.dict.type_is(b"Metadata") && !handler.encrypt_metadata)
+                {
+                    continue;
+                }
+                decrypt_strings(&mut stream.dict, &handler.strings, &key, id);
+                let content = handler.streams.decrypt(&key, id, &stream.content);
+                stream.set_content(content);
+            }
+            other => decrypt_object(other, &handler.strings, &key, id),
+        }
+    }
+
+    doc.trailer.remove(b"Encrypt");
+    unpack_object_streams(doc);
+    Ok(())
+}
+
+fn decrypt_object(object: &mut Object, method: &Method, key: &[u8], id: ObjectId) {
+    match object {
+        Object::String(bytes, _) => *bytes = method.decrypt(key, id, bytes),
+        Object::Array(items) => {
+            for item in items {
+                decrypt_object(item, method, key, id);
+            }
+        }
+        Object::Dictionary(dict) => decrypt_strings(dict, method, key, id),
+        _ => {}
+    }
+}
+
+fn decrypt_strings(dict: &mut Dictionary, method: &Method, key: &[u8], id: ObjectId) {
+    for (_, value) in dict.iter_mut() {
+        decrypt_object(value, method, key, id);
+    }
+}
+
+/// Unpack the object streams sealed by [`load`], now that they are readable.
+fn unpack_object_streams(doc: &mut Document) {
+    let mut unpacked = Vec::new();
+    for object in doc.objects.values_mut() {
+        if let Object::Stream(stream) = object {
+            if stream.dict.type_is(SEALED_OBJECT_STREAM) {
+                stream.dict.set("Type", "ObjStm");
+                if let Ok(objects) = ObjectStream::new(stream) {
+                    unpacked.extend(objects.objects);
+                }
+            }
+        }
+    }
+    // Objects written directly in the file win, as when lopdf loads it.
+    for (id, object) in unpacked {
+        doc.objects.entry(id).or_insert(object);
+    }
+}
+
+/// How strings or streams are encrypted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Method {
+    Identity,
+    Rc4,
+    Aes128,
+    Aes256,
+}
+
+impl Method {
+    fn decrypt(&self, key: &[u8], id: ObjectId, data: &[u8]) -> Vec<u8> {
+        match self {
+            Method::Identity => data.to_vec(),
+            Method::Rc4 => rc4(&object_key(key, id, false), data),
+            Method::Aes128 => aes_cbc_decrypt(&object_key(key, id, true), data),
+            Method::Aes256 => aes_cbc_decrypt(key, data),
+        }
+    }
+}
+
+/// Algorithm 1: the key for one object, from the file key and the object id.
+fn object_key(key: &[u8], id: ObjectId, aes: bool) -> Vec<u8> {
+    let mut hasher = Md5::new();
+    hasher.update(key);
+    hasher.update(&id.0.to_le_bytes()[..3]);
+    hasher.update(&id.1.to_le_bytes()[..2]);
+    if aes {
+        hasher.update(b"sAlT");
+    }
+    let digest = hasher.finalize();
+    digest[..(key.len() + 5).min(16)].to_vec()
+}
+
+/// The entries of the encryption dictionary needed to check a password.
+struct SecurityHandler {
+    revision: i64,
+    /// Length of the file key in bytes.
+    key_length: usize,
+    owner: Vec<u8>,
+    user: Vec<u8>,
+    owner_encrypted: Vec<u8>,
+    user_encrypted: Vec<u8>,
+    permissions: i64,
+    file_id: Vec<u8>,
+    encrypt_metadata: bool,
+    strings: Method,
+    streams: Method,
+}
+
+impl SecurityHandler {
+    fn new(doc: &Document) -> Result<Self, ParseError> {
+        let unsupported = |what: String| ParseError::Format(format!("PDF encryption: {}", what));
+        let dict = doc
+            .get_encrypted()
+            .map_err(|e| unsupported(e.to_string()))?;
+
+        let filter = dict.get(b"Filter").and_then(Object::as_name).unwrap_or(b"");
+        if filter != b"Standard" {
+            return Err(unsupported(format!(
+                "unsupported security handler {:?}",
+                String::from_utf8_lossy(filter)
+            )));
+        }
+        let integer = |key: &[u8]| dict.get(key).and_then(Object::as_i64).ok();
+        let bytes = |key: &[u8]| {
+            dict.get(key)
+                .and_then(Object::as_str)
+                .map(<[u8]>::to_vec)
+                .unwrap_or_default()
+        };
+
+        let version = integer(b"V").unwrap_or(0);
+        let revision = integer(b"R").unwrap_or(2);
+        // Revisions 2 to 4 derive keys from MD5 digests, so they cannot give
+        // a key longer than 16 bytes; only revisions 5 and 6 use AES-256.
+        let revisions = match version {
+            1 | 2 => 2..=4,
+            4 => 4..=4,
+            5 => 5..=6,
+            _ => return Err(unsupported(format!("unsupported version {}", version))),
+        };
+        if !revisions.contains(&revision) {
+            return Err(unsupported(format!(
+                "revision {} does not match version {}",
+                revision, version
+            )));
+        }
+        let (strings, streams) = match version {
+            1 | 2 => (Method::Rc4, Method::Rc4),
+            _ => (crypt_filter(dict, b"StrF")?, crypt_filter(dict, b"StmF")?),
+        };
+        let uses = |method: Method| strings == method || streams == method;
+        let mismatched = if version == 5 {
+            uses(Method::Aes128) || uses(Method::Rc4)
+        } else {
+            uses(Method::Aes256)
+        };
+        if mismatched {
+            return Err(unsupported(format!(
+                "crypt filter method does not match version {}",
+                version
+            )));
+        }
+
+        let length = integer(b"Length");
+        let key_length = if version == 5 {
+            32
+        } else if uses(Method::Aes128) {
+            // AES-128 needs a 16-byte key, which RC4 filters of the same file
+            // share.
+            if let Some(bits) = length.filter(|&bits| bits != 128) {
+                return Err(unsupported(format!(
+                    "key length {} does not match AESV2",
+                    bits
+                )));
+            }
+            16
+        } else {
+            let bits = length.unwrap_or(if version == 4 { 128 } else { 40 });
+            if !(40..=128).contains(&bits) || bits % 8 != 0 {
+                return Err(unsupported(format!("unsupported key length {}", bits)));
+            }
+            (bits / 8) as usize
+        };
+
+        let file_id = doc
+            .trailer
+            .get(b"ID")
+            .and_then(Object::as_array)
+            .ok()
+            .and_then(|id| id.first())
+            .and_then(|first| first.as_str().ok())
+            .map(<[u8]>::to_vec)
+            .unwrap_or_default();
+
+        Ok(Self {
+            revision,
+            key_length,
+            owner: bytes(b"O"),
+            user: bytes(b"U"),
+            owner_encrypted: bytes(b"OE"),
+            user_encrypted: bytes(b"UE"),
+            permissions: integer(b"P").unwrap_or(0),
+            file_id,
+            encrypt_metadata: dict
+                .get(b"EncryptMetadata")
+                .and_then(Object::as_bool)
+                .unwrap_or(true),
+            strings,
+            streams,
+        })
+    }
+
+    /// The file key, if `password` is the user or the owner password.
+    fn file_key(&self, password: &[u8]) -> Option<Vec<u8>> {
+        if self.revision >= 5 {
+            return self.file_key_aes256(password);
+        }
+        if let Some(key) = self.user_key(password) {
+            return Some(key);
+        }
+        // Algorithm 7: the owner password decrypts the user password.
+        let owner_key = self.owner_key(password);
+        let mut user_password = self.owner.get(..32)?.to_vec();
+        if self.revision == 2 {
+            user_password = rc4(&owner_key, &user_password);
+        } else {
+            for i in (0..20).rev() {
+                let key: Vec<u8> = owner_key.iter().map(|b| b ^ i).collect();
+                user_password = rc4(&key, &user_password);
+            }
+        }
+        self.user_key(&user_password)
+    }
+
+    /// Algorithm 2 and the check of Algorithms 4 and 5.
+    fn user_key(&self, password: &[u8]) -> Option<Vec<u8>> {
+        let key = self.compute_key(password);
+        let expected = self.user_hash(&key);
+        let compared = if self.revision == 2 { 32 } else { 16 };
+        (self.user.get(..compared)? == &expected[..compared]).then_some(key)
+    }
+
+    /// Algorithm 2: the file key for a user password.
+    fn compute_key(&self, password: &[u8]) -> Vec<u8> {
+        let mut hasher = Md5::new();
+        hasher.update(pad(password));
+        hasher.update(self.owner.get(..32).unwrap_or(&self.owner));
+        hasher.update((self.permissions as u32).to_le_bytes());
+        hasher.update(&self.file_id);
+        if self.revision >= 4 && !self.encrypt_metadata {
+            hasher.update([0xFF; 4]);
+        }
+        let mut digest = hasher.finalize().to_vec();
+        if self.revision >= 3 {
+            for _ in 0..50 {
+                digest = Md5::digest(&digest[..self.key_length]).to_vec();
+            }
+        }
+        digest.truncate(self.key_length);
+        digest
+    }
+
+    /// Algorithms 4 and 5: the expected `/U` entry for a file key.
+    fn user_hash(&self, key: &[u8]) -> Vec<u8> {
+        if self.revision == 2 {
+            return rc4(key, &PAD);
+        }
+        let mut hasher = Md5::new();
+        hasher.update(PAD);
+        hasher.update(&self.file_id);
+        let mut hash = rc4(key, &hasher.finalize());
+        for i in 1..20u8 {
+            let key: Vec<u8> = key.iter().map(|b| b ^ i).collect();
+            hash = rc4(&key, &hash);
+        }
+        hash.resize(32, 0);
+        hash
+    }
+
+    /// Algorithm 3, steps a to d: the RC4 key protecting `/O`.
+    fn owner_key(&self, password: &[u8]) -> Vec<u8> {
+        let mut digest = Md5::digest(pad(password)).to_vec();
+        if self.revision >= 3 {
+            for _ in 0..50 {
+                digest = Md5::digest(&digest).to_vec();
+            }
+        }
+        digest.truncate(self.key_length);
+        digest
+    }
+
+    /// Algorithm 2.A: the file key of an AES-256 file.
+    fn file_key_aes256(&self, password: &[u8]) -> Option<Vec<u8>> {
+        let password = &password[..password.len().min(127)];
+        let (user, owner) = (self.user.get(..48)?, self.owner.get(..48)?);
+
+        let (salt, udata, wrapped) =
+            if password_hash(self.revision, password, &owner[32..40], user) == owner[..32] {
+                (&owner[40..48], user, &self.owner_encrypted)
+            } else if password_hash(self.revision, password, &user[32..40], &[]) == user[..32] {
+                (&user[40..48], &[][..], &self.user_encrypted)
+            } else {
+                return None;
+            };
+        if wrapped.len() < 32 {
+            return None;
+        }
+
+        let intermediate = password_hash(self.revision, password, salt, udata);
+        let mut iv_and_key = vec![0; 16];
+        iv_and_key.extend_from_slice(&wrapped[..32]);
+        Some(aes_cbc_decrypt_unpadded(&intermediate, &iv_and_key))
+    }
+}
+
+/// The password hash of revision 5 (SHA-256) or 6 (Algorithm 2.B).
+fn password_hash(revision: i64, password: &[u8], salt: &[u8], udata: &[u8]) -> Vec<u8> {
+    let mut k = Sha256::new()
+        .chain_update(password)
+        .chain_update(salt)
+        .chain_update(udata)
+        .finalize()
+        .to_vec();
+    if revision == 5 {
+        return k;
+    }
+
+    let mut round = 0;
+    loop {
+        let mut k1 = Vec::with_capacity(64 * (password.len() + k.len() + udata.len()));
+        for _ in 0..64 {
+            k1.extend_from_slice(password);
+            k1.extend_from_slice(&k);
+            k1.extend_from_slice(udata);
+        }
+        let e = cbc_encrypt(
+            &Aes128::new(GenericArray::from_slice(&k[..16])),
+            &k[16..32],
+            &k1,
+        );
+        // The first 16 bytes of E as a number, modulo 3.
+        let remainder = e[..16].iter().map(|&b| b as u32).sum::<u32>() % 3;
+        k = match remainder {
+            0 => Sha256::digest(&e).to_vec(),
+            1 => Sha384::digest(&e).to_vec(),
+            _ => Sha512::digest(&e).to_vec(),
+        };
+        round += 1;
+        if round >= 64 && u32::from(*e.last().unwrap_or(&0)) + 32 <= round {
+            break;
+        }
+    }
+    k.truncate(32);
+    k
+}
+
+/// The method of the crypt filter named by `entry` (`/StrF` or `/StmF`).
+fn crypt_filter(dict: &Dictionary, entry: &[u8]) -> Result<Method, ParseError> {
+    let name = dict
+        .get(entry)
+        .and_then(Object::as_name)
+        .unwrap_or(b"Identity");
+    if name == b"Identity" {
+        return Ok(Method::Identity);
+    }
+    let method = dict
+        .get(b"CF")
+        .and_then(Object::as_dict)
+        .and_then(|filters| filters.get(name))
+        .and_then(Object::as_dict)
+        .and_then(|filter| filter.get(b"CFM"))
+        .and_then(Object::as_name)
+        .unwrap_or(b"None");
+    match method {
+        b"None" => Ok(Method::Identity),
+        b"V2" => Ok(Method::Rc4),
+        b"AESV2" => Ok(Method::Aes128),
+        b"AESV3" => Ok(Method::Aes256),
+        other => Err(ParseError::Format(format!(
+            "PDF encryption: unsupported crypt filter method {:?}",
+            String::from_utf8_lossy(other)
+        ))),
+    }
+}
+
+fn pad(password: &[u8]) -> [u8; 32] {
+    let mut padded = PAD;
+    let len = password.len().min(32);
+    padded[..len].copy_from_slice(&password[..len]);
+    padded[len..].copy_from_slice(&PAD[..32 - len]);
+    padded
+}
+
+fn rc4(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut state: Vec<u8> = (0..=255).collect();
+    let mut j: u8 = 0;
+    for i in 0..256 {
+        j = j.wrapping_add(state[i]).wrapping_add(key[i % key.len()]);
+        state.swap(i, j as usize);
+    }
+
+    let (mut i, mut j) = (0u8, 0u8);
+    data.iter()
+        .map(|&byte| {
+            i = i.wrapping_add(1);
+            j = j.wrapping_add(state[i as usize]);
+            state.swap(i as usize, j as usize);
+            let k = state[state[i as usize].wrapping_add(state[j as usize]) as usize];
+            byte ^ k
+        })
+        .collect()
+}
+
+/// Decrypt AES-CBC data that starts with its IV and ends with PKCS#7
+/// padding. Damaged padding is left in place rather than failing the file.
+fn aes_cbc_decrypt(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut plain = aes_cbc_decrypt_unpadded(key, data);
+    if let Some(&n) = plain.last() {
+        let n = n as usize;
+        if (1..=16).contains(&n)
+            && n <= plain.len()
+            && plain[plain.len() - n..].iter().all(|&b| b as usize == n)
+        {
+            plain.truncate(plain.len() - n);
+        }
+    }
+    plain
+}
+
+/// Decrypt AES-CBC `data`, whose first block is the IV, without removing
+/// padding. A trailing partial block is dropped, and nothing is decrypted
+/// with a key that is not 16 or 32 bytes long.
+fn aes_cbc_decrypt_unpadded(key: &[u8], data: &[u8]) -> Vec<u8> {
+    if data.len() < 32 {
+        return Vec::new();
+    }
+    let data = &data[..data.len() / 16 * 16];
+    match key.len() {
+        32 => cbc_decrypt(&Aes256::new(GenericArray::from_slice(key)), data),
+        16 => cbc_decrypt(&Aes128::new(GenericArray::from_slice(key)), data),
+        _ => Vec::new(),
+    }
+}
+
+fn cbc_decrypt(cipher: &impl BlockDecrypt, data: &[u8]) -> Vec<u8> {
+    let mut plain = data[16..].to_vec();
+    for (block, previous) in plain.chunks_mut(16).zip(data.chunks(16)) {
+        cipher.decrypt_block(GenericArray::from_mut_slice(block));
+        for (byte, prev) in block.iter_mut().zip(previous) {
+            *byte ^= prev;
+        }
+    }
+    plain
+}
+
+/// CBC encryption without padding; `data` is a whole number of blocks.
+fn cbc_encrypt(cipher: &impl BlockEncrypt, iv: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut out = data.to_vec();
+    let mut previous = iv.to_vec();
+    for block in out.chunks_mut(16) {
+        for (byte, prev) in block.iter_mut().zip(&previous) {
+            *byte ^= prev;
+        }
+        cipher.encrypt_block(GenericArray::from_mut_slice(block));
+        previous.copy_from_slice(block);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::*;
+    use super::super::{parse_pdf, parse_pdf_with_options};
+    use super::*;
+    use crate::document_parsing::{parse, DocumentFormat, ParseOptions};
+    use lopdf::{dictionary, StringFormat};
+
+    const FILE_ID: &[u8] = b"0123456789abcdef";
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Encrypt every string and stream of `doc` with `encrypt`, as a writer
+    /// would, and install `dict` as the encryption dictionary.
+    fn encrypt_document(
+        doc: &mut Document,
+        dict: Dictionary,
+        encrypt: impl Fn(ObjectId, &[u8]) -> Vec<u8>,
+    ) {
+        fn strings(
+            object: &mut Object,
+            id: ObjectId,
+            encrypt: &dyn Fn(ObjectId, &[u8]) -> Vec<u8>,
+        ) {
+            match object {
+                Object::String(bytes, _) => *bytes = encrypt(id, bytes),
+                Object::Array(items) => {
+                    items.iter_mut().for_each(|item| strings(item, id, encrypt))
+                }
+                Object::Dictionary(dict) => dict
+                    .iter_mut()
+                    .for_each(|(_, value)| strings(value, id, encrypt)),
+                Object::Stream(stream) => {
+                    stream
+                        .dict
+                        .iter_mut()
+                        .for_each(|(_, value)| strings(value, id, encrypt));
+                    let content = encrypt(id, &stream.content);
+                    stream.set_content(content);
+                }
+                _ => {}
+            }
+        }
+
+        let info_id = doc.add_object(dictionary! {
+            "Title" => Object::String(b"Quarterly figures".to_vec(), StringFormat::Literal),
+        });
+        doc.trailer.set("Info", info_id);
+        for (&id, object) in doc.objects.iter_mut() {
+            strings(object, id, &encrypt);
+        }
+
+        let encrypt_id = doc.add_object(dict);
+        doc.trailer.set("Encrypt", encrypt_id);
+        let id = Object::String(FILE_ID.to_vec(), StringFormat::Hexadecimal);
+        doc.trailer.set("ID", vec![id.clone(), id]);
+    }
+
+    fn aes_encrypt(key: &[u8], data: &[u8]) -> Vec<u8> {
+        let iv = [9u8; 16];
+        let padding = 16 - data.len() % 16;
+        let mut padded = data.to_vec();
+        padded.extend(std::iter::repeat_n(padding as u8, padding));
+        let body = match key.len() {
+            32 => cbc_encrypt(&Aes256::new(GenericArray::from_slice(key)), &iv, &padded),
+            _ => cbc_encrypt(&Aes128::new(GenericArray::from_slice(key)), &iv, &padded),
+        };
+        [&iv[..], &body].concat()
+    }
+
+    fn string(bytes: Vec<u8>) -> Object {
+        Object::String(bytes, StringFormat::Hexadecimal)
+    }
+
+    fn page() -> Vec<String> {
+        vec![text_content(&[(72.0, 720.0, "Confidential figures")])]
+    }
+
+    /// An AES-256 (revision 6) file with user password "user" and owner
+    /// password "owner".
+    fn aes256_pdf() -> Vec<u8> {
+        build_pdf(&page(), |doc, _| {
+            let file_key = [7u8; 32];
+            let wrap = |key: &[u8]| {
+                let cipher = Aes256::new(GenericArray::from_slice(key));
+                cbc_encrypt(&cipher, &[0; 16], &file_key)
+            };
+            let (validation, key_salt) = ([1u8; 8], [2u8; 8]);
+
+            let user = [
+                password_hash(6, b"user", &validation, &[]),
+                validation.to_vec(),
+                key_salt.to_vec(),
+            ]
+            .concat();
+            let user_encrypted = wrap(&password_hash(6, b"user", &key_salt, &[]));
+            let owner = [
+                password_hash(6, b"owner", &validation, &user),
+                validation.to_vec(),
+                key_salt.to_vec(),
+            ]
+            .concat();
+            let owner_encrypted = wrap(&password_hash(6, b"owner", &key_salt, &user));
+
+            let dict = dictionary! {
+                "Filter" => "Standard",
+                "V" => 5,
+                "R" => 6,
+                "Length" => 256,
+                "CF" => dictionary! {
+                    "StdCF" => dictionary! { "CFM" => "AESV3", "Length" => 32 },
+                },
+                "StmF" => "StdCF",
+                "StrF" => "StdCF",
+                "O" => string(owner),
+                "U" => string(user),
+                "OE" => string(owner_encrypted),
+                "UE" => string(user_encrypted),
+                "P" => -4,
+            };
+            encrypt_document(doc, dict, |_, data| aes_encrypt(&file_key, data));
+        })
+    }
+
+    /// A revision 4 file using `method` for strings and streams, with user
+    /// password `user` and owner password "owner".
+    fn standard_pdf(method: Method, user: &str) -> Vec<u8> {
+        build_pdf(&page(), |doc, _| {
+            let mut handler = SecurityHandler {
+                revision: 4,
+                key_length: 16,
+                owner: Vec::new(),
+                user: Vec::new(),
+                owner_encrypted: Vec::new(),
+                user_encrypted: Vec::new(),
+                permissions: -4,
+                file_id: FILE_ID.to_vec(),
+                encrypt_metadata: true,
+                strings: method,
+                streams: method,
+            };
+            // Algorithm 3: /O is the padded user password encrypted with a
+            // key derived from the owner password.
+            let owner_key = handler.owner_key(b"owner");
+            let mut owner = rc4(&owner_key, &pad(user.as_bytes()));
+            for i in 1..20u8 {
+                let key: Vec<u8> = owner_key.iter().map(|b| b ^ i).collect();
+                owner = rc4(&key, &owner);
+            }
+            handler.owner = owner;
+            let key = handler.compute_key(user.as_bytes());
+            handler.user = handler.user_hash(&key);
+
+            let cfm = if method == Method::Rc4 { "V2" } else { "AESV2" };
+            let dict = dictionary! {
+                "Filter" => "Standard",
+                "V" => 4,
+                "R" => 4,
+                "Length" => 128,
+                "CF" => dictionary! {
+                    "StdCF" => dictionary! { "CFM" => cfm, "Length" => 16 },
+                },
+                "StmF" => "StdCF",
+                "StrF" => "StdCF",
+                "O" => string(handler.owner.clone()),
+                "U" => string(handler.user.clone()),
+                "P" => -4,
+            };
+            encrypt_document(doc, dict, |id, data| match method {
+                Method::Rc4 => rc4(&object_key(&key, id, false), data),
+                _ => aes_encrypt(&object_key(&key, id, true), data),
+            });
+        })
+    }
+
+    fn with_password(password: &str) -> ParseOptions {
+        ParseOptions {
+            password: Some(password.into()),
+            ..ParseOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_password_hash_r6() {
+        // Reference values from an independent implementation of Algorithm 2.B.
+        let salt = [1, 2, 3, 4, 5, 6, 7, 8];
+        assert_eq!(
+            hex(&password_hash(6, b"user", &salt, &[])),
+            "17424b40ead366f7ddef0ff073608aa68ba701714b5cef3409b94c4ffa763726"
+        );
+        assert_eq!(
+            hex(&password_hash(6, b"owner", &salt, &[0x11; 48])),
+            "66705699bc248a9e9aaee7fbb72b0384d88ceca2e4ed57ec6bc73d77bf7c43ba"
+        );
+    }
+
+    #[test]
+    fn test_rc4() {
+        assert_eq!(hex(&rc4(b"Key", b"Plaintext")), "bbf316e8d940af0ad3");
+    }
+
+    #[test]
+    fn test_aes256_user_and_owner_passwords() {
+        let pdf = aes256_pdf();
+
+        for password in ["user", "owner"] {
+            let doc = parse_pdf_with_options(&pdf, &with_password(password)).unwrap();
+            assert!(doc.text.contains("Confidential figures"));
+            assert_eq!(doc.metadata.title.as_deref(), Some("Quarterly figures"));
+            assert_eq!(doc.metadata.extra["encryption"]["method"], "AES-256");
+        }
+    }
+
+    #[test]
+    fn test_missing_or_wrong_password() {
+        let pdf = aes256_pdf();
+        assert!(matches!(parse_pdf(&pdf), Err(ParseError::Encrypted)));
+        let wrong = parse_pdf_with_options(&pdf, &with_password("guess"));
+        assert!(matches!(wrong, Err(ParseError::Encrypted)));
+
+        let pdf = standard_pdf(Method::Aes128, "user");
+        assert!(matches!(parse_pdf(&pdf), Err(ParseError::Encrypted)));
+    }
+
+    #[test]
+    fn test_aes128_and_rc4() {
+        for method in [Method::Aes128, Method::Rc4] {
+            let pdf = standard_pdf(method, "user");
+            for password in ["user", "owner"] {
+                let doc = parse_pdf_with_options(&pdf, &with_password(password)).unwrap();
+                assert!(doc.text.contains("Confidential figures"), "{:?}", method);
+                assert_eq!(doc.metadata.title.as_deref(), Some("Quarterly figures"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_empty_user_password_needs_no_password() {
+        let doc = parse_pdf(&standard_pdf(Method::Aes128, "")).unwrap();
+        assert!(doc.text.contains("Confidential figures"));
+        assert_eq!(doc.metadata.extra["encrypted"], true);
+    }
+
+    /// A file whose encryption dictionary has version `v`, revision `r`,
+    /// key length `bits` and the given string and stream methods. Its
+    /// strings and streams are left in the clear.
+    fn malformed_pdf(v: i64, r: i64, bits: i64, strings: &str, streams: &str) -> Vec<u8> {
+        build_pdf(&page(), |doc, _| {
+            let dict = dictionary! {
+                "Filter" => "Standard",
+                "V" => v,
+                "R" => r,
+                "Length" => bits,
+                "CF" => dictionary! {
+                    "StrCF" => dictionary! { "CFM" => strings },
+                    "StmCF" => dictionary! { "CFM" => streams },
+                },
+                "StrF" => "StrCF",
+                "StmF" => "StmCF",
+                "O" => string(vec![1; 32]),
+                "U" => string(vec![2; 32]),
+                "P" => -4,
+            };
+            encrypt_document(doc, dict, |_, data| data.to_vec());
+        })
+    }
+
+    #[test]
+    fn test_mismatched_encryption_dictionary() {
+        for (v, r, bits, strings, streams) in [
+            (4, 3, 128, "AESV3", "AESV3"),
+            (4, 4, 256, "AESV3", "AESV3"),
+            (5, 6, 256, "AESV2", "AESV3"),
+            (4, 4, 40, "AESV2", "V2"),
+            (4, 4, 20, "V2", "V2"),
+            (2, 5, 128, "V2", "V2"),
+        ] {
+            let pdf = malformed_pdf(v, r, bits, strings, streams);
+            let result = parse(&pdf, DocumentFormat::Pdf);
+            assert!(
+                matches!(result, Err(ParseError::Format(_))),
+                "V {} R {} Length {} {}/{}: {:?}",
+                v,
+                r,
+                bits,
+                strings,
+                streams,
+                result.map(|doc| doc.text)
+            );
+        }
+    }
+
+    #[test]
+    fn test_aes_with_short_key_decrypts_nothing() {
+        assert!(aes_cbc_decrypt(&[7; 10], &[0; 48]).is_empty());
+    }
+
+    /// An RC4 file with an empty user password whose catalog, page tree,
+    /// page and font are packed in an encrypted object stream, written by
+    /// hand since lopdf does not write object streams.
+    fn packed_pdf() -> Vec<u8> {
+        let handler = SecurityHandler {
+            revision: 2,
+            key_length: 5,
+            owner: vec![1; 32],
+            user: Vec::new(),
+            owner_encrypted: Vec::new(),
+            user_encrypted: Vec::new(),
+            permissions: -4,
+            file_id: FILE_ID.to_vec(),
+            encrypt_metadata: true,
+            strings: Method::Rc4,
+            streams: Method::Rc4,
+        };
+        let key = handler.compute_key(b"");
+        let encrypt = |number: u32, data: &[u8]| rc4(&object_key(&key, (number, 0), false), data);
+
+        let packed = [
+            (1, "<< /Type /Catalog /Pages 2 0 R >>"),
+            (2, "<< /Type /Pages /Kids [3 0 R] /Count 1 >>"),
+            (
+                3,
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Contents 4 0 R \
+                 /Resources << /Font << /F1 6 0 R >> >> >>",
+            ),
+            (6, "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>"),
+        ];
+        let (mut index, mut body) = (Vec::new(), String::new());
+        for (number, object) in packed {
+            index.push(format!("{} {}", number, body.len()));
+            body.push_str(object);
+            body.push('\n');
+        }
+        let index = index.join(" ") + "\n";
+
+        let mut out = b"%PDF-1.5\n".to_vec();
+        let mut offsets = std::collections::BTreeMap::new();
+        let mut add = |out: &mut Vec<u8>, number: u32, dict: String, stream: Option<Vec<u8>>| {
+            offsets.insert(number, out.len());
+            out.extend(format!("{} 0 obj\n{}", number, dict).bytes());
+            if let Some(data) = stream {
+                out.extend(b"\nstream\n");
+                out.extend(data);
+                out.extend(b"\nendstream");
+            }
+            out.extend(b"\nendobj\n");
+        };
+
+        let content = encrypt(
+            4,
+            &text_content(&[(72.0, 720.0, "Packed and sealed")]).into_bytes(),
+        );
+        add(
+            &mut out,
+            4,
+            format!("<< /Length {} >>", content.len()),
+            Some(content),
+        );
+        let stream = encrypt(5, (index.clone() + &body).as_bytes());
+        let dict = format!(
+            "<< /Type /ObjStm /N 4 /First {} /Length {} >>",
+            index.len(),
+            stream.len()
+        );
+        add(&mut out, 5, dict, Some(stream));
+        let dict = format!(
+            "<< /Filter /Standard /V 1 /R 2 /O <{}> /U <{}> /P -4 >>",
+            hex(&handler.owner),
+            hex(&handler.user_hash(&key))
+        );
+        add(&mut out, 7, dict, None);
+
+        // Cross-reference stream: type, offset or object stream, generation
+        // or index.
+        let xref_offset = out.len();
+        let mut rows = vec![(0u8, 0u32, 65535u16)];
+        for number in 1..=8u32 {
+            rows.push(match number {
+                1..=3 => (2, 5, number as u16 - 1),
+                6 => (2, 5, 3),
+                8 => (1, xref_offset as u32, 0),
+                _ => (1, offsets[&number] as u32, 0),
+            });
+        }
+        let mut table = Vec::new();
+        for (kind, field, generation) in rows {
+            table.push(kind);
+            table.extend(field.to_be_bytes());
+            table.extend(generation.to_be_bytes());
+        }
+        out.extend(
+            format!(
+                "8 0 obj\n<< /Type /XRef /Size 9 /W [1 4 2] /Root 1 0 R /Encrypt 7 0 R \
+                 /ID [<{id}> <{id}>] /Length {} >>\nstream\n",
+                table.len(),
+                id = hex(FILE_ID)
+            )
+            .bytes(),
+        );
+        out.extend(table);
+        out.extend(format!("\nendstream\nendobj\nstartxref\n{}\n%%EOF\n", xref_offset).bytes());
+        out
+    }
+
+    #[test]
+    fn test_encrypted_object_streams() {
+        let doc = parse_pdf(&packed_pdf()).unwrap();
+        assert_eq!(doc.metadata.page_count, Some(1));
+        assert!(doc.text.contains("Packed and sealed"));
+    }
+}
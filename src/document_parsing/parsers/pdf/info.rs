@@ -0,0 +1,343 @@
+//! Document-level metadata: the Info dictionary, the XMP packet, the PDF
+//! version and the encryption dictionary.
+
+use crate::document_parsing::metadata::{iso_date_to_rfc3339, split_list};
+use crate::document_parsing::DocumentMetadata;
+use lopdf::{Dictionary, Document, Object};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// Fill `metadata` from the Info dictionary and XMP packet of `doc`.
+///
+/// The Info dictionary wins where both are present; the XMP packet fills the
+/// gaps, which matters for files written by tools that only update XMP.
+pub(super) fn read_metadata(doc: &Document, metadata: &mut DocumentMetadata) {
+    let info = info_dictionary(doc);
+    let xmp = xmp_properties(doc);
+    let info_text = |key: &[u8]| -> Option<String> {
+        let value = lopdf::decode_text_string(info?.get(key).ok()?).ok()?;
+        let value = value.trim_start_matches('\u{feff}').trim();
+        (!value.is_empty()).then(|| value.to_string())
+    };
+    let xmp_first = |key: &str| xmp.get(key).and_then(|values| values.first()).cloned();
+
+    metadata.title = info_text(b"Title").or_else(|| xmp_first("dc:title"));
+    metadata.authors = match info_text(b"Author") {
+        Some(author) => split_list(&author, &[';']),
+        None => xmp.get("dc:creator").cloned().unwrap_or_default(),
+    };
+    metadata.created = info_text(b"CreationDate")
+        .and_then(|d| pdf_date_to_rfc3339(&d))
+        .or_else(|| xmp_first("xmp:CreateDate").and_then(|d| iso_date_to_rfc3339(&d)));
+    metadata.modified = info_text(b"ModDate")
+        .and_then(|d| pdf_date_to_rfc3339(&d))
+        .or_else(|| xmp_first("xmp:ModifyDate").and_then(|d| iso_date_to_rfc3339(&d)));
+    metadata.language = catalog(doc)
+        .and_then(|c| lopdf::decode_text_string(c.get(b"Lang").ok()?).ok())
+        .filter(|lang| !lang.is_empty())
+        .or_else(|| xmp_first("dc:language"));
+
+    let extra = &mut metadata.extra;
+    if let Some(subject) = info_text(b"Subject").or_else(|| xmp_first("dc:description")) {
+        extra.insert("subject".into(), subject.into());
+    }
+    let keywords = match info_text(b"Keywords").or_else(|| xmp_first("pdf:Keywords")) {
+        Some(keywords) => split_list(&keywords, &[',', ';']),
+        None => xmp.get("dc:subject").cloned().unwrap_or_default(),
+    };
+    if !keywords.is_empty() {
+        extra.insert("keywords".into(), json!(keywords));
+    }
+    if let Some(producer) = info_text(b"Producer").or_else(|| xmp_first("pdf:Producer")) {
+        extra.insert("producer".into(), producer.into());
+    }
+    if let Some(creator) = info_text(b"Creator").or_else(|| xmp_first("xmp:CreatorTool")) {
+        extra.insert("creator_tool".into(), creator.into());
+    }
+    extra.insert("pdf_version".into(), pdf_version(doc).into());
+}
+
+/// A summary of the encryption dictionary, or `None` for unencrypted files.
+/// Must be read before the document is decrypted.
+pub(super) fn encryption(doc: &Document) -> Option<Map<String, Value>> {
+    let dict = doc.get_encrypted().ok()?;
+    let int = |key: &[u8]| dict.get(key).and_then(Object::as_i64).ok();
+    let name = |d: &Dictionary, key: &[u8]| -> Option<String> {
+        Some(String::from_utf8_lossy(d.get(key).ok()?.as_name().ok()?).into_owned())
+    };
+
+    let version = int(b"V").unwrap_or(0);
+    let method = match version {
+        1 | 2 => "RC4",
+        4 => {
+            // The crypt filter used for streams decides the cipher.
+            let filter = name(dict, b"StmF").unwrap_or_default();
+            let cfm = dict
+                .get(b"CF")
+                .and_then(Object::as_dict)
+                .and_then(|cf| cf.get(filter.as_bytes()))
+                .and_then(Object::as_dict)
+                .ok()
+                .and_then(|f| name(f, b"CFM"));
+            match cfm.as_deref() {
+                Some("AESV2") => "AES-128",
+                Some("V2") => "RC4",
+                _ => "unknown",
+            }
+        }
+        5 => "AES-256",
+        _ => "unknown",
+    };
+
+    let mut summary = Map::new();
+    summary.insert("filter".into(), json!(name(dict, b"Filter")));
+    summary.insert("version".into(), json!(version));
+    summary.insert("revision".into(), json!(int(b"R")));
+    summary.insert("method".into(), method.into());
+    Some(summary)
+}
+
+fn catalog(doc: &Document) -> Option<&Dictionary> {
+    doc.trailer
+        .get(b"Root")
+        .and_then(Object::as_reference)
+        .and_then(|id| doc.get_dictionary(id))
+        .ok()
+}
+
+fn info_dictionary(doc: &Document) -> Option<&Dictionary> {
+    match doc.trailer.get(b"Info").ok()? {
+        Object::Reference(id) => doc.get_dictionary(*id).ok(),
+        Object::Dictionary(dict) => Some(dict),
+        _ => None,
+    }
+}
+
+/// The header version, unless the catalog's `/Version` (PDF 1.4+) raises it.
+fn pdf_version(doc: &Document) -> String {
+    let header = doc.version.clone();
+    let catalog_version = catalog(doc)
+        .and_then(|c| c.get(b"Version").ok())
+        .and_then(|v| v.as_name().ok())
+        .map(|v| String::from_utf8_lossy(v).into_owned());
+
+    match catalog_version {
+        Some(v) if version_key(&v) > version_key(&header) => v,
+        _ => header,
+    }
+}
+
+fn version_key(version: &str) -> (u32, u32) {
+    let (major, minor) = version.split_once('.').unwrap_or((version, "0"));
+    (major.parse().unwrap_or(0), minor.parse().unwrap_or(0))
+}
+
+/// XMP properties we map, by qualified name. Array properties (`rdf:Seq`,
+/// `rdf:Bag`, `rdf:Alt`) have one value per `rdf:li`.
+const XMP_PROPERTIES: [&str; 10] = [
+    "dc:title",
+    "dc:creator",
+    "dc:description",
+    "dc:subject",
+    "dc:language",
+    "pdf:Keywords",
+    "pdf:Producer",
+    "xmp:CreateDate",
+    "xmp:ModifyDate",
+    "xmp:CreatorTool",
+];
+
+fn xmp_properties(doc: &Document) -> HashMap<String, Vec<String>> {
+    let packet = catalog(doc)
+        .and_then(|c| c.get(b"Metadata").and_then(Object::as_reference).ok())
+        .and_then(|id| doc.get_object(id).and_then(Object::as_stream).ok())
+        .map(|stream| {
+            // Unfiltered streams are returned as an error.
+            stream
+                .decompressed_content()
+                .unwrap_or_else(|_| stream.content.clone())
+        });
+
+    match packet {
+        Some(xml) => parse_xmp(&String::from_utf8_lossy(&xml)),
+        None => HashMap::new(),
+    }
+}
+
+fn parse_xmp(xml: &str) -> HashMap<String, Vec<String>> {
+    let mut properties: HashMap<String, Vec<String>> = HashMap::new();
+    let mut reader = Reader::from_str(xml);
+    let mut current: Option<String> = None;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => {
+                let name = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+                if XMP_PROPERTIES.contains(&name.as_str()) {
+                    current = Some(name);
+                } else if name == "rdf:Description" {
+                    // Simple properties may be written as attributes.
+                    for attr in e.attributes().flatten() {
+                        let key = String::from_utf8_lossy(attr.key.as_ref()).into_owned();
+                        if XMP_PROPERTIES.contains(&key.as_str()) {
+                            if let Ok(value) = attr.unescape_value() {
+                                properties.entry(key).or_default().push(value.trim().into());
+                            }
+                        }
+                    }
+                }
+            }
+            Ok(Event::Text(e)) => {
+                if let (Some(name), Ok(text)) = (&current, e.unescape()) {
+                    let text = text.trim();
+                    if !text.is_empty() {
+                        properties
+                            .entry(name.clone())
+                            .or_default()
+                            .push(text.to_string());
+                    }
+                }
+            }
+            Ok(Event::End(e))
+                if current.as_deref().map(str::as_bytes) == Some(e.name().as_ref()) =>
+            {
+                current = None;
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+
+    properties
+}
+
+/// Convert a PDF date string (`D:YYYYMMDDHHmmSSOHH'mm'`, every part after
+/// the year optional) to RFC 3339. Dates without a time zone are taken as
+/// UTC.
+pub(super) fn pdf_date_to_rfc3339(date: &str) -> Option<String> {
+    let date = date.trim();
+    let digits = date.strip_prefix("D:").unwrap_or(date);
+    let numeric_len = digits.bytes().take_while(u8::is_ascii_digit).count();
+    if numeric_len < 4 {
+        return None;
+    }
+
+    let field = |start: usize, len: usize, default: u32| -> u32 {
+        digits
+            .get(start..start + len)
+            .filter(|_| start + len <= numeric_len)
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(default)
+    };
+    let (year, month, day) = (field(0, 4, 0), field(4, 2, 1), field(6, 2, 1));
+    let (hour, minute, second) = (field(8, 2, 0), field(10, 2, 0), field(12, 2, 0));
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+
+    let zone = &digits[numeric_len..];
+    let offset = match zone.chars().next() {
+        Some(sign @ ('+' | '-')) => {
+            let zone_digits: String = zone[1..].chars().filter(char::is_ascii_digit).collect();
+            let hours = zone_digits.get(0..2).unwrap_or("00");
+            let minutes = zone_digits.get(2..4).unwrap_or("00");
+            format!("{}{}:{}", sign, hours, minutes)
+        }
+        _ => "Z".to_string(),
+    };
+
+    Some(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}",
+        year,
+        month,
+        day,
+        hour,
+        minute,
+        second.min(59),
+        offset
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::*;
+    use super::*;
+    use lopdf::{dictionary, Stream, StringFormat};
+
+    #[test]
+    fn test_pdf_date_to_rfc3339() {
+        assert_eq!(
+            pdf_date_to_rfc3339("D:20230415103000+02'00'").as_deref(),
+            Some("2023-04-15T10:30:00+02:00")
+        );
+        assert_eq!(
+            pdf_date_to_rfc3339("D:20230415103000Z").as_deref(),
+            Some("2023-04-15T10:30:00Z")
+        );
+        assert_eq!(
+            pdf_date_to_rfc3339("D:2023").as_deref(),
+            Some("2023-01-01T00:00:00Z")
+        );
+        assert_eq!(pdf_date_to_rfc3339("yesterday"), None);
+    }
+
+    #[test]
+    fn test_parse_xmp() {
+        let xmp = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF>
+            <rdf:Description pdf:Producer="Writer 1.0">
+              <dc:title><rdf:Alt><rdf:li xml:lang="x-default">Annual Report</rdf:li></rdf:Alt></dc:title>
+              <dc:creator><rdf:Seq><rdf:li>Ada</rdf:li><rdf:li>Grace</rdf:li></rdf:Seq></dc:creator>
+            </rdf:Description></rdf:RDF></x:xmpmeta>"#;
+        let props = parse_xmp(xmp);
+        assert_eq!(props["dc:title"], ["Annual Report"]);
+        assert_eq!(props["dc:creator"], ["Ada", "Grace"]);
+        assert_eq!(props["pdf:Producer"], ["Writer 1.0"]);
+    }
+
+    #[test]
+    fn test_read_metadata_from_info_and_xmp() {
+        let xmp = r#"<x:xmpmeta><rdf:RDF><rdf:Description>
+            <xmp:ModifyDate>2024-02-01T08:00:00Z</xmp:ModifyDate>
+            </rdf:Description></rdf:RDF></x:xmpmeta>"#;
+        let bytes = build_pdf(&[text_content(&[(72.0, 720.0, "Hi")])], |doc, _| {
+            let info_id = doc.add_object(dictionary! {
+                "Title" => Object::String(b"\xFE\xFF\x00Q\x00\xe9".to_vec(), StringFormat::Hexadecimal),
+                "Author" => Object::string_literal("Ada Lovelace; Charles Babbage"),
+                "Keywords" => Object::string_literal("engines, notes"),
+                "CreationDate" => Object::string_literal("D:20230415103000-05'00'"),
+            });
+            doc.trailer.set("Info", info_id);
+            let metadata_id = doc.add_object(Stream::new(
+                dictionary! { "Type" => "Metadata", "Subtype" => "XML" },
+                xmp.as_bytes().to_vec(),
+            ));
+            let catalog_id = doc.trailer.get(b"Root").unwrap().as_reference().unwrap();
+            let catalog = doc.get_dictionary_mut(catalog_id).unwrap();
+            catalog.set("Metadata", metadata_id);
+            catalog.set("Lang", Object::string_literal("en-GB"));
+        });
+
+        let doc = Document::load_mem(&bytes).unwrap();
+        let mut metadata = DocumentMetadata::new("pdf");
+        read_metadata(&doc, &mut metadata);
+
+        assert_eq!(metadata.title.as_deref(), Some("Qé"));
+        assert_eq!(metadata.authors, ["Ada Lovelace", "Charles Babbage"]);
+        assert_eq!(
+            metadata.created.as_deref(),
+            Some("2023-04-15T10:30:00-05:00")
+        );
+        assert_eq!(metadata.modified.as_deref(), Some("2024-02-01T08:00:00Z"));
+        assert_eq!(metadata.language.as_deref(), Some("en-GB"));
+        assert_eq!(metadata.extra["keywords"], json!(["engines", "notes"]));
+        assert_eq!(metadata.extra["pdf_version"], "1.5");
+    }
+
+    #[test]
+    fn test_unencrypted_has_no_encryption_summary() {
+        let bytes = create_pdf(&[text_content(&[(72.0, 720.0, "Hi")])]);
+        assert!(encryption(&Document::load_mem(&bytes).unwrap()).is_none());
+    }
+}
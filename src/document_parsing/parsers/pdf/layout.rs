@@ -0,0 +1,316 @@
+//! Reading order from the page layout.
+//!
+//! Lines are split at wide gaps and stacked into text blocks, which are put
+//! in order by recursive XY-cut: the page is split at the widest empty
+//! vertical strip first, so columns are read one after the other, and at
+//! empty horizontal strips otherwise. Text repeated in the top or bottom
+//! margin of several pages, and page numbers there, are dropped.
+
+use super::tables::{self, DetectedTable};
+use super::text::{self, PageLayout, Word};
+use crate::document_parsing::Block;
+use std::collections::HashMap;
+
+/// Words further apart than this many font sizes belong to different
+/// columns.
+const COLUMN_GAP: f64 = 1.0;
+/// Consecutive lines of a block are at most this many font sizes apart.
+const MAX_LINE_SPACING: f64 = 1.7;
+/// Fraction of the page height at the top and bottom where headers and
+/// footers are looked for.
+const MARGIN: f64 = 0.08;
+/// Margin text on at least this fraction of the pages is a running header or
+/// footer.
+const REPEATED_ON: f64 = 0.5;
+
+/// The blocks of each page in reading order.
+pub(super) fn pages_in_reading_order(pages: &[PageLayout]) -> Vec<Vec<Block>> {
+    let items: Vec<Vec<Item>> = pages.iter().map(items).collect();
+
+    // Count the pages each margin text appears on.
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for (page, items) in pages.iter().zip(&items) {
+        let mut keys: Vec<String> = items
+            .iter()
+            .filter_map(|item| item.margin_text(page.height))
+            .map(|text| normalize(&text))
+            .collect();
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            *seen.entry(key).or_default() += 1;
+        }
+    }
+    let threshold = ((pages.len() as f64 * REPEATED_ON).ceil() as usize).max(2);
+
+    pages
+        .iter()
+        .zip(items)
+        .map(|(page, items)| {
+            let kept = items
+                .into_iter()
+                .filter(|item| match item.margin_text(page.height) {
+                    Some(text) => {
+                        !is_page_number(&text)
+                            && seen.get(&normalize(&text)).is_none_or(|&n| n < threshold)
+                    }
+                    None => true,
+                })
+                .collect();
+
+            let mut ordered = Vec::new();
+            xy_cut(kept, &mut ordered);
+            ordered.into_iter().map(Item::into_block).collect()
+        })
+        .collect()
+}
+
+/// A text block or a table, and the box it occupies.
+#[derive(Debug)]
+struct Item {
+    left: f64,
+    right: f64,
+    top: f64,
+    bottom: f64,
+    content: Content,
+}
+
+#[derive(Debug)]
+enum Content {
+    Text(Vec<String>),
+    Table(DetectedTable),
+}
+
+impl Item {
+    /// The text of a block in the top or bottom margin of a page of the given
+    /// height.
+    fn margin_text(&self, height: f64) -> Option<String> {
+        let in_margin = self.bottom <= height * MARGIN || self.top >= height * (1.0 - MARGIN);
+        match &self.content {
+            Content::Text(lines) if in_margin => Some(lines.join(" ")),
+            _ => None,
+        }
+    }
+
+    fn into_block(self) -> Block {
+        match self.content {
+            Content::Text(lines) => Block::paragraph(lines.join("\n")),
+            Content::Table(detected) => Block::Table(detected.table),
+        }
+    }
+}
+
+/// Tables and blocks of text on a page, in no particular order.
+fn items(page: &PageLayout) -> Vec<Item> {
+    let tables = tables::detect_tables(page);
+    let glyphs: Vec<_> = page
+        .glyphs
+        .iter()
+        .filter(|glyph| !tables.iter().any(|table| table.contains(glyph)))
+        .cloned()
+        .collect();
+
+    let mut items: Vec<Item> = Vec::new();
+    for fragment in text::lines(text::words(&glyphs))
+        .iter()
+        .flat_map(|line| line.fragments(COLUMN_GAP))
+    {
+        match items.iter_mut().find(|item| continues(item, &fragment)) {
+            Some(item) => {
+                item.left = item.left.min(fragment.x0);
+                item.right = item.right.max(fragment.x1);
+                item.bottom = fragment.y;
+                if let Content::Text(lines) = &mut item.content {
+                    lines.push(fragment.text);
+                }
+            }
+            None => items.push(Item {
+                left: fragment.x0,
+                right: fragment.x1,
+                top: fragment.y - fragment.size,
+                bottom: fragment.y,
+                content: Content::Text(vec![fragment.text]),
+            }),
+        }
+    }
+
+    items.extend(tables.into_iter().map(|table| Item {
+        left: table.left,
+        right: table.right,
+        top: table.top,
+        bottom: table.bottom,
+        content: Content::Table(table),
+    }));
+    items
+}
+
+/// Whether `fragment` is the next line of the text block `item`: just below
+/// its last line and overlapping it horizontally.
+fn continues(item: &Item, fragment: &Word) -> bool {
+    let Content::Text(_) = item.content else {
+        return false;
+    };
+    let spacing = fragment.y - item.bottom;
+    spacing > fragment.size * 0.5
+        && spacing <= fragment.size * MAX_LINE_SPACING
+        && fragment.x0 < item.right
+        && fragment.x1 > item.left
+}
+
+/// Append `items` to `out` in reading order.
+fn xy_cut(mut items: Vec<Item>, out: &mut Vec<Item>) {
+    if items.len() <= 1 {
+        out.append(&mut items);
+        return;
+    }
+
+    let (before, after): (Vec<Item>, Vec<Item>) =
+        if let Some(x) = widest_gap(&items, |item| (item.left, item.right)) {
+            items.into_iter().partition(|item| item.right <= x)
+        } else if let Some(y) = first_gap(&items, |item| (item.top, item.bottom)) {
+            items.into_iter().partition(|item| item.bottom <= y)
+        } else {
+            items.sort_by(|a, b| a.top.total_cmp(&b.top).then(a.left.total_cmp(&b.left)));
+            out.append(&mut items);
+            return;
+        };
+
+    xy_cut(before, out);
+    xy_cut(after, out);
+}
+
+/// Empty strips between the extents of `items`, as `(start, end)` pairs.
+fn gaps(items: &[Item], extent: impl Fn(&Item) -> (f64, f64)) -> Vec<(f64, f64)> {
+    let mut extents: Vec<(f64, f64)> = items.iter().map(extent).collect();
+    extents.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut gaps = Vec::new();
+    let mut end = extents[0].1;
+    for &(start, stop) in &extents[1..] {
+        if start > end {
+            gaps.push((end, start));
+        }
+        end = end.max(stop);
+    }
+    gaps
+}
+
+/// The middle of the widest empty strip.
+fn widest_gap(items: &[Item], extent: impl Fn(&Item) -> (f64, f64)) -> Option<f64> {
+    gaps(items, extent)
+        .into_iter()
+        .max_by(|a, b| (a.1 - a.0).total_cmp(&(b.1 - b.0)))
+        .map(|(start, end)| (start + end) / 2.0)
+}
+
+/// The middle of the first empty strip.
+fn first_gap(items: &[Item], extent: impl Fn(&Item) -> (f64, f64)) -> Option<f64> {
+    gaps(items, extent)
+        .first()
+        .map(|(start, end)| (start + end) / 2.0)
+}
+
+/// Collapse whitespace and case, and mask digits so running headers such as
+/// "Chapter 3 - page 12" compare equal across pages.
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_digit() { '#' } else { c })
+        .collect()
+}
+
+/// Matches "12", "Page 12", "12 of 40", "- 12 -" and roman numerals.
+fn is_page_number(text: &str) -> bool {
+    let text = text.trim().to_lowercase();
+    let text = text.strip_prefix("page").unwrap_or(&text);
+    let mut numbered = false;
+    for token in text.split(|c: char| c.is_whitespace() || matches!(c, '-' | '–' | '/' | '|')) {
+        match token {
+            "" | "of" => {}
+            _ if token.chars().all(|c| c.is_ascii_digit()) => numbered = true,
+            _ if token.chars().all(|c| matches!(c, 'i' | 'v' | 'x' | 'l')) => numbered = true,
+            _ => return false,
+        }
+    }
+    numbered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::*;
+    use super::super::{parse_pdf_with_options, ParseOptions};
+    use super::*;
+    use crate::document_parsing::ReadingOrder;
+
+    fn layout_options() -> ParseOptions {
+        ParseOptions {
+            reading_order: ReadingOrder::Layout,
+            ..ParseOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_columns_are_read_in_turn() {
+        // Drawn line by line across both columns.
+        let page = text_content(&[
+            (72.0, 700.0, "The left column starts here and"),
+            (320.0, 700.0, "The right column starts here and"),
+            (72.0, 686.0, "carries on for a few more words"),
+            (320.0, 686.0, "ends after a couple more lines"),
+            (72.0, 672.0, "before it comes to an end."),
+            (320.0, 672.0, "of text than its neighbour."),
+        ]);
+        let pdf = create_pdf(&[page]);
+
+        let doc = parse_pdf_with_options(&pdf, &layout_options()).unwrap();
+        let Block::Section { blocks, .. } = &doc.blocks[0] else {
+            panic!("expected a page section");
+        };
+        assert_eq!(
+            blocks,
+            &[
+                Block::paragraph(
+                    "The left column starts here and\ncarries on for a few more words\nbefore it comes to an end."
+                ),
+                Block::paragraph(
+                    "The right column starts here and\nends after a couple more lines\nof text than its neighbour."
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_running_headers_and_page_numbers_are_removed() {
+        let pages: Vec<String> = (1..=3)
+            .map(|n| {
+                text_content(&[
+                    (72.0, 760.0, &format!("Annual Report {}", 2020 + n)),
+                    (72.0, 700.0, &format!("Body of page {}", n)),
+                    (300.0, 40.0, &n.to_string()),
+                ])
+            })
+            .collect();
+        let pdf = create_pdf(&pages);
+
+        let doc = parse_pdf_with_options(&pdf, &layout_options()).unwrap();
+        assert!(!doc.text.contains("Annual Report"));
+        assert!(doc.text.contains("Body of page 2"));
+        assert!(!doc.text.contains("\n3"));
+
+        let doc = parse_pdf_with_options(&pdf, &ParseOptions::default()).unwrap();
+        assert!(doc.text.contains("Annual Report 2021"));
+    }
+
+    #[test]
+    fn test_is_page_number() {
+        assert!(is_page_number("12"));
+        assert!(is_page_number("Page 3 of 10"));
+        assert!(is_page_number("- 4 -"));
+        assert!(is_page_number("xiv"));
+        assert!(!is_page_number("Figure 2"));
+        assert!(!is_page_number(""));
+    }
+}
@@ -0,0 +1,388 @@
+mod annots;
+mod crypt;
+mod info;
+mod layout;
+mod outline;
+mod scan;
+mod tables;
+mod text;
+
+use super::{DocumentParser, ParseError};
+use crate::document_parsing::blocks::paragraphs_from_text;
+use crate::document_parsing::{
+    Block, DocumentFormat, DocumentMetadata, OcrEngine, ParseOptions, ParsedDocument, ReadingOrder,
+    SectionKind,
+};
+use std::collections::HashSet;
+
+/// PDF parser.
+pub struct PdfParser;
+
+impl DocumentParser for PdfParser {
+    fn id(&self) -> &str {
+        DocumentFormat::Pdf.as_str()
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["pdf"]
+    }
+
+    fn mime_types(&self) -> &[&str] {
+        &["application/pdf"]
+    }
+
+    fn parse(&self, bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
+        parse_pdf(bytes)
+    }
+
+    fn parse_with_options(
+        &self,
+        bytes: &[u8],
+        options: &ParseOptions,
+    ) -> Result<ParsedDocument, ParseError> {
+        parse_pdf_with_options(bytes, options)
+    }
+}
+
+pub fn parse_pdf(bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
+    parse_pdf_with_options(bytes, &ParseOptions::default())
+}
+
+/// Parse a PDF into one [`SectionKind::Page`] section per page.
+///
+/// With `options.pages` set, only those pages are extracted; `page_count`
+/// still reports the length of the whole document. Tables found from the
+/// page layout become [`Block::Table`]s in place of their text, and
+/// `options.reading_order` chooses how the rest of the text is ordered. Outline entries become
+/// headings on the pages they point at, and the full tree is kept in
+/// [`ParsedDocument::outline`]. Link URIs, notes and form fields on the
+/// selected pages are returned in [`ParsedDocument::links`],
+/// [`ParsedDocument::annotations`] and [`ParsedDocument::form_fields`].
+///
+/// Pages that are only a scanned image are listed in the `scanned_pages`
+/// metadata entry, and read with `options.ocr` when an engine is given.
+/// Pages whose content cannot be read are listed in `failed_pages` and left
+/// empty; the document only fails when none of its pages can be read.
+pub fn parse_pdf_with_options(
+    bytes: &[u8],
+    options: &ParseOptions,
+) -> Result<ParsedDocument, ParseError> {
+    let mut doc = crypt::load(bytes)?;
+    let encryption = info::encryption(&doc);
+    crypt::decrypt(&mut doc, options.password.as_deref())?;
+    let pages = doc.get_pages();
+
+    let mut metadata = DocumentMetadata::new(DocumentFormat::Pdf.as_str());
+    metadata.page_count = Some(pages.len());
+    info::read_metadata(&doc, &mut metadata);
+    metadata
+        .extra
+        .insert("encrypted".into(), encryption.is_some().into());
+    if let Some(encryption) = encryption {
+        metadata
+            .extra
+            .insert("encryption".into(), encryption.into());
+    }
+    if let Some(range) = options.pages {
+        metadata
+            .extra
+            .insert("page_range".into(), range.to_string().into());
+    }
+    if options.reading_order != ReadingOrder::default() {
+        metadata.extra.insert(
+            "reading_order".into(),
+            options.reading_order.to_string().into(),
+        );
+    }
+
+    let mut numbers = Vec::new();
+    let mut layouts = Vec::new();
+    let mut failed = Vec::new();
+    let mut first_error = None;
+    for &number in pages.keys() {
+        let page = number as usize;
+        if options.pages.is_some_and(|range| !range.contains(page)) {
+            continue;
+        }
+        numbers.push(page);
+        match text::extract_page(&doc, number) {
+            Ok(layout) => layouts.push(layout),
+            Err(err) => {
+                failed.push(page);
+                first_error.get_or_insert(err);
+                layouts.push(text::PageLayout::default());
+            }
+        }
+    }
+    // One bad page leaves an empty section; a document of nothing but bad
+    // pages is an error.
+    if let Some(err) = first_error {
+        if failed.len() == numbers.len() {
+            return Err(err);
+        }
+        metadata.extra.insert("failed_pages".into(), failed.into());
+    }
+
+    let mut links = Vec::new();
+    let mut annotations = Vec::new();
+    for (&number, layout) in numbers.iter().zip(&layouts) {
+        let page_id = pages[&(number as u32)];
+        annots::read_page_annotations(&doc, page_id, number, layout, &mut links, &mut annotations);
+    }
+    let mut form_fields = annots::read_form_fields(&doc, &pages);
+    form_fields.retain(|field| {
+        field
+            .page
+            .is_none_or(|page| options.pages.is_none_or(|range| range.contains(page)))
+    });
+
+    let mut page_blocks = match options.reading_order {
+        ReadingOrder::Content => layouts.iter().map(page_blocks).collect(),
+        ReadingOrder::Layout => layout::pages_in_reading_order(&layouts),
+    };
+
+    let mut scanned = Vec::new();
+    for ((&number, layout), blocks) in numbers.iter().zip(&layouts).zip(&mut page_blocks) {
+        let images = scan::page_images(&doc, pages[&(number as u32)]);
+        if !scan::is_scanned(layout, &images) {
+            continue;
+        }
+        scanned.push(number);
+        if let Some(engine) = &options.ocr {
+            *blocks = ocr_page(&doc, &images, engine.as_ref())?;
+        }
+    }
+    if !scanned.is_empty() {
+        metadata
+            .extra
+            .insert("scanned_pages".into(), scanned.into());
+        if let Some(engine) = &options.ocr {
+            metadata
+                .extra
+                .insert("ocr_engine".into(), engine.id().into());
+        }
+    }
+    let mut blocks: Vec<Block> = numbers
+        .into_iter()
+        .zip(page_blocks)
+        .map(|(number, blocks)| Block::Section {
+            kind: SectionKind::Page,
+            number,
+            title: None,
+            blocks,
+        })
+        .collect();
+
+    let outline = outline::read_outline(&doc);
+    outline::apply_outline(&mut blocks, &outline);
+
+    let mut parsed = ParsedDocument::new(bytes, blocks, metadata);
+    parsed.outline = outline;
+    parsed.links = links;
+    parsed.annotations = annotations;
+    parsed.form_fields = form_fields;
+    Ok(parsed)
+}
+
+/// The paragraphs read by `engine` from the images of a scanned page.
+fn ocr_page(
+    doc: &lopdf::Document,
+    images: &[scan::PlacedImage],
+    engine: &dyn OcrEngine,
+) -> Result<Vec<Block>, ParseError> {
+    let mut seen = HashSet::new();
+    let mut text = String::new();
+    for image in images {
+        if !seen.insert(image.id) {
+            continue;
+        }
+        let Some(encoded) = scan::encode_image(doc, image.id) else {
+            continue;
+        };
+        let recognized = engine.recognize(&encoded)?;
+        if !text.is_empty() {
+            text.push_str("\n\n");
+        }
+        text.push_str(recognized.trim());
+    }
+    Ok(paragraphs_from_text(&text))
+}
+
+/// The paragraphs and tables of a page, in order down the page. Text beside
+/// a table follows it.
+fn page_blocks(layout: &text::PageLayout) -> Vec<Block> {
+    let tables = tables::detect_tables(layout);
+
+    let mut bands = vec![Vec::new(); tables.len() + 1];
+    for glyph in &layout.glyphs {
+        if tables.iter().any(|table| table.contains(glyph)) {
+            continue;
+        }
+        let band = tables.iter().filter(|table| table.top <= glyph.y).count();
+        bands[band].push(glyph.clone());
+    }
+
+    let mut blocks = Vec::new();
+    let mut tables = tables.into_iter();
+    for band in bands {
+        blocks.extend(paragraphs_from_text(&text::page_text(&band)));
+        if let Some(detected) = tables.next() {
+            blocks.push(Block::Table(detected.table));
+        }
+    }
+    blocks
+}
+
+/// Builders for small PDFs used by the tests of this module and its children.
+#[cfg(test)]
+pub(crate) mod test_support {
+    use lopdf::{dictionary, Document, Object, ObjectId, Stream};
+
+    /// A content stream drawing each `(x, y, text)` in 12pt Helvetica, with `y`
+    /// measured up from the bottom of a 612x792 page.
+    pub fn text_content(items: &[(f64, f64, &str)]) -> String {
+        items
+            .iter()
+            .map(|(x, y, text)| {
+                let escaped = text
+                    .replace('\\', "\\\\")
+                    .replace('(', "\\(")
+                    .replace(')', "\\)");
+                format!("BT /F1 12 Tf 1 0 0 1 {} {} Tm ({}) Tj ET\n", x, y, escaped)
+            })
+            .collect()
+    }
+
+    /// A PDF with one page per content stream.
+    pub fn create_pdf(pages: &[String]) -> Vec<u8> {
+        build_pdf(pages, |_, _| {})
+    }
+
+    /// Like [`create_pdf`], letting `customize` add objects before the file is
+    /// written. It receives the document and the page ids.
+    pub fn build_pdf(
+        pages: &[String],
+        customize: impl FnOnce(&mut Document, &[ObjectId]),
+    ) -> Vec<u8> {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+        });
+        let resources_id = doc.add_object(dictionary! {
+            "Font" => dictionary! { "F1" => font_id },
+        });
+
+        let page_ids: Vec<ObjectId> = pages
+            .iter()
+            .map(|content| {
+                let content_id =
+                    doc.add_object(Stream::new(dictionary! {}, content.as_bytes().to_vec()));
+                doc.add_object(dictionary! {
+                    "Type" => "Page",
+                    "Parent" => pages_id,
+                    "Contents" => content_id,
+                    "Resources" => resources_id,
+                    "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+                })
+            })
+            .collect();
+
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => page_ids.iter().map(|&id| id.into()).collect::<Vec<Object>>(),
+                "Count" => page_ids.len() as i64,
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        doc.trailer.set("Root", catalog_id);
+
+        customize(&mut doc, &page_ids);
+
+        let mut bytes = Vec::new();
+        doc.save_to(&mut bytes).expect("write test PDF");
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_support::*;
+    use super::*;
+    use crate::document_parsing::PageRange;
+
+    fn three_pages() -> Vec<u8> {
+        create_pdf(&[
+            text_content(&[(72.0, 720.0, "First page")]),
+            text_content(&[(72.0, 720.0, "Second page")]),
+            text_content(&[(72.0, 720.0, "Third page")]),
+        ])
+    }
+
+    #[test]
+    fn test_parse_pdf_invalid() {
+        let result = parse_pdf(b"not a pdf");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_pdf_keeps_pages_after_a_failed_one() {
+        let pdf = create_pdf(&[
+            text_content(&[(72.0, 720.0, "First page")]),
+            // Uses a font the page does not have.
+            "BT /F9 12 Tf 72 720 Td (Broken) Tj ET".to_string(),
+            text_content(&[(72.0, 720.0, "Third page")]),
+        ]);
+        let doc = parse_pdf(&pdf).unwrap();
+        assert_eq!(doc.metadata.extra["failed_pages"], serde_json::json!([2]));
+        assert_eq!(doc.blocks.len(), 3);
+        assert!(matches!(
+            &doc.blocks[1],
+            Block::Section { number: 2, blocks, .. } if blocks.is_empty()
+        ));
+        assert!(doc.text.contains("Third page"));
+
+        let broken = create_pdf(&["BT /F9 12 Tf (Broken) Tj ET".to_string()]);
+        assert!(matches!(parse_pdf(&broken), Err(ParseError::Format(_))));
+    }
+
+    #[test]
+    fn test_parse_pdf_pages() {
+        let doc = parse_pdf(&three_pages()).unwrap();
+        assert_eq!(doc.metadata.page_count, Some(3));
+        assert_eq!(doc.blocks.len(), 3);
+        match &doc.blocks[1] {
+            Block::Section {
+                kind: SectionKind::Page,
+                number: 2,
+                blocks,
+                ..
+            } => assert_eq!(blocks, &[Block::paragraph("Second page")]),
+            other => panic!("unexpected block {:?}", other),
+        }
+        assert!(doc.text.contains("--- Page 3 ---\nThird page"));
+        assert_eq!(doc.metadata.extra["encrypted"], false);
+        assert_eq!(doc.metadata.extra["pdf_version"], "1.5");
+    }
+
+    #[test]
+    fn test_parse_pdf_page_range() {
+        let options = ParseOptions {
+            pages: Some(PageRange::from(2)),
+            ..ParseOptions::default()
+        };
+        let doc = parse_pdf_with_options(&three_pages(), &options).unwrap();
+        assert_eq!(doc.metadata.page_count, Some(3));
+        assert_eq!(doc.metadata.extra["page_range"], "2-");
+        assert!(!doc.text.contains("First page"));
+        assert!(doc.text.contains("Second page"));
+        assert!(doc.text.contains("Third page"));
+    }
+}
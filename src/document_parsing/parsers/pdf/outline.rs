@@ -0,0 +1,329 @@
+//! The document outline (bookmarks).
+
+use crate::document_parsing::{Block, OutlineItem};
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use std::collections::{HashMap, HashSet};
+
+/// Outlines and name trees deeper than this are treated as malformed.
+const MAX_DEPTH: usize = 32;
+
+/// Read the outline tree of `doc`, resolving each entry's destination to a
+/// page number.
+pub(super) fn read_outline(doc: &Document) -> Vec<OutlineItem> {
+    let Some(first) = doc
+        .catalog()
+        .and_then(|c| c.get_deref(b"Outlines", doc))
+        .and_then(Object::as_dict)
+        .and_then(|outlines| outlines.get(b"First"))
+        .ok()
+    else {
+        return Vec::new();
+    };
+
+    let pages: HashMap<ObjectId, usize> = doc
+        .get_pages()
+        .into_iter()
+        .map(|(number, id)| (id, number as usize))
+        .collect();
+    let mut reader = OutlineReader {
+        doc,
+        pages,
+        visited: HashSet::new(),
+    };
+    reader.siblings(first, 0)
+}
+
+/// Turn outline entries into headings inside the page sections they point at,
+/// so chunking splits at them and records them in `heading_path`.
+///
+/// A paragraph on the target page whose text is the entry's title becomes the
+/// heading; otherwise the heading is inserted at the top of the page, after
+/// any earlier headings for the same page.
+pub(super) fn apply_outline(blocks: &mut [Block], outline: &[OutlineItem]) {
+    let mut by_page: HashMap<usize, Vec<(u8, &str)>> = HashMap::new();
+    flatten(outline, 1, &mut by_page);
+
+    for block in blocks {
+        let Block::Section {
+            number,
+            blocks: children,
+            ..
+        } = block
+        else {
+            continue;
+        };
+        let Some(headings) = by_page.get(number) else {
+            continue;
+        };
+
+        let mut cursor = 0;
+        for &(level, title) in headings {
+            let wanted = normalize(title);
+            let existing = children[cursor..]
+                .iter()
+                .position(|b| matches!(b, Block::Paragraph { text } if normalize(text) == wanted));
+            match existing {
+                Some(offset) => {
+                    cursor += offset;
+                    children[cursor] = Block::heading(level, title);
+                }
+                None => children.insert(cursor, Block::heading(level, title)),
+            }
+            cursor += 1;
+        }
+    }
+}
+
+fn flatten<'a>(items: &'a [OutlineItem], level: u8, out: &mut HashMap<usize, Vec<(u8, &'a str)>>) {
+    for item in items {
+        if let Some(page) = item.page {
+            out.entry(page).or_default().push((level, &item.title));
+        }
+        flatten(&item.children, level.saturating_add(1), out);
+    }
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+struct OutlineReader<'a> {
+    doc: &'a Document,
+    pages: HashMap<ObjectId, usize>,
+    /// Guards against `/Next` or `/First` cycles in broken files.
+    visited: HashSet<ObjectId>,
+}
+
+impl<'a> OutlineReader<'a> {
+    /// The entry `first` and its `/Next` siblings.
+    fn siblings(&mut self, first: &Object, depth: usize) -> Vec<OutlineItem> {
+        let mut items = Vec::new();
+        let mut next = first.as_reference().ok();
+
+        while let Some(id) = next {
+            if depth > MAX_DEPTH || !self.visited.insert(id) {
+                break;
+            }
+            let Ok(entry) = self.doc.get_dictionary(id) else {
+                break;
+            };
+
+            let title = entry
+                .get(b"Title")
+                .ok()
+                .and_then(|t| lopdf::decode_text_string(t).ok())
+                .unwrap_or_default();
+            let children = match entry.get(b"First") {
+                Ok(first) => self.siblings(first, depth + 1),
+                Err(_) => Vec::new(),
+            };
+            items.push(OutlineItem {
+                title: title.trim_start_matches('\u{feff}').trim().to_string(),
+                page: self.entry_page(entry),
+                children,
+            });
+
+            next = entry.get(b"Next").and_then(Object::as_reference).ok();
+        }
+
+        items
+    }
+
+    fn entry_page(&self, entry: &Dictionary) -> Option<usize> {
+        if let Ok(dest) = entry.get(b"Dest") {
+            return self.destination_page(dest, 0);
+        }
+        let action = entry
+            .get_deref(b"A", self.doc)
+            .and_then(Object::as_dict)
+            .ok()?;
+        match action.get(b"S").and_then(Object::as_name).ok()? {
+            b"GoTo" => self.destination_page(action.get(b"D").ok()?, 0),
+            _ => None,
+        }
+    }
+
+    /// The page a destination points at. Destinations are explicit arrays
+    /// (`[page /XYZ left top zoom]`) or names looked up in the catalog.
+    fn destination_page(&self, dest: &Object, depth: usize) -> Option<usize> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        let (_, dest) = self.doc.dereference(dest).ok()?;
+        match dest {
+            Object::Array(items) => match items.first()? {
+                Object::Reference(id) => self.pages.get(id).copied(),
+                // Remote destinations use a 0-based page index.
+                Object::Integer(index) => usize::try_from(*index).ok().map(|i| i + 1),
+                _ => None,
+            },
+            Object::Dictionary(dict) => self.destination_page(dict.get(b"D").ok()?, depth + 1),
+            Object::Name(name) | Object::String(name, _) => {
+                let target = self.named_destination(name)?;
+                self.destination_page(target, depth + 1)
+            }
+            _ => None,
+        }
+    }
+
+    /// Look a named destination up in the `/Dests` name tree (PDF 1.2+) or
+    /// the catalog's `/Dests` dictionary (PDF 1.1).
+    fn named_destination(&self, name: &[u8]) -> Option<&'a Object> {
+        let catalog = self.doc.catalog().ok()?;
+        let tree = catalog
+            .get_deref(b"Names", self.doc)
+            .and_then(Object::as_dict)
+            .and_then(|names| names.get_deref(b"Dests", self.doc))
+            .and_then(Object::as_dict);
+        if let Ok(tree) = tree {
+            if let Some(found) = self.name_tree_lookup(tree, name, 0) {
+                return Some(found);
+            }
+        }
+        catalog
+            .get_deref(b"Dests", self.doc)
+            .and_then(Object::as_dict)
+            .and_then(|dests| dests.get(name))
+            .ok()
+    }
+
+    fn name_tree_lookup(
+        &self,
+        node: &'a Dictionary,
+        name: &[u8],
+        depth: usize,
+    ) -> Option<&'a Object> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        if let Ok(names) = node
+            .get_deref(b"Names", self.doc)
+            .and_then(Object::as_array)
+        {
+            for pair in names.chunks(2) {
+                if let [key, value] = pair {
+                    if key.as_str().ok() == Some(name) {
+                        return Some(value);
+                    }
+                }
+            }
+        }
+        let kids = node
+            .get_deref(b"Kids", self.doc)
+            .and_then(Object::as_array)
+            .ok()?;
+        kids.iter().find_map(|kid| {
+            let kid = self.doc.dereference(kid).ok()?.1.as_dict().ok()?;
+            self.name_tree_lookup(kid, name, depth + 1)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::*;
+    use super::*;
+    use lopdf::{dictionary, StringFormat};
+
+    /// Three pages with an outline: "Part I" -> page 1 with child "Setup" ->
+    /// page 2 via a named destination, then "Appendix" -> page 3 via a GoTo
+    /// action.
+    fn pdf_with_outline() -> Vec<u8> {
+        let pages = [
+            text_content(&[(72.0, 720.0, "Part I"), (72.0, 690.0, "Intro text")]),
+            text_content(&[(72.0, 720.0, "Body text")]),
+            text_content(&[(72.0, 720.0, "Tables")]),
+        ];
+        build_pdf(&pages, |doc, page_ids| {
+            let outlines_id = doc.new_object_id();
+            let part_id = doc.new_object_id();
+            let setup_id = doc.new_object_id();
+            let appendix_id = doc.new_object_id();
+            let literal = |s: &str| Object::String(s.as_bytes().to_vec(), StringFormat::Literal);
+
+            doc.objects.insert(
+                setup_id,
+                dictionary! {
+                    "Title" => literal("Setup"),
+                    "Parent" => part_id,
+                    "Dest" => literal("setup"),
+                }
+                .into(),
+            );
+            doc.objects.insert(
+                part_id,
+                dictionary! {
+                    "Title" => literal("Part I"),
+                    "Parent" => outlines_id,
+                    "First" => setup_id,
+                    "Last" => setup_id,
+                    "Next" => appendix_id,
+                    "Dest" => vec![page_ids[0].into(), "Fit".into()],
+                }
+                .into(),
+            );
+            doc.objects.insert(
+                appendix_id,
+                dictionary! {
+                    "Title" => literal("Appendix"),
+                    "Parent" => outlines_id,
+                    "A" => dictionary! {
+                        "S" => "GoTo",
+                        "D" => vec![page_ids[2].into(), "XYZ".into(), 0.into(), 792.into(), Object::Null],
+                    },
+                }
+                .into(),
+            );
+            doc.objects.insert(
+                outlines_id,
+                dictionary! {
+                    "Type" => "Outlines",
+                    "First" => part_id,
+                    "Last" => appendix_id,
+                }
+                .into(),
+            );
+
+            let dests = dictionary! {
+                "Names" => vec![literal("setup"), vec![page_ids[1].into(), "Fit".into()].into()],
+            };
+            let catalog = doc.catalog_mut().unwrap();
+            catalog.set("Outlines", outlines_id);
+            catalog.set("Names", dictionary! { "Dests" => dests });
+        })
+    }
+
+    #[test]
+    fn test_read_outline() {
+        let doc = Document::load_mem(&pdf_with_outline()).unwrap();
+        let outline = read_outline(&doc);
+        assert_eq!(outline.len(), 2);
+        assert_eq!(outline[0].title, "Part I");
+        assert_eq!(outline[0].page, Some(1));
+        assert_eq!(outline[0].children[0].title, "Setup");
+        assert_eq!(outline[0].children[0].page, Some(2));
+        assert_eq!(outline[1].page, Some(3));
+    }
+
+    #[test]
+    fn test_outline_becomes_headings() {
+        let doc = super::super::parse_pdf(&pdf_with_outline()).unwrap();
+        assert_eq!(doc.outline.len(), 2);
+
+        let Block::Section { blocks, .. } = &doc.blocks[0] else {
+            panic!("expected a page section");
+        };
+        // The title already on the page is promoted rather than repeated.
+        assert_eq!(blocks[0], Block::heading(1, "Part I"));
+        assert_eq!(blocks[1], Block::paragraph("Intro text"));
+
+        let Block::Section { blocks, .. } = &doc.blocks[1] else {
+            panic!("expected a page section");
+        };
+        assert_eq!(blocks[0], Block::heading(2, "Setup"));
+        assert!(doc.text.contains("--- Page 3 ---\n# Appendix\n\nTables"));
+    }
+}
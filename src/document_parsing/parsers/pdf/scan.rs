@@ -0,0 +1,432 @@
+//! Scanned pages: pages that are a picture of text rather than text.
+//!
+//! A page is taken for a scan when images cover most of it and it holds
+//! almost no text. The images of such pages can be handed to an OCR engine;
+//! those stored as JPEG or JPEG 2000 are passed through, and uncompressed or
+//! Flate/LZW bitmaps in gray or RGB are written out as PNM. Other encodings
+//! (CCITT fax, JBIG2, indexed colour) and inline images are not read.
+
+use super::text::PageLayout;
+use lopdf::content::Content;
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use std::collections::HashSet;
+
+/// Images must cover at least this fraction of the page.
+const MIN_IMAGE_COVERAGE: f64 = 0.5;
+/// Pages with at least this many characters per square inch have a text
+/// layer; a page of body text has about 30.
+const MIN_TEXT_DENSITY: f64 = 0.25;
+/// Form XObjects nested deeper than this are not searched for images.
+const MAX_FORM_DEPTH: usize = 8;
+
+/// An image drawn on a page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct PlacedImage {
+    pub id: ObjectId,
+    /// Area covered on the page, in square points.
+    pub area: f64,
+}
+
+/// Whether the page laid out as `layout`, showing `images`, is a scan.
+pub(super) fn is_scanned(layout: &PageLayout, images: &[PlacedImage]) -> bool {
+    let page_area = layout.width * layout.height;
+    if page_area <= 0.0 {
+        return false;
+    }
+    let covered: f64 = images.iter().map(|image| image.area).sum();
+    let characters = layout
+        .glyphs
+        .iter()
+        .flat_map(|glyph| glyph.text.chars())
+        .filter(|c| !c.is_whitespace())
+        .count();
+    let square_inches = page_area / (72.0 * 72.0);
+
+    covered.min(page_area) / page_area >= MIN_IMAGE_COVERAGE
+        && (characters as f64) / square_inches < MIN_TEXT_DENSITY
+}
+
+/// The image XObjects drawn by page `page_id`, in drawing order.
+pub(super) fn page_images(doc: &Document, page_id: ObjectId) -> Vec<PlacedImage> {
+    let Ok(content) = doc.get_page_content(page_id) else {
+        return Vec::new();
+    };
+    let mut images = Vec::new();
+    if let Some(resources) = page_resources(doc, page_id) {
+        let mut visited = HashSet::new();
+        collect_images(
+            doc,
+            &content,
+            resources,
+            IDENTITY,
+            0,
+            &mut visited,
+            &mut images,
+        );
+    }
+    images
+}
+
+/// The image `id` as a file an OCR engine can read, or `None` when its
+/// encoding is not supported.
+pub(super) fn encode_image(doc: &Document, id: ObjectId) -> Option<Vec<u8>> {
+    let stream = doc.get_object(id).and_then(Object::as_stream).ok()?;
+    let filters = stream.filters().unwrap_or_default();
+    match filters.as_slice() {
+        [filter] if filter == "DCTDecode" || filter == "JPXDecode" => {
+            return Some(stream.content.clone());
+        }
+        _ => {}
+    }
+
+    let dict = &stream.dict;
+    let number = |key: &[u8]| dict.get(key).and_then(Object::as_i64).ok();
+    let width = usize::try_from(number(b"Width")?).ok()?;
+    let height = usize::try_from(number(b"Height")?).ok()?;
+    let mask = dict
+        .get(b"ImageMask")
+        .and_then(Object::as_bool)
+        .unwrap_or(false);
+    let bits = if mask {
+        1
+    } else {
+        number(b"BitsPerComponent").filter(|bits| matches!(bits, 1 | 2 | 4 | 8 | 16))? as usize
+    };
+    let components = if mask {
+        1
+    } else {
+        color_components(doc, dict.get(b"ColorSpace").ok()?)?
+    };
+    let inverted = dict
+        .get(b"Decode")
+        .and_then(Object::as_array)
+        .ok()
+        .and_then(|decode| decode.first())
+        .and_then(|first| first.as_float().ok())
+        .is_some_and(|first| first >= 1.0);
+
+    // The dimensions come from the file, so a damaged or hostile one must
+    // not overflow them.
+    let row = width
+        .checked_mul(components)?
+        .checked_mul(bits)?
+        .div_ceil(8);
+    let size = row.checked_mul(height)?;
+    let samples = decoded_samples(stream)?;
+    let samples = samples.get(..size)?;
+
+    let mut out;
+    match (bits, components) {
+        (1, 1) => {
+            // In PBM a set bit is black; in PDF it is white, unless the
+            // decode array flips it.
+            out = format!("P4\n{} {}\n", width, height).into_bytes();
+            out.extend(samples.iter().map(|b| if inverted { *b } else { !b }));
+        }
+        (8, 1) => {
+            out = format!("P5\n{} {}\n255\n", width, height).into_bytes();
+            out.extend(samples.iter().map(|b| if inverted { 255 - b } else { *b }));
+        }
+        (8, 3) => {
+            out = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+            out.extend_from_slice(samples);
+        }
+        (8, 4) => {
+            // CMYK, converted naively; good enough to read text from.
+            out = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+            for cmyk in samples.chunks_exact(4) {
+                let k = 255 - cmyk[3] as u16;
+                out.extend(
+                    cmyk[..3]
+                        .iter()
+                        .map(|&c| ((255 - c as u16) * k / 255) as u8),
+                );
+            }
+        }
+        _ => return None,
+    }
+    Some(out)
+}
+
+/// A transformation matrix `[a b c d e f]`.
+type Matrix = [f64; 6];
+
+const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// `first` followed by `then`.
+fn multiply(first: &Matrix, then: &Matrix) -> Matrix {
+    let [a, b, c, d, e, f] = *first;
+    let [a2, b2, c2, d2, e2, f2] = *then;
+    [
+        a * a2 + b * c2,
+        a * b2 + b * d2,
+        c * a2 + d * c2,
+        c * b2 + d * d2,
+        e * a2 + f * c2 + e2,
+        e * b2 + f * d2 + f2,
+    ]
+}
+
+fn matrix(operands: &[Object]) -> Option<Matrix> {
+    let values: Vec<f64> = operands
+        .iter()
+        .map(|o| o.as_float().ok().map(f64::from))
+        .collect::<Option<_>>()?;
+    values.try_into().ok()
+}
+
+/// Find the images drawn by `content`, recursing into form XObjects.
+fn collect_images(
+    doc: &Document,
+    content: &[u8],
+    resources: &Dictionary,
+    base: Matrix,
+    depth: usize,
+    visited: &mut HashSet<ObjectId>,
+    images: &mut Vec<PlacedImage>,
+) {
+    let Ok(content) = Content::decode(content) else {
+        return;
+    };
+    let xobjects = resources
+        .get_deref(b"XObject", doc)
+        .and_then(Object::as_dict)
+        .ok();
+
+    let mut stack = Vec::new();
+    let mut ctm = base;
+    for operation in &content.operations {
+        match operation.operator.as_str() {
+            "q" => stack.push(ctm),
+            "Q" => ctm = stack.pop().unwrap_or(base),
+            "cm" => {
+                if let Some(m) = matrix(&operation.operands) {
+                    ctm = multiply(&m, &ctm);
+                }
+            }
+            "Do" => {
+                let Some(id) = operation
+                    .operands
+                    .first()
+                    .and_then(|name| name.as_name().ok())
+                    .and_then(|name| xobjects?.get(name).ok())
+                    .and_then(|o| o.as_reference().ok())
+                else {
+                    continue;
+                };
+                let Ok(stream) = doc.get_object(id).and_then(Object::as_stream) else {
+                    continue;
+                };
+                match stream.dict.get(b"Subtype").and_then(Object::as_name) {
+                    Ok(b"Image") => images.push(PlacedImage {
+                        id,
+                        // The image fills the unit square.
+                        area: (ctm[0] * ctm[3] - ctm[1] * ctm[2]).abs(),
+                    }),
+                    Ok(b"Form") if depth < MAX_FORM_DEPTH && visited.insert(id) => {
+                        let form_matrix = stream
+                            .dict
+                            .get(b"Matrix")
+                            .and_then(Object::as_array)
+                            .ok()
+                            .and_then(|m| matrix(m))
+                            .unwrap_or(IDENTITY);
+                        let form_resources = stream
+                            .dict
+                            .get_deref(b"Resources", doc)
+                            .and_then(Object::as_dict)
+                            .unwrap_or(resources);
+                        let data = decoded(stream).unwrap_or_else(|| stream.content.clone());
+                        collect_images(
+                            doc,
+                            &data,
+                            form_resources,
+                            multiply(&form_matrix, &ctm),
+                            depth + 1,
+                            visited,
+                            images,
+                        );
+                        visited.remove(&id);
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The resources of a page, which it may inherit from its ancestors.
+fn page_resources(doc: &Document, page_id: ObjectId) -> Option<&Dictionary> {
+    let mut node = doc.get_dictionary(page_id).ok()?;
+    for _ in 0..MAX_FORM_DEPTH * 4 {
+        if let Ok(resources) = node.get_deref(b"Resources", doc).and_then(Object::as_dict) {
+            return Some(resources);
+        }
+        node = node
+            .get_deref(b"Parent", doc)
+            .and_then(Object::as_dict)
+            .ok()?;
+    }
+    None
+}
+
+/// Number of colour components of an image colour space.
+fn color_components(doc: &Document, space: &Object) -> Option<usize> {
+    let (_, space) = doc.dereference(space).ok()?;
+    match space {
+        Object::Name(name) => match name.as_slice() {
+            b"DeviceGray" | b"CalGray" | b"G" => Some(1),
+            b"DeviceRGB" | b"CalRGB" | b"RGB" => Some(3),
+            b"DeviceCMYK" | b"CMYK" => Some(4),
+            _ => None,
+        },
+        Object::Array(items) => match items.first()?.as_name().ok()? {
+            b"CalGray" => Some(1),
+            b"CalRGB" | b"Lab" => Some(3),
+            b"ICCBased" => {
+                let profile = doc.dereference(items.get(1)?).ok()?.1.as_stream().ok()?;
+                usize::try_from(profile.dict.get(b"N").and_then(Object::as_i64).ok()?).ok()
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The samples of an image stream.
+fn decoded_samples(stream: &Stream) -> Option<Vec<u8>> {
+    if stream.filters().unwrap_or_default().is_empty() {
+        return Some(stream.content.clone());
+    }
+    // lopdf declines to decode image streams, though the filters are the
+    // same as for any other stream.
+    let mut plain = stream.clone();
+    plain.dict.remove(b"Subtype");
+    decoded(&plain)
+}
+
+fn decoded(stream: &Stream) -> Option<Vec<u8>> {
+    if stream.filters().unwrap_or_default().is_empty() {
+        return Some(stream.content.clone());
+    }
+    stream.decompressed_content().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::*;
+    use super::super::{parse_pdf, parse_pdf_with_options};
+    use super::*;
+    use crate::document_parsing::{OcrEngine, ParseOptions, StubOcrEngine};
+    use lopdf::dictionary;
+    use std::sync::Arc;
+
+    /// Pages drawing a 4x2 gray bitmap over `coverage` of their height, with
+    /// `text` on top.
+    fn scanned_pdf(coverage: f64, text: &str) -> Vec<u8> {
+        let height = 792.0 * coverage;
+        let page = format!(
+            "q 612 0 0 {} 0 0 cm /Im1 Do Q\n{}",
+            height,
+            text_content(&[(72.0, 40.0, text)])
+        );
+        build_pdf(&[page], |doc, page_ids| {
+            let image = doc.add_object(Stream::new(
+                dictionary! {
+                    "Type" => "XObject",
+                    "Subtype" => "Image",
+                    "Width" => 4,
+                    "Height" => 2,
+                    "ColorSpace" => "DeviceGray",
+                    "BitsPerComponent" => 8,
+                },
+                vec![0, 64, 128, 255, 255, 128, 64, 0],
+            ));
+            let resources = doc
+                .get_dictionary(page_ids[0])
+                .unwrap()
+                .get(b"Resources")
+                .unwrap()
+                .as_reference()
+                .unwrap();
+            doc.get_dictionary_mut(resources)
+                .unwrap()
+                .set("XObject", dictionary! { "Im1" => image });
+        })
+    }
+
+    #[test]
+    fn test_encode_image_rejects_bad_dimensions() {
+        let mut doc = Document::with_version("1.5");
+        let mut image = |width: i64, bits: i64| {
+            doc.add_object(Stream::new(
+                dictionary! {
+                    "Type" => "XObject",
+                    "Subtype" => "Image",
+                    "Width" => width,
+                    "Height" => 2,
+                    "ColorSpace" => "DeviceGray",
+                    "BitsPerComponent" => bits,
+                },
+                vec![0; 8],
+            ))
+        };
+        let fine = image(4, 8);
+        let negative = image(4, -8);
+        let odd = image(4, 3);
+        let huge = image(i64::MAX, 16);
+        assert!(encode_image(&doc, fine).is_some());
+        for id in [negative, odd, huge] {
+            assert_eq!(encode_image(&doc, id), None);
+        }
+    }
+
+    #[test]
+    fn test_image_only_page_is_flagged() {
+        let doc = parse_pdf(&scanned_pdf(1.0, "")).unwrap();
+        assert_eq!(doc.metadata.extra["scanned_pages"], serde_json::json!([1]));
+        assert_eq!(doc.text, "--- Page 1 ---");
+
+        // Text over the image means it has a text layer.
+        let doc = parse_pdf(&scanned_pdf(
+            1.0,
+            "A caption long enough to count as real text on this page, and then some more.",
+        ))
+        .unwrap();
+        assert!(!doc.metadata.extra.contains_key("scanned_pages"));
+
+        // A small picture does not make a scan.
+        let doc = parse_pdf(&scanned_pdf(0.2, "")).unwrap();
+        assert!(!doc.metadata.extra.contains_key("scanned_pages"));
+    }
+
+    #[test]
+    fn test_scanned_pages_are_read_with_ocr() {
+        let engine = Arc::new(StubOcrEngine::new("Recognised line one\nline two\n"));
+        let options = ParseOptions {
+            ocr: Some(engine.clone() as Arc<dyn OcrEngine>),
+            ..ParseOptions::default()
+        };
+        let doc = parse_pdf_with_options(&scanned_pdf(1.0, ""), &options).unwrap();
+        assert_eq!(engine.calls(), 1);
+        assert_eq!(doc.text, "--- Page 1 ---\nRecognised line one\nline two");
+        assert_eq!(doc.metadata.extra["ocr_engine"], "stub");
+    }
+
+    #[test]
+    fn test_encode_gray_image_as_pgm() {
+        let pdf = scanned_pdf(1.0, "");
+        let doc = Document::load_mem(&pdf).unwrap();
+        let page = doc.get_pages()[&1];
+        let images = page_images(&doc, page);
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].area, 612.0 * 792.0);
+
+        let pgm = encode_image(&doc, images[0].id).unwrap();
+        assert_eq!(
+            pgm,
+            b"P5\n4 2\n255\n\x00\x40\x80\xff\xff\x80\x40\x00".to_vec()
+        );
+    }
+}
@@ -0,0 +1,450 @@
+//! Table detection from the page layout.
+//!
+//! Two kinds of tables are recognised. Ruled tables are grids of horizontal
+//! and vertical rules, and their cells are the spaces between the rules.
+//! Unruled tables are runs of lines whose words line up in columns separated
+//! by wide gaps, as in most financial statements.
+
+use super::text::{self, Glyph, Line, PageLayout, Rule, Word};
+use crate::document_parsing::Table;
+
+/// How far apart, in points, rules may be and still meet.
+const TOLERANCE: f64 = 2.0;
+/// Words further apart than this many font sizes are in different cells of an
+/// unruled table.
+const COLUMN_GAP: f64 = 1.0;
+/// Lines further apart than this many font sizes end an unruled table.
+const MAX_ROW_GAP: f64 = 2.5;
+/// Unruled tables need this many rows, to avoid reading every line with a
+/// tab stop as a table.
+const MIN_UNRULED_ROWS: usize = 3;
+/// Cells of an unruled table average at most this many words. Columns of
+/// prose line up too, but have long lines.
+const MAX_WORDS_PER_CELL: f64 = 4.0;
+
+/// A table found on a page and the area it covers.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct DetectedTable {
+    pub top: f64,
+    pub bottom: f64,
+    pub left: f64,
+    pub right: f64,
+    pub table: Table,
+}
+
+impl DetectedTable {
+    /// Whether `glyph` is drawn inside the table.
+    pub fn contains(&self, glyph: &Glyph) -> bool {
+        let (x, y) = (glyph.x + glyph.width / 2.0, center_y(glyph.y, glyph.size));
+        x >= self.left - TOLERANCE
+            && x <= self.right + TOLERANCE
+            && y >= self.top - TOLERANCE
+            && y <= self.bottom + TOLERANCE
+    }
+
+    fn covers(&self, word: &Word) -> bool {
+        let (x, y) = ((word.x0 + word.x1) / 2.0, center_y(word.y, word.size));
+        x >= self.left && x <= self.right && y >= self.top && y <= self.bottom
+    }
+}
+
+/// Find the tables on a page, from top to bottom.
+pub(super) fn detect_tables(layout: &PageLayout) -> Vec<DetectedTable> {
+    let words = text::words(&layout.glyphs);
+    let mut tables = ruled_tables(&layout.rules, &words);
+
+    let free: Vec<Word> = words
+        .into_iter()
+        .filter(|word| !tables.iter().any(|table| table.covers(word)))
+        .collect();
+    let mut unruled = unruled_tables(&text::lines(free));
+    tables.append(&mut unruled);
+
+    tables.sort_by(|a, b| a.top.total_cmp(&b.top));
+    tables
+}
+
+/// Vertical middle of text with baseline `y`.
+fn center_y(y: f64, size: f64) -> f64 {
+    y - size * 0.3
+}
+
+fn ruled_tables(rules: &[Rule], words: &[Word]) -> Vec<DetectedTable> {
+    let mut groups = UnionFind::new(rules.len());
+    for i in 0..rules.len() {
+        for j in i + 1..rules.len() {
+            if touch(&rules[i], &rules[j]) {
+                groups.union(i, j);
+            }
+        }
+    }
+
+    let mut components: Vec<Vec<&Rule>> = Vec::new();
+    let mut index = vec![usize::MAX; rules.len()];
+    for (i, rule) in rules.iter().enumerate() {
+        let root = groups.find(i);
+        if index[root] == usize::MAX {
+            index[root] = components.len();
+            components.push(Vec::new());
+        }
+        components[index[root]].push(rule);
+    }
+
+    components
+        .iter()
+        .filter_map(|component| grid_table(component, words))
+        .collect()
+}
+
+/// Whether two rules cross or meet, allowing for small gaps.
+fn touch(a: &Rule, b: &Rule) -> bool {
+    a.x0 <= b.x1 + TOLERANCE
+        && b.x0 <= a.x1 + TOLERANCE
+        && a.y0 <= b.y1 + TOLERANCE
+        && b.y0 <= a.y1 + TOLERANCE
+}
+
+/// Read the cells of a grid of connected rules.
+fn grid_table(rules: &[&Rule], words: &[Word]) -> Option<DetectedTable> {
+    let xs = positions(rules.iter().filter(|r| !r.is_horizontal()).map(|r| r.x0));
+    let ys = positions(rules.iter().filter(|r| r.is_horizontal()).map(|r| r.y0));
+    if xs.len() < 3 || ys.len() < 2 {
+        return None;
+    }
+    let (left, right) = (xs[0], xs[xs.len() - 1]);
+    let (top, bottom) = (ys[0], ys[ys.len() - 1]);
+
+    let inside: Vec<Word> = words
+        .iter()
+        .filter(|word| {
+            let (x, y) = ((word.x0 + word.x1) / 2.0, center_y(word.y, word.size));
+            x > left && x < right && y > top && y < bottom
+        })
+        .cloned()
+        .collect();
+
+    // With only the outer frame drawn, each line of text is a row.
+    let frame_only = ys.len() == 2;
+    let row_count = if frame_only { 0 } else { ys.len() - 1 };
+    let mut rows = vec![vec![String::new(); xs.len() - 1]; row_count];
+
+    for line in text::lines(inside) {
+        let row = if frame_only {
+            rows.push(vec![String::new(); xs.len() - 1]);
+            rows.len() - 1
+        } else {
+            interval(&ys, center_y(line.y, line.size))?
+        };
+        for word in &line.words {
+            let column = interval(&xs, (word.x0 + word.x1) / 2.0)?;
+            append(&mut rows[row][column], &word.text);
+        }
+    }
+
+    let rows = drop_empty(rows);
+    if rows.len() < 2 || rows[0].len() < 2 {
+        return None;
+    }
+    Some(DetectedTable {
+        top,
+        bottom,
+        left,
+        right,
+        table: table(rows),
+    })
+}
+
+/// Distinct positions, merging those within [`TOLERANCE`] of each other.
+fn positions(values: impl Iterator<Item = f64>) -> Vec<f64> {
+    let mut values: Vec<f64> = values.collect();
+    values.sort_by(f64::total_cmp);
+    let mut merged: Vec<f64> = Vec::new();
+    for value in values {
+        match merged.last() {
+            Some(&last) if value - last <= TOLERANCE => {}
+            _ => merged.push(value),
+        }
+    }
+    merged
+}
+
+/// Index of the interval between consecutive `bounds` holding `value`.
+fn interval(bounds: &[f64], value: f64) -> Option<usize> {
+    bounds
+        .windows(2)
+        .position(|w| value >= w[0] && value < w[1])
+}
+
+fn unruled_tables(lines: &[Line]) -> Vec<DetectedTable> {
+    let cells: Vec<Vec<Word>> = lines
+        .iter()
+        .map(|line| line.fragments(COLUMN_GAP))
+        .collect();
+    let mut tables = Vec::new();
+    let mut start = 0;
+
+    while start < lines.len() {
+        if cells[start].len() < 2 {
+            start += 1;
+            continue;
+        }
+        // Extend over lines with several cells, allowing one single-cell line
+        // (such as a group label) between them.
+        let mut last = start;
+        let mut next = start + 1;
+        while next < lines.len()
+            && lines[next].y - lines[next - 1].y <= lines[next].size * MAX_ROW_GAP
+            && next - last <= 2
+        {
+            if cells[next].len() >= 2 {
+                last = next;
+            }
+            next += 1;
+        }
+
+        match unruled_table(&lines[start..=last], &cells[start..=last]) {
+            Some(table) => {
+                tables.push(table);
+                start = last + 1;
+            }
+            None => start += 1,
+        }
+    }
+
+    tables
+}
+
+fn unruled_table(lines: &[Line], cells: &[Vec<Word>]) -> Option<DetectedTable> {
+    if lines.len() < MIN_UNRULED_ROWS {
+        return None;
+    }
+
+    // Columns are the horizontal extents of the cells, merged where they
+    // overlap from one line to the next.
+    let mut spans: Vec<(f64, f64)> = cells.iter().flatten().map(|c| (c.x0, c.x1)).collect();
+    spans.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut columns: Vec<(f64, f64)> = Vec::new();
+    for (x0, x1) in spans {
+        match columns.last_mut() {
+            Some(column) if x0 <= column.1 => column.1 = column.1.max(x1),
+            _ => columns.push((x0, x1)),
+        }
+    }
+    if columns.len() < 2 {
+        return None;
+    }
+
+    let cell_count = cells.iter().map(Vec::len).sum::<usize>();
+    let word_count: usize = lines.iter().map(|line| line.words.len()).sum();
+    if word_count as f64 / cell_count as f64 > MAX_WORDS_PER_CELL {
+        return None;
+    }
+
+    let mut rows = Vec::new();
+    for line_cells in cells {
+        let mut row = vec![String::new(); columns.len()];
+        for cell in line_cells {
+            let column = columns
+                .iter()
+                .position(|&(x0, x1)| cell.x0 >= x0 && cell.x0 <= x1)?;
+            append(&mut row[column], &cell.text);
+        }
+        rows.push(row);
+    }
+
+    let first = &lines[0];
+    let last = &lines[lines.len() - 1];
+    Some(DetectedTable {
+        top: first.y - first.size,
+        bottom: last.y,
+        left: columns[0].0,
+        right: columns[columns.len() - 1].1,
+        table: table(rows),
+    })
+}
+
+fn append(cell: &mut String, text: &str) {
+    if !cell.is_empty() {
+        cell.push(' ');
+    }
+    cell.push_str(text);
+}
+
+/// Remove rows and columns without any text.
+fn drop_empty(rows: Vec<Vec<String>>) -> Vec<Vec<String>> {
+    let width = rows.first().map_or(0, Vec::len);
+    let used: Vec<bool> = (0..width)
+        .map(|column| rows.iter().any(|row| !row[column].is_empty()))
+        .collect();
+
+    rows.into_iter()
+        .filter(|row| row.iter().any(|cell| !cell.is_empty()))
+        .map(|row| {
+            row.into_iter()
+                .zip(&used)
+                .filter_map(|(cell, &used)| used.then_some(cell))
+                .collect()
+        })
+        .collect()
+}
+
+/// The first row is taken as a header when it has no numbers other than years
+/// and the rows below do.
+fn table(rows: Vec<Vec<String>>) -> Table {
+    let is_year =
+        |cell: &str| cell.len() == 4 && (cell.starts_with("19") || cell.starts_with("20"));
+    let header = rows.len() > 1
+        && !rows[0]
+            .iter()
+            .any(|cell| is_numeric(cell) && !is_year(cell.trim()))
+        && rows[1..].iter().flatten().any(|cell| is_numeric(cell));
+    Table { rows, header }
+}
+
+fn is_numeric(cell: &str) -> bool {
+    let digits: String = cell
+        .chars()
+        .filter(|c| !matches!(c, '$' | '€' | '£' | '%' | '(' | ')' | '-' | '+' | ' '))
+        .collect();
+    !digits.is_empty()
+        && digits.chars().any(|c| c.is_ascii_digit())
+        && digits
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '.' || c == ',')
+}
+
+struct UnionFind {
+    parent: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(len: usize) -> Self {
+        Self {
+            parent: (0..len).collect(),
+        }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        self.parent[a] = b;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parse_pdf;
+    use super::super::test_support::*;
+    use crate::document_parsing::Block;
+
+    fn page_blocks(content: String) -> Vec<Block> {
+        let doc = parse_pdf(&create_pdf(&[content])).unwrap();
+        match doc.blocks.into_iter().next() {
+            Some(Block::Section { blocks, .. }) => blocks,
+            other => panic!("unexpected block {:?}", other),
+        }
+    }
+
+    fn rows(cells: &[&[&str]]) -> Vec<Vec<String>> {
+        cells
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_ruled_table() {
+        // A 3x2 grid from x=72 to 372 and y=700 down to 640, in PDF space.
+        let mut content = String::from("0.5 w\n");
+        for x in [72, 222, 372] {
+            content.push_str(&format!("{} 640 m {} 700 l S\n", x, x));
+        }
+        for y in [640, 670, 700] {
+            content.push_str(&format!("72 {} m 372 {} l S\n", y, y));
+        }
+        content.push_str(&text_content(&[
+            (72.0, 740.0, "Specifications"),
+            (80.0, 680.0, "Weight"),
+            (230.0, 680.0, "12 kg"),
+            (80.0, 650.0, "Width"),
+            (230.0, 650.0, "40 cm"),
+        ]));
+
+        let blocks = page_blocks(content);
+        assert_eq!(blocks[0], Block::paragraph("Specifications"));
+        match &blocks[1] {
+            Block::Table(table) => {
+                assert_eq!(
+                    table.rows,
+                    rows(&[&["Weight", "12 kg"], &["Width", "40 cm"]])
+                );
+            }
+            other => panic!("expected a table, got {:?}", other),
+        }
+        assert_eq!(blocks.len(), 2);
+    }
+
+    #[test]
+    fn test_unruled_table() {
+        let content = text_content(&[
+            (72.0, 740.0, "Results for the year were strong."),
+            (72.0, 700.0, "Item"),
+            (300.0, 700.0, "2023"),
+            (400.0, 700.0, "2024"),
+            (72.0, 686.0, "Net revenue"),
+            (300.0, 686.0, "1,200"),
+            (400.0, 686.0, "1,450"),
+            (72.0, 672.0, "Operating costs"),
+            (300.0, 672.0, "(800)"),
+            (400.0, 672.0, "(910)"),
+            (72.0, 630.0, "Costs rose in line with revenue."),
+        ]);
+
+        let blocks = page_blocks(content);
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(
+            blocks[0],
+            Block::paragraph("Results for the year were strong.")
+        );
+        match &blocks[1] {
+            Block::Table(table) => {
+                assert!(table.header);
+                assert_eq!(
+                    table.rows,
+                    rows(&[
+                        &["Item", "2023", "2024"],
+                        &["Net revenue", "1,200", "1,450"],
+                        &["Operating costs", "(800)", "(910)"],
+                    ])
+                );
+            }
+            other => panic!("expected a table, got {:?}", other),
+        }
+        assert_eq!(
+            blocks[2],
+            Block::paragraph("Costs rose in line with revenue.")
+        );
+    }
+
+    #[test]
+    fn test_prose_columns_are_not_tables() {
+        let content = text_content(&[
+            (72.0, 700.0, "The first column of text runs on"),
+            (320.0, 700.0, "while the second column of text"),
+            (72.0, 686.0, "for several lines of ordinary prose"),
+            (320.0, 686.0, "sits beside it with a wide gutter"),
+            (72.0, 672.0, "and should stay a paragraph here"),
+            (320.0, 672.0, "rather than turn into a table too"),
+        ]);
+
+        let blocks = page_blocks(content);
+        assert!(blocks.iter().all(|b| !matches!(b, Block::Table(_))));
+    }
+}
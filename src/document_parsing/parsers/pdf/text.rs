@@ -0,0 +1,463 @@
+//! Glyph-level text extraction.
+//!
+//! `pdf_extract` interprets the content streams and reports every character it
+//! draws. We keep the characters with their positions so later passes can work
+//! on the page layout rather than on a flattened string.
+
+use crate::document_parsing::ParseError;
+use lopdf::Document;
+use pdf_extract::{ColorSpace, MediaBox, OutputDev, OutputError, Path, PathOp, Transform};
+use std::panic::{self, AssertUnwindSafe};
+
+/// Lines thinner than this, and rectangles narrower than this, count as rules.
+const MAX_RULE_THICKNESS: f64 = 3.0;
+/// Rules shorter than this are ignored, as are lines further than this from
+/// horizontal or vertical.
+const MIN_RULE_LENGTH: f64 = 2.0;
+
+/// One drawn character, in page space with the origin at the top left.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Glyph {
+    pub x: f64,
+    /// Baseline, measured down from the top of the page.
+    pub y: f64,
+    /// Horizontal advance.
+    pub width: f64,
+    /// Font size after the text matrix is applied.
+    pub size: f64,
+    pub text: String,
+    /// Whether this is the first glyph of a text-showing operation, where the
+    /// gap to the previous glyph may be a space.
+    pub word_start: bool,
+}
+
+impl Glyph {
+    pub fn end(&self) -> f64 {
+        self.x + self.width
+    }
+}
+
+/// A horizontal or vertical line drawn on the page, such as a table border.
+/// `x0 <= x1` and `y0 <= y1`, in the same space as [`Glyph`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct Rule {
+    pub x0: f64,
+    pub y0: f64,
+    pub x1: f64,
+    pub y1: f64,
+}
+
+impl Rule {
+    pub fn is_horizontal(&self) -> bool {
+        self.y1 - self.y0 < self.x1 - self.x0
+    }
+}
+
+/// A run of glyphs on one baseline with no space between them.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Word {
+    pub x0: f64,
+    pub x1: f64,
+    pub y: f64,
+    pub size: f64,
+    pub text: String,
+}
+
+/// One line of text: words sharing a baseline, left to right.
+#[derive(Debug, Clone)]
+pub(super) struct Line {
+    pub y: f64,
+    pub size: f64,
+    pub words: Vec<Word>,
+}
+
+impl Line {
+    /// Merge the words into runs, splitting where the gap between words is
+    /// wider than `max_gap` font sizes, such as between table cells or
+    /// columns of text.
+    pub fn fragments(&self, max_gap: f64) -> Vec<Word> {
+        let mut fragments: Vec<Word> = Vec::new();
+        for word in &self.words {
+            match fragments.last_mut() {
+                Some(run) if word.x0 - run.x1 <= word.size * max_gap => {
+                    run.x1 = run.x1.max(word.x1);
+                    run.text.push(' ');
+                    run.text.push_str(&word.text);
+                }
+                _ => fragments.push(word.clone()),
+            }
+        }
+        fragments
+    }
+}
+
+/// The glyphs of one page, in content-stream order, and its rules.
+#[derive(Debug, Clone, Default)]
+pub(super) struct PageLayout {
+    pub width: f64,
+    pub height: f64,
+    /// Top left corner of the media box in user space, where page space
+    /// starts.
+    pub left: f64,
+    pub top: f64,
+    pub glyphs: Vec<Glyph>,
+    pub rules: Vec<Rule>,
+}
+
+impl PageLayout {
+    /// Map a point from default user space to page space.
+    pub fn to_page(&self, x: f64, y: f64) -> (f64, f64) {
+        (x - self.left, self.top - y)
+    }
+}
+
+/// Collect the glyphs of page `number` (1-based). `pdf_extract` panics on
+/// some malformed content, which is reported as an error like its own.
+pub(super) fn extract_page(doc: &Document, number: u32) -> Result<PageLayout, ParseError> {
+    let mut collector = Collector::default();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        pdf_extract::output_doc_page(doc, &mut collector, number).map_err(|e| e.to_string())
+    }))
+    .unwrap_or_else(|payload| {
+        Err(payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unreadable content".into()))
+    });
+    result.map_err(|e| ParseError::Format(format!("PDF page {}: {}", number, e)))?;
+    Ok(collector.page)
+}
+
+/// Lay glyphs out as text the way `pdf_extract`'s plain-text output does: a
+/// newline when the baseline moves down, a blank line for a larger jump, and
+/// a space for a horizontal gap between words.
+pub(super) fn page_text(glyphs: &[Glyph]) -> String {
+    let mut out = String::new();
+    let mut last_end = f64::MAX;
+    let mut last_y = 0.0;
+
+    for glyph in glyphs {
+        if glyph.word_start && !out.is_empty() {
+            let dy = (glyph.y - last_y).abs();
+            if dy > glyph.size * 1.5 {
+                out.push('\n');
+            }
+            if glyph.x < last_end && dy > glyph.size * 0.5 {
+                out.push('\n');
+            }
+            if glyph.x > last_end + glyph.size * 0.1 {
+                out.push(' ');
+            }
+        }
+        out.push_str(&glyph.text);
+        last_y = glyph.y;
+        last_end = glyph.end();
+    }
+
+    out
+}
+
+/// Group glyphs into words, splitting at whitespace, at a change of baseline
+/// and at horizontal gaps.
+pub(super) fn words(glyphs: &[Glyph]) -> Vec<Word> {
+    let mut words: Vec<Word> = Vec::new();
+    let mut open = false;
+
+    for glyph in glyphs {
+        if glyph.text.trim().is_empty() {
+            open = false;
+            continue;
+        }
+        if let Some(word) = words.last_mut().filter(|_| open) {
+            let same_line = (glyph.y - word.y).abs() <= word.size * 0.2;
+            let gap = glyph.x - word.x1;
+            if same_line && gap <= word.size * 0.15 && gap >= -word.size * 0.5 {
+                word.x1 = word.x1.max(glyph.end());
+                word.text.push_str(&glyph.text);
+                continue;
+            }
+        }
+        words.push(Word {
+            x0: glyph.x,
+            x1: glyph.end(),
+            y: glyph.y,
+            size: glyph.size,
+            text: glyph.text.clone(),
+        });
+        open = true;
+    }
+
+    words
+}
+
+/// Group words into lines, from top to bottom.
+pub(super) fn lines(mut words: Vec<Word>) -> Vec<Line> {
+    words.sort_by(|a, b| a.y.total_cmp(&b.y));
+    let mut lines: Vec<Line> = Vec::new();
+
+    for word in words {
+        match lines.last_mut() {
+            Some(line) if (word.y - line.y).abs() <= line.size * 0.4 => {
+                line.size = line.size.max(word.size);
+                line.words.push(word);
+            }
+            _ => lines.push(Line {
+                y: word.y,
+                size: word.size,
+                words: vec![word],
+            }),
+        }
+    }
+    for line in &mut lines {
+        line.words.sort_by(|a, b| a.x0.total_cmp(&b.x0));
+    }
+
+    lines
+}
+
+#[derive(Default)]
+struct Collector {
+    page: PageLayout,
+    word_start: bool,
+}
+
+impl Collector {
+    /// Map a point from user space to page space.
+    fn to_page(&self, ctm: &Transform, x: f64, y: f64) -> (f64, f64) {
+        let px = ctm.m11 * x + ctm.m21 * y + ctm.m31;
+        let py = ctm.m12 * x + ctm.m22 * y + ctm.m32;
+        self.page.to_page(px, py)
+    }
+
+    /// Record the segment from `a` to `b` if it is horizontal or vertical.
+    fn add_rule(&mut self, a: (f64, f64), b: (f64, f64)) {
+        let (dx, dy) = ((a.0 - b.0).abs(), (a.1 - b.1).abs());
+        if dx.max(dy) < MIN_RULE_LENGTH || dx.min(dy) > MIN_RULE_LENGTH {
+            return;
+        }
+        let rule = if dx >= dy {
+            let y = (a.1 + b.1) / 2.0;
+            Rule {
+                x0: a.0.min(b.0),
+                y0: y,
+                x1: a.0.max(b.0),
+                y1: y,
+            }
+        } else {
+            let x = (a.0 + b.0) / 2.0;
+            Rule {
+                x0: x,
+                y0: a.1.min(b.1),
+                x1: x,
+                y1: a.1.max(b.1),
+            }
+        };
+        self.page.rules.push(rule);
+    }
+}
+
+impl OutputDev for Collector {
+    fn begin_page(
+        &mut self,
+        _page_num: u32,
+        media_box: &MediaBox,
+        _art_box: Option<(f64, f64, f64, f64)>,
+    ) -> Result<(), OutputError> {
+        self.page.width = media_box.urx - media_box.llx;
+        self.page.height = media_box.ury - media_box.lly;
+        self.page.left = media_box.llx;
+        self.page.top = media_box.ury;
+        Ok(())
+    }
+
+    fn end_page(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn output_character(
+        &mut self,
+        trm: &Transform,
+        width: f64,
+        _spacing: f64,
+        font_size: f64,
+        char: &str,
+    ) -> Result<(), OutputError> {
+        // Side of the square with the same area as the transformed font box.
+        let sx = font_size * trm.m11 + font_size * trm.m21;
+        let sy = font_size * trm.m12 + font_size * trm.m22;
+        let size = (sx * sy).abs().sqrt();
+
+        let (x, y) = self.page.to_page(trm.m31, trm.m32);
+        self.page.glyphs.push(Glyph {
+            x,
+            y,
+            width: width * size,
+            size,
+            text: char.to_string(),
+            word_start: self.word_start,
+        });
+        self.word_start = false;
+        Ok(())
+    }
+
+    fn begin_word(&mut self) -> Result<(), OutputError> {
+        self.word_start = true;
+        Ok(())
+    }
+
+    fn end_word(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn end_line(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn stroke(
+        &mut self,
+        ctm: &Transform,
+        _colorspace: &ColorSpace,
+        _color: &[f64],
+        path: &Path,
+    ) -> Result<(), OutputError> {
+        let mut start = None;
+        let mut current = None;
+        for op in &path.ops {
+            match *op {
+                PathOp::MoveTo(x, y) => {
+                    let point = self.to_page(ctm, x, y);
+                    start = Some(point);
+                    current = Some(point);
+                }
+                PathOp::LineTo(x, y) => {
+                    let point = self.to_page(ctm, x, y);
+                    if let Some(from) = current {
+                        self.add_rule(from, point);
+                    }
+                    current = Some(point);
+                }
+                PathOp::CurveTo(_, _, _, _, x, y) => current = Some(self.to_page(ctm, x, y)),
+                PathOp::Rect(x, y, w, h) => {
+                    let corners = [
+                        self.to_page(ctm, x, y),
+                        self.to_page(ctm, x + w, y),
+                        self.to_page(ctm, x + w, y + h),
+                        self.to_page(ctm, x, y + h),
+                    ];
+                    for i in 0..4 {
+                        self.add_rule(corners[i], corners[(i + 1) % 4]);
+                    }
+                    start = Some(corners[0]);
+                    current = start;
+                }
+                PathOp::Close => {
+                    if let (Some(from), Some(to)) = (current, start) {
+                        self.add_rule(from, to);
+                    }
+                    current = start;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Many producers draw table borders as thin filled rectangles.
+    fn fill(
+        &mut self,
+        ctm: &Transform,
+        _colorspace: &ColorSpace,
+        _color: &[f64],
+        path: &Path,
+    ) -> Result<(), OutputError> {
+        let mut points = Vec::new();
+        for op in &path.ops {
+            match *op {
+                PathOp::MoveTo(x, y) | PathOp::LineTo(x, y) | PathOp::CurveTo(_, _, _, _, x, y) => {
+                    points.push(self.to_page(ctm, x, y))
+                }
+                PathOp::Rect(x, y, w, h) => {
+                    points.push(self.to_page(ctm, x, y));
+                    points.push(self.to_page(ctm, x + w, y + h));
+                }
+                PathOp::Close => {}
+            }
+        }
+        let Some(&(x, y)) = points.first() else {
+            return Ok(());
+        };
+        let (mut x0, mut y0, mut x1, mut y1) = (x, y, x, y);
+        for &(x, y) in &points {
+            x0 = x0.min(x);
+            y0 = y0.min(y);
+            x1 = x1.max(x);
+            y1 = y1.max(y);
+        }
+
+        if y1 - y0 <= MAX_RULE_THICKNESS {
+            let y = (y0 + y1) / 2.0;
+            self.add_rule((x0, y), (x1, y));
+        } else if x1 - x0 <= MAX_RULE_THICKNESS {
+            let x = (x0 + x1) / 2.0;
+            self.add_rule((x, y0), (x, y1));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyph(x: f64, y: f64, text: &str, word_start: bool) -> Glyph {
+        Glyph {
+            x,
+            y,
+            width: 6.0 * text.len() as f64,
+            size: 12.0,
+            text: text.into(),
+            word_start,
+        }
+    }
+
+    #[test]
+    fn test_page_text_spacing() {
+        let glyphs = [
+            glyph(72.0, 100.0, "Hello", true),
+            glyph(110.0, 100.0, "world", true),
+            glyph(72.0, 114.0, "next", true),
+            glyph(72.0, 150.0, "para", true),
+        ];
+        assert_eq!(page_text(&glyphs), "Hello world\nnext\n\npara");
+    }
+
+    #[test]
+    fn test_words() {
+        let mut glyphs = vec![glyph(72.0, 100.0, "a", true)];
+        glyphs.push(glyph(78.0, 100.0, "b", false));
+        glyphs.push(glyph(84.0, 100.0, " ", false));
+        glyphs.push(glyph(90.0, 100.0, "c", false));
+        glyphs.push(glyph(200.0, 100.0, "d", true));
+
+        let words: Vec<_> = words(&glyphs).into_iter().map(|w| w.text).collect();
+        assert_eq!(words, ["ab", "c", "d"]);
+    }
+
+    #[test]
+    fn test_media_box_origin() {
+        use super::super::test_support::{build_pdf, text_content};
+        let pdf = build_pdf(&[text_content(&[(72.0, 820.0, "Top")])], |doc, pages| {
+            let page = doc.get_dictionary_mut(pages[0]).unwrap();
+            page.set(
+                "MediaBox",
+                vec![0.into(), 100.into(), 612.into(), 892.into()],
+            );
+        });
+        let doc = Document::load_mem(&pdf).unwrap();
+
+        let layout = extract_page(&doc, 1).unwrap();
+        assert_eq!(layout.height, 792.0);
+        assert_eq!(layout.glyphs[0].text, "T");
+        assert!((layout.glyphs[0].y - 72.0).abs() < 0.01);
+    }
+}
@@ -1,18 +1,39 @@
-use super::ParseError;
-use crate::document_parsing::ParsedDocument;
-use serde_json::json;
+use super::{DocumentParser, ParseError};
+use crate::document_parsing::blocks::paragraphs_from_text;
+use crate::document_parsing::{DocumentFormat, DocumentMetadata, ParsedDocument};
+
+/// Plain text parser.
+pub struct TextParser;
+
+impl DocumentParser for TextParser {
+    fn id(&self) -> &str {
+        DocumentFormat::Txt.as_str()
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["txt", "text", "log"]
+    }
+
+    fn mime_types(&self) -> &[&str] {
+        &["text/plain"]
+    }
+
+    fn parse(&self, bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
+        parse_text(bytes)
+    }
+}
 
 pub fn parse_text(bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
     let text = String::from_utf8(bytes.to_vec())?;
     let line_count = text.lines().count();
 
-    Ok(ParsedDocument {
-        text,
-        metadata: json!({
-            "format": "txt",
-            "line_count": line_count,
-        }),
-    })
+    let mut metadata = DocumentMetadata::new(DocumentFormat::Txt.as_str());
+    metadata
+        .extra
+        .insert("line_count".into(), line_count.into());
+
+    let blocks = paragraphs_from_text(&text);
+    Ok(ParsedDocument::with_text(bytes, text, blocks, metadata))
 }
 
 #[cfg(test)]
@@ -27,6 +48,7 @@ mod tests {
         assert_eq!(
             result
                 .metadata
+                .extra
                 .get("line_count")
                 .unwrap()
                 .as_u64()
@@ -35,6 +57,14 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_text_keeps_source_text() {
+        let data = "First paragraph.\n\n\n\nSecond  paragraph\n";
+        let result = parse_text(data.as_bytes()).unwrap();
+        assert_eq!(result.text, data);
+        assert_eq!(result.blocks.len(), 2);
+    }
+
     #[test]
     fn test_parse_text_empty() {
         let result = parse_text(b"").expect("Failed to parse empty text");
@@ -1,14 +1,55 @@
-use super::ParseError;
-use crate::document_parsing::ParsedDocument;
-use serde_json::json;
+use super::ooxml::{self, attr, Relationship};
+use super::{DocumentParser, ParseError};
+use crate::document_parsing::{
+    Block, DocumentFormat, DocumentMetadata, ParseOptions, ParsedDocument, SectionKind, Table,
+};
+use quick_xml::events::{BytesStart, Event};
+use std::collections::HashMap;
 use std::io::{Cursor, Read};
 
+/// PPTX parser.
+pub struct PptxParser;
+
+impl DocumentParser for PptxParser {
+    fn id(&self) -> &str {
+        DocumentFormat::Pptx.as_str()
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["pptx"]
+    }
+
+    fn mime_types(&self) -> &[&str] {
+        &["application/vnd.openxmlformats-officedocument.presentationml.presentation"]
+    }
+
+    fn parse(&self, bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
+        parse_pptx(bytes)
+    }
+
+    fn parse_with_options(
+        &self,
+        bytes: &[u8],
+        options: &ParseOptions,
+    ) -> Result<ParsedDocument, ParseError> {
+        parse_pptx_with_options(bytes, options)
+    }
+}
+
 pub fn parse_pptx(bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
+    parse_pptx_with_options(bytes, &ParseOptions::default())
+}
+
+/// Parse a PPTX, returning the files of `ppt/media` as `options.media` says.
+pub fn parse_pptx_with_options(
+    bytes: &[u8],
+    options: &ParseOptions,
+) -> Result<ParsedDocument, ParseError> {
     let cursor = Cursor::new(bytes);
     let mut archive = zip::ZipArchive::new(cursor)
         .map_err(|e| ParseError::Format(format!("Not a valid PPTX/ZIP: {}", e)))?;
 
-    let mut slides: Vec<(usize, String)> = Vec::new();
+    let mut slides: Vec<(usize, Vec<Block>)> = Vec::new();
 
     for i in 0..archive.len() {
         let mut file = archive
@@ -16,57 +57,161 @@ pub fn parse_pptx(bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
             .map_err(|e| ParseError::Format(format!("ZIP entry error: {}", e)))?;
         let name = file.name().to_string();
 
-        if name.starts_with("ppt/slides/slide") && name.ends_with(".xml") {
+        if let Some(number) = slide_number(&name) {
             let mut xml_content = String::new();
             file.read_to_string(&mut xml_content)?;
-            let text = extract_ooxml_text(&xml_content, b"a:t");
-            slides.push((slides.len() + 1, text));
+            drop(file);
+            let relationships = ooxml::read_relationships(&mut archive, &name);
+            slides.push((number, slide_blocks(&xml_content, &relationships)));
         }
     }
 
+    // ZIP order is arbitrary (slide10 may precede slide2), so sort by number.
+    slides.sort_by_key(|(number, _)| *number);
+
     let slide_count = slides.len();
-    let text = slides
+    let sections = slides
         .into_iter()
-        .map(|(num, text)| format!("--- Slide {} ---\n{}", num, text))
-        .collect::<Vec<_>>()
-        .join("\n\n");
-
-    Ok(ParsedDocument {
-        text,
-        metadata: json!({
-            "format": "pptx",
-            "slide_count": slide_count,
-        }),
-    })
+        .map(|(number, blocks)| {
+            let title = blocks.iter().find_map(|b| match b {
+                Block::Heading { text, .. } => Some(text.clone()),
+                _ => None,
+            });
+            Block::Section {
+                kind: SectionKind::Slide,
+                number,
+                title,
+                blocks,
+            }
+        })
+        .collect();
+
+    let mut metadata = DocumentMetadata::new(DocumentFormat::Pptx.as_str());
+    metadata.slide_count = Some(slide_count);
+    ooxml::read_properties(&mut archive, &mut metadata);
+
+    let mut parsed = ParsedDocument::new(bytes, sections, metadata);
+    parsed.attachments =
+        ooxml::read_media(&mut archive, "ppt/media/", &parsed.blocks, options.media);
+    Ok(parsed)
 }
 
-fn extract_ooxml_text(xml: &str, tag: &[u8]) -> String {
+/// `ppt/slides/slide12.xml` -> `Some(12)`.
+fn slide_number(name: &str) -> Option<usize> {
+    name.strip_prefix("ppt/slides/slide")?
+        .strip_suffix(".xml")?
+        .parse()
+        .ok()
+}
+
+/// Extract the blocks of one slide: title placeholders become headings, other
+/// text paragraphs stay paragraphs, `a:tbl` becomes a table and pictures
+/// become images, with their source resolved through the slide's
+/// `relationships`.
+fn slide_blocks(xml: &str, relationships: &HashMap<String, Relationship>) -> Vec<Block> {
     let mut reader = quick_xml::Reader::from_str(xml);
-    let mut in_target = false;
-    let mut parts = Vec::new();
+    let mut blocks = Vec::new();
     let mut buf = Vec::new();
 
+    let mut in_title_shape = false;
+    let mut in_text = false;
+    let mut in_picture = false;
+    let mut paragraph = String::new();
+    let mut table: Option<Table> = None;
+    let mut row: Vec<String> = Vec::new();
+    let mut cell: Vec<String> = Vec::new();
+
     loop {
         match reader.read_event_into(&mut buf) {
-            Ok(quick_xml::events::Event::Start(ref e)) if e.name().as_ref() == tag => {
-                in_target = true;
-            }
-            Ok(quick_xml::events::Event::Text(ref e)) if in_target => {
+            Ok(Event::Start(ref e)) => match e.name().as_ref() {
+                b"a:t" => in_text = true,
+                b"a:p" => paragraph.clear(),
+                b"a:tbl" => table = Some(Table::default()),
+                b"a:tr" => row.clear(),
+                b"a:tc" => cell.clear(),
+                b"p:pic" => in_picture = true,
+                b"p:ph" => in_title_shape = is_title_placeholder(e),
+                b"a:blip" if in_picture => picture_source(e, relationships, &mut blocks),
+                _ => {}
+            },
+            Ok(Event::Empty(ref e)) => match e.name().as_ref() {
+                b"p:ph" => in_title_shape = is_title_placeholder(e),
+                b"p:cNvPr" if in_picture => {
+                    let alt = attr(e, b"descr").filter(|d| !d.is_empty());
+                    blocks.push(Block::Image { alt, source: None });
+                }
+                b"a:blip" if in_picture => picture_source(e, relationships, &mut blocks),
+                b"a:br" => paragraph.push('\n'),
+                _ => {}
+            },
+            Ok(Event::Text(ref e)) if in_text => {
                 if let Ok(text) = e.unescape() {
-                    parts.push(text.to_string());
+                    paragraph.push_str(&text);
                 }
             }
-            Ok(quick_xml::events::Event::End(ref e)) if e.name().as_ref() == tag => {
-                in_target = false;
-            }
-            Ok(quick_xml::events::Event::Eof) => break,
+            Ok(Event::End(ref e)) => match e.name().as_ref() {
+                b"a:t" => in_text = false,
+                b"a:p" => {
+                    let text = paragraph.trim();
+                    if table.is_some() {
+                        cell.push(text.to_string());
+                    } else if !text.is_empty() {
+                        blocks.push(if in_title_shape {
+                            Block::heading(1, text)
+                        } else {
+                            Block::paragraph(text)
+                        });
+                    }
+                }
+                b"a:tc" => row.push(cell.join("\n").trim().to_string()),
+                b"a:tr" => {
+                    if let Some(table) = table.as_mut() {
+                        table.rows.push(std::mem::take(&mut row));
+                    }
+                }
+                b"a:tbl" => {
+                    if let Some(table) = table.take() {
+                        blocks.push(Block::Table(table));
+                    }
+                }
+                b"p:sp" => in_title_shape = false,
+                b"p:pic" => in_picture = false,
+                _ => {}
+            },
+            Ok(Event::Eof) => break,
             Err(_) => break,
             _ => {}
         }
         buf.clear();
     }
 
-    parts.join(" ")
+    blocks
+}
+
+/// Set the source of the picture just read from its `a:blip`, the embedded
+/// or linked image.
+fn picture_source(
+    e: &BytesStart,
+    relationships: &HashMap<String, Relationship>,
+    blocks: &mut [Block],
+) {
+    if let Some(Block::Image {
+        source: source @ None,
+        ..
+    }) = blocks.last_mut()
+    {
+        *source = attr(e, b"r:embed")
+            .or_else(|| attr(e, b"r:link"))
+            .and_then(|id| relationships.get(&id))
+            .map(|r| r.target.clone());
+    }
+}
+
+fn is_title_placeholder(e: &BytesStart) -> bool {
+    matches!(
+        attr(e, b"type").as_deref(),
+        Some("title") | Some("ctrTitle")
+    )
 }
 
 #[cfg(test)]
@@ -74,37 +219,54 @@ mod tests {
     use super::*;
     use std::io::Write;
 
-    fn create_minimal_pptx(text: &str) -> Vec<u8> {
+    fn create_pptx(slides: &[(usize, &str)]) -> Vec<u8> {
+        create_pptx_with_parts(slides, &[])
+    }
+
+    /// A package with the given slides, by number, and extra parts.
+    fn create_pptx_with_parts(slides: &[(usize, &str)], parts: &[(&str, &str)]) -> Vec<u8> {
         let buf = Vec::new();
         let cursor = Cursor::new(buf);
         let mut zip = zip::ZipWriter::new(cursor);
 
         let options = zip::write::SimpleFileOptions::default();
-        zip.start_file("ppt/slides/slide1.xml", options).unwrap();
-        let xml = format!(
-            r#"<?xml version="1.0" encoding="UTF-8"?>
+        for (name, content) in parts {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        for (number, shapes) in slides {
+            zip.start_file(format!("ppt/slides/slide{}.xml", number), options)
+                .unwrap();
+            let xml = format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
 <p:sld xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main"
        xmlns:p="http://schemas.openxmlformats.org/presentationml/2006/main">
-  <p:cSld><p:spTree><p:sp><p:txBody>
-    <a:p><a:r><a:t>{}</a:t></a:r></a:p>
-  </p:txBody></p:sp></p:spTree></p:cSld>
+  <p:cSld><p:spTree>{}</p:spTree></p:cSld>
 </p:sld>"#,
-            text
-        );
-        zip.write_all(xml.as_bytes()).unwrap();
+                shapes
+            );
+            zip.write_all(xml.as_bytes()).unwrap();
+        }
 
         zip.finish().unwrap().into_inner()
     }
 
+    fn create_minimal_pptx(text: &str) -> Vec<u8> {
+        create_pptx(&[(
+            1,
+            &format!(
+                "<p:sp><p:txBody><a:p><a:r><a:t>{}</a:t></a:r></a:p></p:txBody></p:sp>",
+                text
+            ),
+        )])
+    }
+
     #[test]
     fn test_parse_pptx_basic() {
         let pptx_bytes = create_minimal_pptx("Slide Content");
         let result = parse_pptx(&pptx_bytes).expect("Failed to parse PPTX");
         assert!(result.text.contains("Slide Content"));
-        assert_eq!(
-            result.metadata.get("slide_count").unwrap().as_u64().unwrap(),
-            1
-        );
+        assert_eq!(result.metadata.slide_count, Some(1));
     }
 
     #[test]
@@ -112,4 +274,78 @@ mod tests {
         let result = parse_pptx(b"not a zip");
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_parse_pptx_structure() {
+        let title = r#"<p:sp><p:nvSpPr><p:nvPr><p:ph type="title"/></p:nvPr></p:nvSpPr>
+            <p:txBody><a:p><a:r><a:t>Roadmap</a:t></a:r></a:p></p:txBody></p:sp>"#;
+        let table = r#"<p:graphicFrame><a:graphic><a:graphicData><a:tbl>
+            <a:tr><a:tc><a:txBody><a:p><a:r><a:t>Q1</a:t></a:r></a:p></a:txBody></a:tc>
+                  <a:tc><a:txBody><a:p><a:r><a:t>Beta</a:t></a:r></a:p></a:txBody></a:tc></a:tr>
+            </a:tbl></a:graphicData></a:graphic></p:graphicFrame>"#;
+        let pptx_bytes = create_pptx(&[
+            (
+                10,
+                "<p:sp><p:txBody><a:p><a:r><a:t>Last</a:t></a:r></a:p></p:txBody></p:sp>",
+            ),
+            (2, &format!("{}{}", title, table)),
+        ]);
+
+        let result = parse_pptx(&pptx_bytes).expect("Failed to parse PPTX");
+        assert_eq!(
+            result.blocks[0],
+            Block::Section {
+                kind: SectionKind::Slide,
+                number: 2,
+                title: Some("Roadmap".into()),
+                blocks: vec![
+                    Block::heading(1, "Roadmap"),
+                    Block::Table(Table {
+                        rows: vec![vec!["Q1".into(), "Beta".into()]],
+                        header: false,
+                    }),
+                ],
+            }
+        );
+        assert!(result.text.ends_with("--- Slide 10 ---\nLast"));
+    }
+
+    #[test]
+    fn test_parse_pptx_media() {
+        let rels = r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+  <Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/image" Target="../media/image3.jpeg"/>
+</Relationships>"#;
+        let picture = r#"<p:pic><p:nvPicPr><p:cNvPr id="4" name="Picture 3" descr="Team photo"/></p:nvPicPr>
+            <p:blipFill><a:blip r:embed="rId2"/></p:blipFill></p:pic>"#;
+        let pptx_bytes = create_pptx_with_parts(
+            &[
+                (
+                    1,
+                    "<p:sp><p:txBody><a:p><a:r><a:t>Welcome</a:t></a:r></a:p></p:txBody></p:sp>",
+                ),
+                (2, picture),
+            ],
+            &[
+                ("ppt/slides/_rels/slide2.xml.rels", rels),
+                ("ppt/media/image3.jpeg", "\u{7f}"),
+            ],
+        );
+        let options = ParseOptions {
+            media: crate::document_parsing::MediaMode::Hash,
+            ..Default::default()
+        };
+
+        let result = parse_pptx_with_options(&pptx_bytes, &options).expect("Failed to parse PPTX");
+        assert!(result
+            .text
+            .ends_with("--- Slide 2 ---\n[Image: Team photo]"));
+        let [attachment] = result.attachments.as_slice() else {
+            panic!("expected one attachment, got {:?}", result.attachments);
+        };
+        assert_eq!(attachment.name, "ppt/media/image3.jpeg");
+        assert_eq!(attachment.mime_type, "image/jpeg");
+        assert_eq!(attachment.alt.as_deref(), Some("Team photo"));
+        assert_eq!(attachment.slide, Some(2));
+        assert_eq!(attachment.offset, result.text.find("[Image: Team photo]"));
+    }
 }
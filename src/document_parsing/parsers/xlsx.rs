@@ -1,35 +1,195 @@
-use super::ParseError;
-use crate::document_parsing::ParsedDocument;
+use super::ooxml::{self, attr, Relationship};
+use super::{DocumentParser, ParseError};
+use crate::document_parsing::{
+    Block, DocumentFormat, DocumentMetadata, ParseOptions, ParsedDocument, SectionKind, Table,
+};
 use calamine::{Reader, Xlsx};
-use serde_json::json;
-use std::io::Cursor;
+use quick_xml::events::Event;
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Seek};
+use zip::ZipArchive;
+
+/// XLSX parser.
+pub struct XlsxParser;
+
+impl DocumentParser for XlsxParser {
+    fn id(&self) -> &str {
+        DocumentFormat::Xlsx.as_str()
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["xlsx"]
+    }
+
+    fn mime_types(&self) -> &[&str] {
+        &["application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"]
+    }
+
+    fn parse(&self, bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
+        parse_xlsx(bytes)
+    }
+
+    fn parse_with_options(
+        &self,
+        bytes: &[u8],
+        options: &ParseOptions,
+    ) -> Result<ParsedDocument, ParseError> {
+        parse_xlsx_with_options(bytes, options)
+    }
+}
 
 pub fn parse_xlsx(bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
+    parse_xlsx_with_options(bytes, &ParseOptions::default())
+}
+
+/// Parse an XLSX into one section per sheet: its cells as a table, then the
+/// pictures drawn on it. The files of `xl/media` are returned as
+/// `options.media` says.
+pub fn parse_xlsx_with_options(
+    bytes: &[u8],
+    options: &ParseOptions,
+) -> Result<ParsedDocument, ParseError> {
     let cursor = Cursor::new(bytes);
-    let mut workbook: Xlsx<_> = Xlsx::new(cursor)
-        .map_err(|e| ParseError::Format(format!("XLSX open failed: {}", e)))?;
+    let mut workbook: Xlsx<_> =
+        Xlsx::new(cursor).map_err(|e| ParseError::Format(format!("XLSX open failed: {}", e)))?;
 
     let sheet_names: Vec<String> = workbook.sheet_names().to_vec();
-    let mut text_parts = Vec::new();
+    let mut sections = Vec::new();
+    let mut archive = ZipArchive::new(Cursor::new(bytes)).ok();
+    let mut pictures = archive.as_mut().map(sheet_pictures).unwrap_or_default();
 
-    for name in &sheet_names {
+    for (index, name) in sheet_names.iter().enumerate() {
         if let Ok(range) = workbook.worksheet_range(name) {
-            text_parts.push(format!("--- Sheet: {} ---", name));
-            for row in range.rows() {
-                let cells: Vec<String> = row.iter().map(|c| c.to_string()).collect();
-                text_parts.push(cells.join("\t"));
+            let rows: Vec<Vec<String>> = range
+                .rows()
+                .map(|row| row.iter().map(|c| c.to_string()).collect())
+                .collect();
+            let mut blocks = if rows.is_empty() {
+                Vec::new()
+            } else {
+                vec![Block::Table(Table {
+                    rows,
+                    header: false,
+                })]
+            };
+            blocks.extend(pictures.remove(name).unwrap_or_default());
+            sections.push(Block::Section {
+                kind: SectionKind::Sheet,
+                number: index + 1,
+                title: Some(name.clone()),
+                blocks,
+            });
+        }
+    }
+
+    let mut metadata = DocumentMetadata::new(DocumentFormat::Xlsx.as_str());
+    metadata.sheet_count = Some(sheet_names.len());
+    metadata
+        .extra
+        .insert("sheet_names".into(), sheet_names.into());
+    if let Some(archive) = archive.as_mut() {
+        ooxml::read_properties(archive, &mut metadata);
+    }
+
+    let mut parsed = ParsedDocument::new(bytes, sections, metadata);
+    if let Some(archive) = archive.as_mut() {
+        parsed.attachments = ooxml::read_media(archive, "xl/media/", &parsed.blocks, options.media);
+    }
+    Ok(parsed)
+}
+
+/// The pictures drawn on each sheet, by sheet name, following the
+/// relationships from the workbook to its sheets, their drawings and the
+/// images these show.
+fn sheet_pictures<R: Read + Seek>(archive: &mut ZipArchive<R>) -> HashMap<String, Vec<Block>> {
+    let mut pictures = HashMap::new();
+    let Ok(Some(workbook)) = ooxml::read_part(archive, "xl/workbook.xml") else {
+        return pictures;
+    };
+    let workbook_relationships = ooxml::read_relationships(archive, "xl/workbook.xml");
+
+    for (name, id) in sheet_ids(&workbook) {
+        let Some(sheet) = workbook_relationships.get(&id) else {
+            continue;
+        };
+        let mut drawings: Vec<String> = ooxml::read_relationships(archive, &sheet.target)
+            .into_values()
+            .filter(|r| r.kind == "drawing" && !r.external)
+            .map(|r| r.target)
+            .collect();
+        drawings.sort();
+
+        let mut blocks = Vec::new();
+        for drawing in drawings {
+            if let Ok(Some(xml)) = ooxml::read_part(archive, &drawing) {
+                let relationships = ooxml::read_relationships(archive, &drawing);
+                blocks.extend(drawing_pictures(&xml, &relationships));
+            }
+        }
+        if !blocks.is_empty() {
+            pictures.insert(name, blocks);
+        }
+    }
+    pictures
+}
+
+/// The name and relationship id of each sheet of `xl/workbook.xml`.
+fn sheet_ids(xml: &str) -> Vec<(String, String)> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut sheets = Vec::new();
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e))
+                if e.local_name().as_ref() == b"sheet" =>
+            {
+                if let (Some(name), Some(id)) = (attr(e, b"name"), attr(e, b"r:id")) {
+                    sheets.push((name, id));
+                }
             }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
         }
+        buf.clear();
     }
+    sheets
+}
 
-    Ok(ParsedDocument {
-        text: text_parts.join("\n"),
-        metadata: json!({
-            "format": "xlsx",
-            "sheet_names": sheet_names,
-            "sheet_count": sheet_names.len(),
-        }),
-    })
+/// The pictures (`xdr:pic`) of a drawing, in drawing order.
+fn drawing_pictures(xml: &str, relationships: &HashMap<String, Relationship>) -> Vec<Block> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut blocks = Vec::new();
+    let mut in_picture = false;
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => match e.name().as_ref() {
+                b"xdr:pic" => in_picture = true,
+                b"xdr:cNvPr" if in_picture => blocks.push(Block::Image {
+                    alt: attr(e, b"descr").filter(|d| !d.is_empty()),
+                    source: None,
+                }),
+                b"a:blip" if in_picture => {
+                    if let Some(Block::Image {
+                        source: source @ None,
+                        ..
+                    }) = blocks.last_mut()
+                    {
+                        *source = attr(e, b"r:embed")
+                            .or_else(|| attr(e, b"r:link"))
+                            .and_then(|id| relationships.get(&id))
+                            .map(|r| r.target.clone());
+                    }
+                }
+                _ => {}
+            },
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"xdr:pic" => in_picture = false,
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    blocks
 }
 
 #[cfg(test)]
@@ -1,11 +1,31 @@
-use super::ParseError;
-use crate::document_parsing::ParsedDocument;
-use serde_json::json;
+use super::{DocumentParser, ParseError};
+use crate::document_parsing::{Block, DocumentFormat, DocumentMetadata, ParsedDocument};
+
+/// XML parser.
+pub struct XmlParser;
+
+impl DocumentParser for XmlParser {
+    fn id(&self) -> &str {
+        DocumentFormat::Xml.as_str()
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["xml"]
+    }
+
+    fn mime_types(&self) -> &[&str] {
+        &["application/xml", "text/xml"]
+    }
+
+    fn parse(&self, bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
+        parse_xml(bytes)
+    }
+}
 
 pub fn parse_xml(bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
     let xml_str = String::from_utf8(bytes.to_vec())?;
     let mut reader = quick_xml::Reader::from_str(&xml_str);
-    let mut text_parts = Vec::new();
+    let mut blocks = Vec::new();
     let mut buf = Vec::new();
 
     loop {
@@ -14,7 +34,7 @@ pub fn parse_xml(bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
                 if let Ok(text) = e.unescape() {
                     let trimmed = text.trim();
                     if !trimmed.is_empty() {
-                        text_parts.push(trimmed.to_string());
+                        blocks.push(Block::paragraph(trimmed));
                     }
                 }
             }
@@ -25,10 +45,11 @@ pub fn parse_xml(bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
         buf.clear();
     }
 
-    Ok(ParsedDocument {
-        text: text_parts.join("\n"),
-        metadata: json!({ "format": "xml" }),
-    })
+    Ok(ParsedDocument::new(
+        bytes,
+        blocks,
+        DocumentMetadata::new(DocumentFormat::Xml.as_str()),
+    ))
 }
 
 #[cfg(test)]
@@ -0,0 +1,403 @@
+use super::{
+    extension_of, DocumentFormat, DocumentParser, ParseError, ParseOptions, ParsedDocument,
+};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::{OnceLock, PoisonError, RwLock, RwLockReadGuard};
+
+static GLOBAL: OnceLock<RwLock<ParserRegistry>> = OnceLock::new();
+
+/// Maps format ids, file extensions and MIME types to parsers.
+///
+/// `ParserRegistry::default()` comes pre-filled with the built-in parsers.
+/// Registering a parser under an existing id replaces it, which is how
+/// built-ins are overridden.
+///
+/// [`ParserRegistry::global`] is the registry behind the free functions such
+/// as [`parse`](super::parse), the command line and batch runs; a parser
+/// registered there is used by all of them.
+pub struct ParserRegistry {
+    parsers: HashMap<String, Box<dyn DocumentParser>>,
+    /// The ids claiming each extension, the current owner last.
+    by_extension: HashMap<String, Vec<String>>,
+    /// The ids claiming each MIME type, the current owner last.
+    by_mime: HashMap<String, Vec<String>>,
+}
+
+impl ParserRegistry {
+    /// An empty registry.
+    pub fn new() -> Self {
+        Self {
+            parsers: HashMap::new(),
+            by_extension: HashMap::new(),
+            by_mime: HashMap::new(),
+        }
+    }
+
+    /// The registry used by [`parse`](super::parse),
+    /// [`parse_path`](super::parse_path) and their `_with_options` forms, the
+    /// async functions, the command line and batch runs by default. It starts
+    /// with the built-in parsers.
+    ///
+    /// Parsing holds the read lock, so a parser must not register parsers
+    /// here while it runs.
+    pub fn global() -> &'static RwLock<ParserRegistry> {
+        GLOBAL.get_or_init(|| RwLock::new(Self::with_builtins()))
+    }
+
+    /// Read access to [`ParserRegistry::global`]. A panic while it was being
+    /// changed leaves it usable.
+    pub fn read_global() -> RwLockReadGuard<'static, ParserRegistry> {
+        Self::global()
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// A registry holding the parser for every [`DocumentFormat`].
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        for format in DocumentFormat::ALL {
+            registry.register(Box::new(Builtin(format)));
+        }
+        registry
+    }
+
+    /// Register `parser` under its id, extensions and MIME types. Returns the
+    /// parser previously registered under the same id, if any.
+    ///
+    /// Extensions and MIME types already claimed by another parser are taken
+    /// over by the new one, and handed back when it is unregistered.
+    pub fn register(&mut self, parser: Box<dyn DocumentParser>) -> Option<Box<dyn DocumentParser>> {
+        let id = parser.id().to_string();
+
+        let previous = self.parsers.remove(&id);
+        if previous.is_some() {
+            release(&mut self.by_extension, &id);
+            release(&mut self.by_mime, &id);
+        }
+
+        for ext in parser.extensions() {
+            claim(&mut self.by_extension, ext, &id);
+        }
+        for mime in parser.mime_types() {
+            claim(&mut self.by_mime, mime, &id);
+        }
+        self.parsers.insert(id, parser);
+
+        previous
+    }
+
+    /// Remove the parser registered under `id`. Its extensions and MIME types
+    /// go back to the parsers that claimed them before it.
+    pub fn unregister(&mut self, id: &str) -> Option<Box<dyn DocumentParser>> {
+        let removed = self.parsers.remove(id)?;
+        release(&mut self.by_extension, id);
+        release(&mut self.by_mime, id);
+        Some(removed)
+    }
+
+    /// Registered format ids, sorted.
+    pub fn ids(&self) -> Vec<&str> {
+        let mut ids: Vec<&str> = self.parsers.keys().map(String::as_str).collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    pub fn get(&self, id: &str) -> Option<&dyn DocumentParser> {
+        self.parsers.get(id).map(|p| p.as_ref())
+    }
+
+    /// Look up a parser by extension, with or without the leading dot.
+    pub fn for_extension(&self, ext: &str) -> Option<&dyn DocumentParser> {
+        let ext = ext.trim_start_matches('.').to_lowercase();
+        self.by_extension
+            .get(&ext)
+            .and_then(|ids| ids.last())
+            .and_then(|id| self.get(id))
+    }
+
+    /// Look up a parser by MIME type. Parameters such as `; charset=utf-8`
+    /// are ignored.
+    pub fn for_mime(&self, mime: &str) -> Option<&dyn DocumentParser> {
+        let essence = mime.split(';').next().unwrap_or(mime).trim().to_lowercase();
+        self.by_mime
+            .get(&essence)
+            .and_then(|ids| ids.last())
+            .and_then(|id| self.get(id))
+    }
+
+    /// Look up a parser by the extension of a file name or URL.
+    pub fn for_path(&self, path: &str) -> Option<&dyn DocumentParser> {
+        extension_of(path).and_then(|ext| self.for_extension(&ext))
+    }
+
+    /// Pick a parser for `bytes`.
+    ///
+    /// An extension claimed by a custom (non built-in) format is trusted, since
+    /// content sniffing only knows the built-in formats. Otherwise the format
+    /// is detected from the content, falling back to the extension.
+    pub fn resolve(&self, bytes: &[u8], hint_name: Option<&str>) -> Option<&dyn DocumentParser> {
+        let by_name = hint_name.and_then(|n| self.for_path(n));
+        if let Some(parser) = by_name {
+            if !is_builtin_id(parser.id()) {
+                return Some(parser);
+            }
+        }
+
+        DocumentFormat::detect(bytes, hint_name)
+            .and_then(|format| self.get(format.as_str()))
+            .or(by_name)
+    }
+
+    /// Parse `bytes` with the parser registered under `id`.
+    pub fn parse(&self, id: &str, bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
+        self.parse_with_options(id, bytes, &ParseOptions::default())
+    }
+
+    /// Parse `bytes` with the parser registered under `id`, with `options`.
+    pub fn parse_with_options(
+        &self,
+        id: &str,
+        bytes: &[u8],
+        options: &ParseOptions,
+    ) -> Result<ParsedDocument, ParseError> {
+        self.get(id)
+            .ok_or_else(|| ParseError::UnsupportedFormat(id.to_string()))?
+            .parse_with_options(bytes, options)
+    }
+
+    /// Read and parse the file at `path` with the parser chosen by
+    /// [`ParserRegistry::resolve`].
+    pub fn parse_path(&self, path: impl AsRef<Path>) -> Result<ParsedDocument, ParseError> {
+        self.parse_path_with_options(path, &ParseOptions::default())
+    }
+
+    /// Read and parse the file at `path` with the parser chosen by
+    /// [`ParserRegistry::resolve`], with `options`.
+    pub fn parse_path_with_options(
+        &self,
+        path: impl AsRef<Path>,
+        options: &ParseOptions,
+    ) -> Result<ParsedDocument, ParseError> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)?;
+        let name = path.file_name().and_then(|n| n.to_str());
+
+        self.resolve(&bytes, name)
+            .ok_or_else(|| ParseError::UnsupportedFormat(path.display().to_string()))?
+            .parse_with_options(&bytes, options)
+    }
+}
+
+impl Default for ParserRegistry {
+    fn default() -> Self {
+        Self::with_builtins()
+    }
+}
+
+impl fmt::Debug for ParserRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ParserRegistry")
+            .field("ids", &self.ids())
+            .finish()
+    }
+}
+
+/// Make `id` the owner of `key`.
+fn claim(owners: &mut HashMap<String, Vec<String>>, key: &str, id: &str) {
+    let ids = owners.entry(key.to_lowercase()).or_default();
+    ids.retain(|owner| owner != id);
+    ids.push(id.to_string());
+}
+
+/// Drop every claim of `id`, so each key goes back to its previous owner.
+fn release(owners: &mut HashMap<String, Vec<String>>, id: &str) {
+    owners.retain(|_, ids| {
+        ids.retain(|owner| owner != id);
+        !ids.is_empty()
+    });
+}
+
+fn is_builtin_id(id: &str) -> bool {
+    DocumentFormat::ALL.iter().any(|f| f.as_str() == id)
+}
+
+/// Owned handle to a built-in parser, so it can be boxed like custom ones.
+struct Builtin(DocumentFormat);
+
+impl DocumentParser for Builtin {
+    fn id(&self) -> &str {
+        self.0.parser().id()
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.0.parser().extensions()
+    }
+
+    fn mime_types(&self) -> &[&str] {
+        self.0.parser().mime_types()
+    }
+
+    fn parse(&self, bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
+        self.0.parser().parse(bytes)
+    }
+
+    fn parse_with_options(
+        &self,
+        bytes: &[u8],
+        options: &ParseOptions,
+    ) -> Result<ParsedDocument, ParseError> {
+        self.0.parser().parse_with_options(bytes, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document_parsing::{Block, DocumentMetadata};
+
+    struct LogBundleParser;
+
+    impl DocumentParser for LogBundleParser {
+        fn id(&self) -> &str {
+            "logbundle"
+        }
+
+        fn extensions(&self) -> &[&str] {
+            &["logb"]
+        }
+
+        fn mime_types(&self) -> &[&str] {
+            &["application/x-logbundle"]
+        }
+
+        fn parse(&self, bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
+            let text = String::from_utf8(bytes.to_vec())?.to_uppercase();
+            Ok(ParsedDocument::new(
+                bytes,
+                vec![Block::paragraph(text)],
+                DocumentMetadata::new("logbundle"),
+            ))
+        }
+    }
+
+    struct ShoutingTextParser;
+
+    impl DocumentParser for ShoutingTextParser {
+        fn id(&self) -> &str {
+            "txt"
+        }
+
+        fn extensions(&self) -> &[&str] {
+            &["txt"]
+        }
+
+        fn parse(&self, bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
+            LogBundleParser.parse(bytes)
+        }
+    }
+
+    #[test]
+    fn test_registry_builtins() {
+        let registry = ParserRegistry::default();
+        assert_eq!(registry.ids().len(), DocumentFormat::ALL.len());
+        assert_eq!(registry.for_extension(".HTM").unwrap().id(), "html");
+        assert_eq!(
+            registry.for_mime("text/csv; charset=utf-8").unwrap().id(),
+            "csv"
+        );
+        assert_eq!(
+            registry.for_path("https://x.test/a.pdf?dl=1").unwrap().id(),
+            "pdf"
+        );
+    }
+
+    #[test]
+    fn test_registry_custom_format() {
+        let mut registry = ParserRegistry::default();
+        assert!(registry.register(Box::new(LogBundleParser)).is_none());
+
+        let parser = registry.resolve(b"boot ok", Some("node1.logb")).unwrap();
+        assert_eq!(parser.id(), "logbundle");
+        assert_eq!(
+            registry.for_mime("application/x-logbundle").unwrap().id(),
+            "logbundle"
+        );
+
+        let doc = registry.parse("logbundle", b"boot ok").unwrap();
+        assert_eq!(doc.text, "BOOT OK");
+    }
+
+    #[test]
+    fn test_registry_override_builtin() {
+        let mut registry = ParserRegistry::default();
+        assert!(registry.register(Box::new(ShoutingTextParser)).is_some());
+
+        // The old parser's extra extensions are released along with it.
+        assert!(registry.for_extension("log").is_none());
+        let doc = registry.parse("txt", b"quiet").unwrap();
+        assert_eq!(doc.text, "QUIET");
+    }
+
+    /// A custom parser claiming an extension of a built-in one.
+    struct NotesParser;
+
+    impl DocumentParser for NotesParser {
+        fn id(&self) -> &str {
+            "notes"
+        }
+
+        fn extensions(&self) -> &[&str] {
+            &["txt", "notes"]
+        }
+
+        fn parse(&self, bytes: &[u8]) -> Result<ParsedDocument, ParseError> {
+            LogBundleParser.parse(bytes)
+        }
+    }
+
+    #[test]
+    fn test_registry_unregister_restores_extensions() {
+        let mut registry = ParserRegistry::default();
+        registry.register(Box::new(NotesParser));
+        assert_eq!(registry.for_extension("txt").unwrap().id(), "notes");
+
+        assert!(registry.unregister("notes").is_some());
+        assert_eq!(registry.for_extension("txt").unwrap().id(), "txt");
+        assert!(registry.for_extension("notes").is_none());
+    }
+
+    #[test]
+    fn test_global_registry_serves_free_functions() {
+        let dir = crate::test_support::TempDir::new("registry");
+        let path = dir.join("node1.logb");
+        fs::write(&path, "boot ok").unwrap();
+
+        ParserRegistry::global()
+            .write()
+            .unwrap()
+            .register(Box::new(LogBundleParser));
+        let doc = crate::document_parsing::parse_path(&path);
+        ParserRegistry::global()
+            .write()
+            .unwrap()
+            .unregister("logbundle");
+        assert_eq!(doc.unwrap().text, "BOOT OK");
+    }
+
+    #[test]
+    fn test_registry_content_beats_builtin_extension() {
+        let registry = ParserRegistry::default();
+        let parser = registry.resolve(b"%PDF-1.4\n", Some("notes.txt")).unwrap();
+        assert_eq!(parser.id(), "pdf");
+    }
+
+    #[test]
+    fn test_registry_unknown_id() {
+        let registry = ParserRegistry::new();
+        let result = registry.parse("pdf", b"");
+        assert!(matches!(result, Err(ParseError::UnsupportedFormat(_))));
+    }
+}
@@ -0,0 +1,7 @@
+pub mod batch;
+pub mod chunking;
+pub mod document_parsing;
+pub mod tokenizer;
+
+#[cfg(test)]
+mod test_support;
@@ -0,0 +1,349 @@
+use super::{Tokenizer, TokenizerError};
+use base64::Engine;
+use fancy_regex::Regex;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fs;
+use std::path::Path;
+
+/// Pre-tokenizer pattern of the GPT-2 / `r50k_base` encodings.
+pub const GPT2_PATTERN: &str =
+    r"'s|'t|'re|'ve|'m|'ll|'d| ?\p{L}+| ?\p{N}+| ?[^\s\p{L}\p{N}]+|\s+(?!\S)|\s+";
+
+/// Pre-tokenizer pattern of the `cl100k_base` encoding (GPT-4, GPT-3.5).
+pub const CL100K_PATTERN: &str = r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+(?!\S)|\s+";
+
+/// Pre-tokenizer pattern of the `o200k_base` encoding (GPT-4o).
+pub const O200K_PATTERN: &str = concat!(
+    r"[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]*[\p{Ll}\p{Lm}\p{Lo}\p{M}]+(?i:'s|'t|'re|'ve|'m|'ll|'d)?",
+    r"|[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]+[\p{Ll}\p{Lm}\p{Lo}\p{M}]*(?i:'s|'t|'re|'ve|'m|'ll|'d)?",
+    r"|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n/]*|\s*[\r\n]+|\s+(?!\S)|\s+",
+);
+
+/// Merge priority of each adjacent pair of token ids; lower merges first.
+type MergeRanks = HashMap<(u32, u32), u32>;
+
+/// Byte-pair-encoding tokenizer loaded from a local vocabulary file.
+///
+/// Two formats are supported: tiktoken `.tiktoken` files (one base64 token and
+/// its rank per line) and Hugging Face `tokenizer.json` files with a byte-level
+/// BPE model. Text is split with the model's pre-tokenizer pattern, then each
+/// piece is merged pair by pair in rank order, exactly as the reference
+/// implementations do.
+pub struct BpeTokenizer {
+    /// Token bytes to rank (tiktoken) or id (Hugging Face).
+    vocab: HashMap<Vec<u8>, u32>,
+    /// Merge priorities for Hugging Face models. tiktoken ranks merges by the
+    /// rank of the merged token instead.
+    merges: Option<MergeRanks>,
+    pattern: Regex,
+}
+
+impl BpeTokenizer {
+    /// Load a tiktoken vocabulary. `pattern` is the encoding's pre-tokenizer
+    /// regex, e.g. [`CL100K_PATTERN`]; tiktoken files do not carry it.
+    pub fn from_tiktoken_file(
+        path: impl AsRef<Path>,
+        pattern: &str,
+    ) -> Result<Self, TokenizerError> {
+        Self::from_tiktoken(&fs::read_to_string(path)?, pattern)
+    }
+
+    pub fn from_tiktoken(data: &str, pattern: &str) -> Result<Self, TokenizerError> {
+        let engine = base64::engine::general_purpose::STANDARD;
+        let mut vocab = HashMap::new();
+
+        for (line_no, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid =
+                || TokenizerError::Vocabulary(format!("line {}: {:?}", line_no + 1, line));
+            let (token, rank) = line.split_once(' ').ok_or_else(invalid)?;
+            let token = engine.decode(token).map_err(|_| invalid())?;
+            let rank = rank.trim().parse().map_err(|_| invalid())?;
+            vocab.insert(token, rank);
+        }
+
+        Ok(Self {
+            vocab,
+            merges: None,
+            pattern: compile(pattern)?,
+        })
+    }
+
+    /// Load a Hugging Face `tokenizer.json` with a BPE model. The pre-tokenizer
+    /// pattern is taken from the file when it has a `Split` step, and defaults
+    /// to the GPT-2 pattern for plain `ByteLevel` pre-tokenizers.
+    pub fn from_hf_json_file(path: impl AsRef<Path>) -> Result<Self, TokenizerError> {
+        Self::from_hf_json(&fs::read_to_string(path)?)
+    }
+
+    pub fn from_hf_json(data: &str) -> Result<Self, TokenizerError> {
+        let json: serde_json::Value =
+            serde_json::from_str(data).map_err(|e| TokenizerError::Vocabulary(e.to_string()))?;
+        let model = &json["model"];
+        if model["type"].as_str().is_some_and(|t| t != "BPE") {
+            return Err(TokenizerError::Vocabulary(format!(
+                "unsupported model type {}",
+                model["type"]
+            )));
+        }
+
+        let decode = byte_level_decoder();
+        let to_bytes = |token: &str| -> Vec<u8> {
+            token
+                .chars()
+                .map(|c| decode.get(&c).copied())
+                .collect::<Option<Vec<u8>>>()
+                // Not a byte-level token: fall back to its UTF-8 spelling.
+                .unwrap_or_else(|| token.as_bytes().to_vec())
+        };
+
+        let vocab: HashMap<Vec<u8>, u32> = model["vocab"]
+            .as_object()
+            .ok_or_else(|| TokenizerError::Vocabulary("missing model.vocab".into()))?
+            .iter()
+            .filter_map(|(token, id)| Some((to_bytes(token), id.as_u64()? as u32)))
+            .collect();
+
+        let mut merges = HashMap::new();
+        let entries = model["merges"]
+            .as_array()
+            .ok_or_else(|| TokenizerError::Vocabulary("missing model.merges".into()))?;
+        for (rank, merge) in entries.iter().enumerate() {
+            // Older files store "a b", newer ones ["a", "b"].
+            let pair = match merge {
+                serde_json::Value::String(s) => s.split_once(' '),
+                serde_json::Value::Array(parts) => match (parts.first(), parts.get(1)) {
+                    (Some(a), Some(b)) => a.as_str().zip(b.as_str()),
+                    _ => None,
+                },
+                _ => None,
+            };
+            let (a, b) = pair.ok_or_else(|| {
+                TokenizerError::Vocabulary(format!("invalid merge at index {}", rank))
+            })?;
+            // A merge of a token outside the vocabulary can never apply.
+            if let (Some(&a), Some(&b)) = (vocab.get(&to_bytes(a)), vocab.get(&to_bytes(b))) {
+                merges.insert((a, b), rank as u32);
+            }
+        }
+
+        let pattern = hf_split_pattern(&json["pre_tokenizer"]).unwrap_or(GPT2_PATTERN.to_string());
+
+        Ok(Self {
+            vocab,
+            merges: Some(merges),
+            pattern: compile(&pattern)?,
+        })
+    }
+
+    /// Number of tokens `piece` encodes to.
+    ///
+    /// Parts are byte ranges of `piece`, linked through `next` by their start.
+    /// Candidate merges wait in a heap ordered by rank and then position, and
+    /// one that no longer joins two neighbouring parts is dropped when popped.
+    fn count_piece(&self, piece: &[u8]) -> usize {
+        let len = piece.len();
+        if len <= 1 || self.vocab.contains_key(piece) {
+            return 1;
+        }
+
+        let mut next: Vec<usize> = (1..=len).collect();
+        let mut prev: Vec<usize> = (0..len).map(|i| i.saturating_sub(1)).collect();
+        let mut alive = vec![true; len];
+        let mut heap = BinaryHeap::new();
+        for start in 0..len - 1 {
+            if let Some(rank) = self.merge_rank(piece, start, start + 1, start + 2) {
+                heap.push(Reverse((rank, start, start + 2)));
+            }
+        }
+
+        let mut parts = len;
+        while let Some(Reverse((_, start, end))) = heap.pop() {
+            let mid = next[start];
+            if !alive[start] || mid >= len || next[mid] != end {
+                continue;
+            }
+            next[start] = end;
+            alive[mid] = false;
+            if end < len {
+                prev[end] = start;
+            }
+            parts -= 1;
+
+            if start > 0 {
+                let before = prev[start];
+                if let Some(rank) = self.merge_rank(piece, before, start, end) {
+                    heap.push(Reverse((rank, before, end)));
+                }
+            }
+            if end < len {
+                if let Some(rank) = self.merge_rank(piece, start, end, next[end]) {
+                    heap.push(Reverse((rank, start, next[end])));
+                }
+            }
+        }
+        parts
+    }
+
+    /// Rank of merging `piece[start..mid]` with `piece[mid..end]`.
+    fn merge_rank(&self, piece: &[u8], start: usize, mid: usize, end: usize) -> Option<u32> {
+        match &self.merges {
+            Some(merges) => {
+                let left = self.vocab.get(&piece[start..mid])?;
+                let right = self.vocab.get(&piece[mid..end])?;
+                merges.get(&(*left, *right)).copied()
+            }
+            None => self.vocab.get(&piece[start..end]).copied(),
+        }
+    }
+}
+
+impl Tokenizer for BpeTokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        let mut count = 0;
+        let mut pos = 0;
+        while pos < text.len() {
+            match self.pattern.find_from_pos(text, pos) {
+                Ok(Some(m)) if m.end() > pos => {
+                    count += self.count_piece(m.as_str().as_bytes());
+                    pos = m.end();
+                }
+                Ok(Some(_)) => {
+                    pos += text[pos..].chars().next().map_or(1, char::len_utf8);
+                }
+                Ok(None) => break,
+                // The pattern gave up, e.g. on its backtrack limit, and would
+                // give up again at the same spot. Count the rest byte by
+                // byte, the most tokens it can encode to.
+                Err(_) => {
+                    count += text.len() - pos;
+                    break;
+                }
+            }
+        }
+        count
+    }
+}
+
+fn compile(pattern: &str) -> Result<Regex, TokenizerError> {
+    Regex::new(pattern).map_err(|e| TokenizerError::Pattern(e.to_string()))
+}
+
+/// The regex of the first `Split` step of a Hugging Face pre-tokenizer.
+fn hf_split_pattern(pre_tokenizer: &serde_json::Value) -> Option<String> {
+    match pre_tokenizer["type"].as_str()? {
+        "Split" => pre_tokenizer["pattern"]["Regex"].as_str().map(String::from),
+        "Sequence" => pre_tokenizer["pretokenizers"]
+            .as_array()?
+            .iter()
+            .find_map(hf_split_pattern),
+        _ => None,
+    }
+}
+
+/// Inverse of the GPT-2 byte-to-unicode table used by byte-level BPE
+/// vocabularies, which spell every byte as a printable character.
+fn byte_level_decoder() -> HashMap<char, u8> {
+    let printable = |b: u8| matches!(b, b'!'..=b'~' | 0xA1..=0xAC | 0xAE..=0xFF);
+    let mut decoder = HashMap::with_capacity(256);
+    let mut next = 256u32;
+    for b in 0..=255u8 {
+        let c = if printable(b) {
+            char::from(b)
+        } else {
+            let c = char::from_u32(next).expect("valid code point");
+            next += 1;
+            c
+        };
+        decoder.insert(c, b);
+    }
+    decoder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tiktoken_vocab(tokens: &[&str]) -> String {
+        let engine = base64::engine::general_purpose::STANDARD;
+        tokens
+            .iter()
+            .enumerate()
+            .map(|(rank, token)| format!("{} {}\n", engine.encode(token), rank))
+            .collect()
+    }
+
+    #[test]
+    fn test_tiktoken_bpe_merges_by_rank() {
+        let vocab = tiktoken_vocab(&["a", "b", "c", " ", "ab", "abc", " a"]);
+        let tokenizer = BpeTokenizer::from_tiktoken(&vocab, GPT2_PATTERN).unwrap();
+
+        // "abc" is a whole token. In " abc", "ab" outranks " a", so the piece
+        // ends up as " " + "abc".
+        assert_eq!(tokenizer.count_tokens("abc"), 1);
+        assert_eq!(tokenizer.count_tokens(" abc"), 2);
+        assert_eq!(tokenizer.count_tokens("abc abc"), 3);
+    }
+
+    #[test]
+    fn test_long_piece_merges() {
+        let vocab = tiktoken_vocab(&["a", "b", "ab", "abab"]);
+        let tokenizer = BpeTokenizer::from_tiktoken(&vocab, GPT2_PATTERN).unwrap();
+        let text = "ab".repeat(50_001);
+        assert_eq!(tokenizer.count_tokens(&text), 25_001);
+    }
+
+    #[test]
+    fn test_backtrack_limit_does_not_hang() {
+        let vocab = tiktoken_vocab(&["a", " ", "\t"]);
+        let tokenizer = BpeTokenizer::from_tiktoken(&vocab, GPT2_PATTERN).unwrap();
+        let text = " \t".repeat(1_000_000) + "a";
+        let count = tokenizer.count_tokens(&text);
+        assert!(count > 0 && count <= text.len());
+    }
+
+    #[test]
+    fn test_tiktoken_invalid_line() {
+        let result = BpeTokenizer::from_tiktoken("not-base64!! x\n", CL100K_PATTERN);
+        assert!(matches!(result, Err(TokenizerError::Vocabulary(_))));
+    }
+
+    #[test]
+    fn test_hf_byte_level_bpe() {
+        let json = r#"{
+            "pre_tokenizer": {"type": "ByteLevel", "add_prefix_space": false},
+            "model": {
+                "type": "BPE",
+                "vocab": {"a": 0, "b": 1, "c": 2, "Ġ": 3, "ab": 4, "abc": 5, "Ġabc": 6},
+                "merges": ["a b", ["ab", "c"], "Ġ abc"]
+            }
+        }"#;
+        let tokenizer = BpeTokenizer::from_hf_json(json).unwrap();
+        assert_eq!(tokenizer.count_tokens("abc"), 1);
+        assert_eq!(tokenizer.count_tokens("abc abc"), 2);
+        assert_eq!(tokenizer.count_tokens("abcab"), 2);
+    }
+
+    #[test]
+    fn test_hf_split_pattern_from_sequence() {
+        let pre = serde_json::json!({
+            "type": "Sequence",
+            "pretokenizers": [
+                {"type": "Split", "pattern": {"Regex": "\\d"}, "behavior": "Isolated"},
+                {"type": "ByteLevel"}
+            ]
+        });
+        assert_eq!(hf_split_pattern(&pre).as_deref(), Some("\\d"));
+    }
+
+    #[test]
+    fn test_builtin_patterns_compile() {
+        for pattern in [GPT2_PATTERN, CL100K_PATTERN, O200K_PATTERN] {
+            assert!(compile(pattern).is_ok());
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Token counting for chunk budgets and truncation.
+//!
+//! Character counts are a poor stand-in for model context limits, so anything
+//! that sizes text against a budget goes through a [`Tokenizer`]. Use
+//! [`BpeTokenizer`] with the vocabulary of the target model for exact counts,
+//! or one of the cheap estimators when no vocabulary is at hand.
+
+mod bpe;
+
+use std::fmt;
+
+pub use bpe::{BpeTokenizer, CL100K_PATTERN, GPT2_PATTERN, O200K_PATTERN};
+
+/// Error type for loading tokenizer vocabularies.
+#[derive(Debug, thiserror::Error)]
+pub enum TokenizerError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid vocabulary: {0}")]
+    Vocabulary(String),
+    #[error("Invalid pre-tokenizer pattern: {0}")]
+    Pattern(String),
+}
+
+/// Counts tokens the way a particular model would.
+pub trait Tokenizer: Send + Sync {
+    fn count_tokens(&self, text: &str) -> usize;
+}
+
+impl fmt::Debug for dyn Tokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Tokenizer")
+    }
+}
+
+/// Counts whitespace-separated words.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WhitespaceTokenizer;
+
+impl Tokenizer for WhitespaceTokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        text.split_whitespace().count()
+    }
+}
+
+/// Estimates tokens from the character count.
+///
+/// Four characters per token is a good average for English with GPT-style
+/// vocabularies; CJK text is closer to one.
+#[derive(Debug, Clone, Copy)]
+pub struct CharHeuristicTokenizer {
+    pub chars_per_token: f64,
+}
+
+impl Default for CharHeuristicTokenizer {
+    fn default() -> Self {
+        Self {
+            chars_per_token: 4.0,
+        }
+    }
+}
+
+impl Tokenizer for CharHeuristicTokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        let chars = text.chars().count() as f64;
+        (chars / self.chars_per_token.max(f64::MIN_POSITIVE)).ceil() as usize
+    }
+}
+
+/// The longest prefix of `text` that is at most `max_tokens` long, cut at a
+/// character boundary.
+pub fn truncate_to_tokens<'a>(
+    text: &'a str,
+    max_tokens: usize,
+    tokenizer: &dyn Tokenizer,
+) -> &'a str {
+    if tokenizer.count_tokens(text) <= max_tokens {
+        return text;
+    }
+
+    // Token counts grow with the prefix, so binary search over the character
+    // boundaries for the longest prefix that fits. `lo` always fits, `hi` never.
+    let ends: Vec<usize> = text
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(text.len()))
+        .collect();
+    let (mut lo, mut hi) = (0, ends.len() - 1);
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        if tokenizer.count_tokens(&text[..ends[mid]]) <= max_tokens {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+
+    &text[..ends[lo]]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_whitespace_tokenizer() {
+        assert_eq!(WhitespaceTokenizer.count_tokens("  one two\nthree "), 3);
+    }
+
+    #[test]
+    fn test_char_heuristic_tokenizer() {
+        let tokenizer = CharHeuristicTokenizer::default();
+        assert_eq!(tokenizer.count_tokens(""), 0);
+        assert_eq!(tokenizer.count_tokens("abcde"), 2);
+    }
+
+    #[test]
+    fn test_truncate_to_tokens() {
+        let text = "one two three four";
+        assert_eq!(
+            truncate_to_tokens(text, 2, &WhitespaceTokenizer),
+            "one two "
+        );
+        assert_eq!(truncate_to_tokens(text, 10, &WhitespaceTokenizer), text);
+        assert_eq!(truncate_to_tokens(text, 0, &WhitespaceTokenizer), "");
+    }
+}